@@ -0,0 +1,401 @@
+use crate::models::{MarketData, Position, Trade};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const YEAR_SECONDS: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+const DAY_SECONDS: f64 = 24.0 * 60.0 * 60.0;
+const DEFAULT_PERIODS_PER_YEAR: f64 = 252.0;
+
+/// Return, risk and trade statistics of a backtest
+///
+/// Ratios are computed from bar-to-bar returns of the equity curve and annualized with the
+/// number of bars the curve actually has per year, so daily bars of a 24/7 market count 365
+/// periods, weekday-only daily bars about 252 and hourly bars about 24 times as many. The
+/// risk-free rate is taken as zero. Trade statistics are over closed round trips.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub periods_per_year: f64,
+    pub total_return: f64,
+    pub cagr: f64,
+    pub annual_volatility: f64,
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub calmar_ratio: f64,
+    pub omega_ratio: Option<f64>, // None when no bar lost money
+    pub max_drawdown: f64,
+    pub max_drawdown_days: f64, // Longest stretch below a previous equity peak
+    pub win_rate: f64,
+    pub profit_factor: Option<f64>, // None when no round trip lost money
+    pub expectancy: f64, // Average P&L per round trip
+    pub avg_win: f64,
+    pub avg_loss: f64, // As a positive amount
+    pub max_consecutive_losses: usize,
+    pub exposure_time: f64, // Fraction of bars with an open position
+    pub turnover: f64, // Traded notional per year as a multiple of average equity
+    pub benchmark: Option<BenchmarkStats>,
+}
+
+/// Strategy returns regressed on a benchmark's returns over the bars both have
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkStats {
+    pub symbol: String,
+    pub benchmark_return: f64,
+    pub beta: f64,
+    pub alpha: f64, // Annualized
+    pub correlation: f64,
+}
+
+// Statistics of a run from its ledger
+pub fn analyze(initial_capital: f64, equity_curve: &[(DateTime<Utc>, f64)], positions: &[Position], trades: &[Trade]) -> PerformanceReport {
+    let periods_per_year = periods_per_year(equity_curve.iter().map(|(t, _)| *t));
+    let returns = bar_returns(equity_curve);
+    let years = span_seconds(equity_curve) / YEAR_SECONDS;
+    let final_equity = equity_curve.last().map_or(initial_capital, |p| p.1);
+
+    let total_return = if initial_capital > 0.0 { final_equity / initial_capital - 1.0 } else { 0.0 };
+    let cagr = if years > 0.0 && total_return > -1.0 { (1.0 + total_return).powf(1.0 / years) - 1.0 } else { 0.0 };
+    let (max_drawdown, max_drawdown_days) = drawdowns(equity_curve);
+
+    let mean = mean(&returns);
+    let volatility = std_dev(&returns);
+    let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len().max(1) as f64).sqrt();
+    let gains: f64 = returns.iter().filter(|r| **r > 0.0).sum();
+    let losses: f64 = -returns.iter().filter(|r| **r < 0.0).sum::<f64>();
+
+    let wins: Vec<f64> = positions.iter().map(|p| p.profit_loss).filter(|pnl| *pnl > 0.0).collect();
+    let losing: Vec<f64> = positions.iter().map(|p| p.profit_loss).filter(|pnl| *pnl <= 0.0).map(f64::abs).collect();
+    let gross_profit: f64 = wins.iter().sum();
+    let gross_loss: f64 = losing.iter().sum();
+
+    let notional: f64 = trades.iter().map(|t| t.price * t.size).sum();
+    let average_equity = mean_of(equity_curve.iter().map(|p| p.1));
+
+    PerformanceReport {
+        periods_per_year,
+        total_return,
+        cagr,
+        annual_volatility: volatility * periods_per_year.sqrt(),
+        sharpe_ratio: ratio(mean, volatility) * periods_per_year.sqrt(),
+        sortino_ratio: ratio(mean, downside) * periods_per_year.sqrt(),
+        calmar_ratio: ratio(cagr, max_drawdown),
+        omega_ratio: (losses > 0.0).then(|| gains / losses),
+        max_drawdown,
+        max_drawdown_days,
+        win_rate: ratio(wins.len() as f64, positions.len() as f64),
+        profit_factor: (gross_loss > 0.0).then(|| gross_profit / gross_loss),
+        expectancy: ratio(gross_profit - gross_loss, positions.len() as f64),
+        avg_win: ratio(gross_profit, wins.len() as f64),
+        avg_loss: ratio(gross_loss, losing.len() as f64),
+        max_consecutive_losses: max_consecutive_losses(positions),
+        exposure_time: exposure_time(equity_curve, positions),
+        turnover: if years > 0.0 { ratio(notional, average_equity) / years } else { 0.0 },
+        benchmark: None,
+    }
+}
+
+// Beta, alpha and correlation against `benchmark`, matched on the equity curve's timestamps
+pub fn benchmark_stats(equity_curve: &[(DateTime<Utc>, f64)], periods_per_year: f64, benchmark: &MarketData) -> Option<BenchmarkStats> {
+    let closes: HashMap<DateTime<Utc>, f64> = benchmark.candles.iter().map(|c| (c.timestamp, c.close)).collect();
+    let matched: Vec<(f64, f64)> = equity_curve.iter()
+        .filter_map(|(timestamp, equity)| closes.get(timestamp).map(|close| (*equity, *close)))
+        .collect();
+
+    let (strategy, market): (Vec<f64>, Vec<f64>) = matched.windows(2)
+        .filter(|w| w[0].0 > 0.0 && w[0].1 > 0.0)
+        .map(|w| (w[1].0 / w[0].0 - 1.0, w[1].1 / w[0].1 - 1.0))
+        .unzip();
+    if market.len() < 2 {
+        return None;
+    }
+
+    let (mean_strategy, mean_market) = (mean(&strategy), mean(&market));
+    let covariance = strategy.iter().zip(&market)
+        .map(|(s, m)| (s - mean_strategy) * (m - mean_market))
+        .sum::<f64>() / (market.len() - 1) as f64;
+    let beta = ratio(covariance, std_dev(&market).powi(2));
+    let (first, last) = (matched.first()?, matched.last()?);
+
+    Some(BenchmarkStats {
+        symbol: benchmark.symbol.clone(),
+        benchmark_return: ratio(last.1, first.1) - 1.0,
+        beta,
+        alpha: (mean_strategy - beta * mean_market) * periods_per_year,
+        correlation: ratio(covariance, std_dev(&strategy) * std_dev(&market)),
+    })
+}
+
+/// Bars per year in a series with these timestamps, from its length and the time it spans
+///
+/// Falls back to 252 trading days when the series spans no time at all.
+pub fn periods_per_year(timestamps: impl IntoIterator<Item = DateTime<Utc>>) -> f64 {
+    let mut timestamps = timestamps.into_iter();
+    let Some(first) = timestamps.next() else {
+        return DEFAULT_PERIODS_PER_YEAR;
+    };
+    let (bars, last) = timestamps.fold((1, first), |(bars, _), t| (bars + 1, t));
+
+    let years = (last - first).num_seconds() as f64 / YEAR_SECONDS;
+    if years > 0.0 {
+        (bars - 1) as f64 / years
+    } else {
+        DEFAULT_PERIODS_PER_YEAR
+    }
+}
+
+fn span_seconds(equity_curve: &[(DateTime<Utc>, f64)]) -> f64 {
+    match (equity_curve.first(), equity_curve.last()) {
+        (Some(first), Some(last)) => (last.0 - first.0).num_seconds() as f64,
+        _ => 0.0,
+    }
+}
+
+fn bar_returns(equity_curve: &[(DateTime<Utc>, f64)]) -> Vec<f64> {
+    equity_curve.windows(2)
+        .filter(|w| w[0].1 > 0.0)
+        .map(|w| w[1].1 / w[0].1 - 1.0)
+        .collect()
+}
+
+// Maximum drawdown and the longest time in days spent below a previous peak, counting a
+// drawdown that has not recovered by the end of the curve
+fn drawdowns(equity_curve: &[(DateTime<Utc>, f64)]) -> (f64, f64) {
+    let Some(&(first_time, first_equity)) = equity_curve.first() else {
+        return (0.0, 0.0);
+    };
+
+    let (mut peak, mut peak_time) = (first_equity, first_time);
+    let (mut max_drawdown, mut longest) = (0.0_f64, 0.0_f64);
+    let mut underwater = false;
+    for &(timestamp, equity) in equity_curve {
+        if equity >= peak {
+            if underwater {
+                longest = longest.max((timestamp - peak_time).num_seconds() as f64);
+                underwater = false;
+            }
+            peak = equity;
+            peak_time = timestamp;
+        } else if peak > 0.0 {
+            max_drawdown = max_drawdown.max((peak - equity) / peak);
+            underwater = true;
+        }
+    }
+    if let (true, Some(&(last_time, _))) = (underwater, equity_curve.last()) {
+        longest = longest.max((last_time - peak_time).num_seconds() as f64);
+    }
+
+    (max_drawdown, longest / DAY_SECONDS)
+}
+
+// Longest run of round trips that lost money, in the order they were closed
+fn max_consecutive_losses(positions: &[Position]) -> usize {
+    let mut run = 0;
+    let mut longest = 0;
+    for position in positions {
+        if position.profit_loss <= 0.0 {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    longest
+}
+
+// Fraction of the curve's bars at which some round trip was open
+fn exposure_time(equity_curve: &[(DateTime<Utc>, f64)], positions: &[Position]) -> f64 {
+    let mut intervals: Vec<(DateTime<Utc>, DateTime<Utc>)> = positions.iter()
+        .filter_map(|p| p.exit_time.map(|exit| (p.entry_time, exit)))
+        .collect();
+    intervals.sort();
+
+    // Sweep the bars and the intervals sorted by entry together
+    let mut next = 0;
+    let mut open_until: Option<DateTime<Utc>> = None;
+    let mut exposed = 0;
+    for (timestamp, _) in equity_curve {
+        while next < intervals.len() && intervals[next].0 <= *timestamp {
+            open_until = open_until.max(Some(intervals[next].1));
+            next += 1;
+        }
+        if open_until.is_some_and(|until| until > *timestamp) {
+            exposed += 1;
+        }
+    }
+    ratio(exposed as f64, equity_curve.len() as f64)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    mean_of(values.iter().copied())
+}
+
+fn mean_of(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    ratio(sum, count as f64)
+}
+
+// Sample standard deviation
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+// `a / b`, or 0.0 when `b` is zero
+fn ratio(a: f64, b: f64) -> f64 {
+    if b == 0.0 { 0.0 } else { a / b }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{day, flat};
+    use crate::models::{Candle, TradeDirection};
+    use chrono::Duration;
+
+    // Half of a 365.25-day year
+    const HALF_YEAR: i64 = 4383;
+
+    // Equity points `step` hours apart from `day(0)`
+    fn curve(step: i64, equity: &[f64]) -> Vec<(DateTime<Utc>, f64)> {
+        equity.iter().enumerate().map(|(i, e)| (at(step, i), *e)).collect()
+    }
+
+    fn at(step: i64, i: usize) -> DateTime<Utc> {
+        day(0) + Duration::hours(step * i as i64)
+    }
+
+    fn round_trip(entry: DateTime<Utc>, exit: DateTime<Utc>, profit_loss: f64) -> Position {
+        Position {
+            symbol: "TEST".to_string(),
+            direction: TradeDirection::Long,
+            entry_price: 100.0,
+            current_price: 100.0,
+            size: 1.0,
+            entry_time: entry,
+            exit_time: Some(exit),
+            exit_price: Some(100.0),
+            costs: 0.0,
+            profit_loss,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn return_ratios_over_two_years() {
+        // Returns of +20%, -25%, +20% and +1/3 every half year
+        let equity = curve(HALF_YEAR, &[100.0, 120.0, 90.0, 108.0, 144.0]);
+        let report = analyze(100.0, &equity, &[], &[]);
+
+        assert_close(report.periods_per_year, 2.0);
+        assert_close(report.total_return, 0.44);
+        assert_close(report.cagr, 0.2);
+        assert_close(report.max_drawdown, 0.25);
+        assert_close(report.calmar_ratio, 0.2 / 0.25);
+
+        let mean = (0.2 - 0.25 + 0.2 + 1.0 / 3.0) / 4.0;
+        let downside = (0.25_f64.powi(2) / 4.0).sqrt();
+        assert_close(report.sortino_ratio, mean / downside * 2.0_f64.sqrt());
+        assert_close(report.omega_ratio.unwrap(), (0.2 + 0.2 + 1.0 / 3.0) / 0.25);
+
+        // Below the peak of 120 from the second point until the last
+        assert_close(report.max_drawdown_days, 3.0 * 182.625);
+    }
+
+    #[test]
+    fn unrecovered_drawdown_runs_to_the_end() {
+        let report = analyze(100.0, &curve(24, &[100.0, 110.0, 99.0, 105.0]), &[], &[]);
+
+        assert_close(report.max_drawdown, 0.1);
+        assert_close(report.max_drawdown_days, 2.0);
+        assert!(report.omega_ratio.is_some());
+        assert!(analyze(100.0, &curve(24, &[100.0, 110.0]), &[], &[]).omega_ratio.is_none());
+    }
+
+    #[test]
+    fn trade_statistics_over_round_trips() {
+        let equity = curve(24, &[100.0, 100.0]);
+        let positions: Vec<Position> = [10.0, -5.0, -5.0, 20.0, -10.0].iter()
+            .map(|pnl| round_trip(day(0), day(1), *pnl))
+            .collect();
+        let report = analyze(100.0, &equity, &positions, &[]);
+
+        assert_close(report.win_rate, 0.4);
+        assert_close(report.profit_factor.unwrap(), 30.0 / 20.0);
+        assert_close(report.expectancy, 10.0 / 5.0);
+        assert_close(report.avg_win, 15.0);
+        assert_close(report.avg_loss, 20.0 / 3.0);
+        assert_eq!(report.max_consecutive_losses, 2);
+
+        let winners = [round_trip(day(0), day(1), 5.0)];
+        assert!(analyze(100.0, &equity, &winners, &[]).profit_factor.is_none());
+    }
+
+    #[test]
+    fn exposure_and_turnover() {
+        let equity = curve(HALF_YEAR, &[100.0, 120.0, 90.0, 108.0, 144.0]);
+        // Open over the first two bars (twice, overlapping) and the fourth
+        let positions = [
+            round_trip(at(HALF_YEAR, 0), at(HALF_YEAR, 2), 1.0),
+            round_trip(at(HALF_YEAR, 1), at(HALF_YEAR, 2), 1.0),
+            round_trip(at(HALF_YEAR, 3), at(HALF_YEAR, 4), 1.0),
+        ];
+        // 449.6 traded over two years against an average equity of 112.4
+        let trades = [Trade {
+            timestamp: at(HALF_YEAR, 1),
+            symbol: "TEST".to_string(),
+            direction: TradeDirection::Long,
+            price: 112.4,
+            size: 4.0,
+            costs: 0.0,
+        }];
+        let report = analyze(100.0, &equity, &positions, &trades);
+
+        assert_close(report.exposure_time, 3.0 / 5.0);
+        assert_close(report.turnover, 2.0);
+    }
+
+    #[test]
+    fn benchmark_is_matched_on_the_curve_timestamps() {
+        // Strategy returns are twice the benchmark's plus 1% a bar
+        let equity = curve(24, &[100.0, 121.0, 98.01, 118.5921]);
+        let mut candles: Vec<_> = [100.0, 110.0, 99.0, 108.9].iter().enumerate()
+            .map(|(n, close)| flat(n as i64, *close))
+            .collect();
+        // A bar the strategy never saw must not enter the regression
+        candles.insert(2, Candle { timestamp: day(1) + Duration::hours(12), ..flat(1, 500.0) });
+        let benchmark = MarketData::new("BENCH", "1D", candles);
+
+        let stats = benchmark_stats(&equity, 252.0, &benchmark).unwrap();
+        assert_close(stats.benchmark_return, 0.089);
+        assert_close(stats.beta, 2.0);
+        assert_close(stats.alpha, 0.01 * 252.0);
+        assert_close(stats.correlation, 1.0);
+
+        let unrelated = MarketData::new("BENCH", "1D", vec![flat(10, 100.0), flat(11, 101.0), flat(12, 102.0)]);
+        assert!(benchmark_stats(&equity, 252.0, &unrelated).is_none());
+    }
+
+    #[test]
+    fn annualization_follows_the_bar_spacing() {
+        let equity = [100.0, 101.0, 99.0, 100.5, 100.0];
+        let daily = analyze(100.0, &curve(24, &equity), &[], &[]);
+        let hourly = analyze(100.0, &curve(1, &equity), &[], &[]);
+
+        assert_close(daily.periods_per_year, 365.25);
+        assert_close(hourly.periods_per_year, 24.0 * 365.25);
+        assert_close(hourly.annual_volatility, daily.annual_volatility * 24.0_f64.sqrt());
+        assert_close(hourly.sharpe_ratio, daily.sharpe_ratio * 24.0_f64.sqrt());
+
+        // Weekday bars over two weeks: 9 steps in 11 days
+        let weekdays = [0, 1, 2, 3, 4, 7, 8, 9, 10, 11].map(day);
+        assert_close(periods_per_year(weekdays), 9.0 / 11.0 * 365.25);
+        assert_close(periods_per_year([day(0)]), DEFAULT_PERIODS_PER_YEAR);
+        assert_close(periods_per_year([]), DEFAULT_PERIODS_PER_YEAR);
+    }
+}
@@ -1,204 +1,388 @@
+use crate::analytics;
+use crate::config::BacktestConfig;
+use crate::costs::CostModel;
 use crate::data;
-use crate::models::{BacktestResult, Trade, TradeDirection};
-use crate::strategies;
+use crate::matching::{Fill, MatchingEngine};
+use crate::models::{BacktestResult, Candle, MarketData, Order, PortfolioBacktestResult};
+use crate::portfolio::Portfolio;
+use crate::risk::RiskManager;
+use crate::strategies::{self, Strategy};
+use crate::utils::statistics;
 use std::collections::HashMap;
 use std::error::Error;
 use chrono::{DateTime, Utc};
 
-pub async fn run_backtest(strategy_name: &str) -> Result<BacktestResult, Box<dyn Error>> {
-    // Fetch historical data for backtesting
-    let symbol = "BTC/USD";
-    let start_date = "2023-01-01";
-    let end_date = "2023-12-31";
-    
-    println!("Fetching data for {} from {} to {}", symbol, start_date, end_date);
-    let market_data = data::fetch_historical_data(symbol, start_date, end_date).await?;
+pub async fn run_backtest(
+    strategy_name: &str,
+    params: &HashMap<String, f64>,
+    config: &BacktestConfig,
+) -> Result<BacktestResult, Box<dyn Error>> {
+    // Load historical data for backtesting
+    eprintln!("Fetching data for {} from {} to {}", config.symbol, config.start_date, config.end_date);
+    let market_data = data::load_market_data(config).await?;
     
     // Create strategy
-    let strategy = strategies::create_strategy(strategy_name);
-    println!("Running strategy: {}", strategy.name());
+    let mut strategy = strategies::create_strategy_with_params(strategy_name, params)?;
+    eprintln!("Running strategy: {}", strategy.name());
     
-    // Execute strategy on historical data
-    let trades = strategy.execute(&market_data)?;
-    println!("Generated {} trades", trades.len());
+    let mut result = run_strategy(strategy.as_mut(), &market_data, config);
+    eprintln!("Generated {} trades", result.trades.len());
+    if let Some(benchmark) = data::load_benchmark(config).await? {
+        set_benchmark(&mut result, &benchmark);
+    }
     
-    // Calculate performance metrics
-    let (total_profit_loss, winning_trades, losing_trades) = calculate_profit_loss(&trades);
-    let equity_curve = generate_equity_curve(&trades);
-    let sharpe_ratio = calculate_sharpe_ratio(&equity_curve);
-    let max_drawdown = calculate_max_drawdown(&equity_curve);
+    Ok(result)
+}
+
+/// Bar-by-bar event loop
+///
+/// For every candle: orders submitted on the previous bar are handed to the matching engine,
+/// which fills them from this bar's prices (market orders at the open), the ledger is marked
+/// to market at the close, and the strategy is asked for new orders.
+/// Positions still open after the last bar are closed at its close. When the data carries a
+/// pair leg, its bar at the same index is matched and marked the same way, so a pairs
+/// strategy can trade both symbols.
+pub fn run_strategy(strategy: &mut dyn Strategy, data: &MarketData, config: &BacktestConfig) -> BacktestResult {
+    run_strategy_from(strategy, data, config, 0)
+}
+
+/// Same event loop, but bars before `start_index` only warm the strategy up
+///
+/// The strategy sees every candle so its indicators are primed, while orders it emits
+/// before `start_index` are discarded and the equity curve starts at `start_index`.
+pub fn run_strategy_from(strategy: &mut dyn Strategy, data: &MarketData, config: &BacktestConfig, start_index: usize) -> BacktestResult {
+    let mut portfolio = Portfolio::new(config.initial_capital);
+    let mut equity_curve = Vec::with_capacity(data.candles.len().saturating_sub(start_index));
+    let mut engine = MatchingEngine::new(config.matching.clone());
+    let mut costs = CostModel::from_config(config);
+    let mut risk = RiskManager::new(config);
+    let mut pending_orders: Vec<Order> = Vec::new();
+    strategy.prepare(data);
     
-    // Additional metrics
-    let mut metrics = HashMap::new();
-    metrics.insert("win_rate".to_string(), winning_trades as f64 / trades.len() as f64);
-    metrics.insert("avg_trade_profit".to_string(), total_profit_loss / trades.len() as f64);
+    for (i, candle) in data.candles.iter().enumerate() {
+        if i < start_index {
+            for leg in legs(data) {
+                risk.observe(leg, i);
+            }
+            strategy.on_candle(data, i, &portfolio);
+            continue;
+        }
+        
+        // Orders from the previous bar start working at this bar's open. A pair leg opened
+        // together with the main symbol is sized per unit like it, keeping the hedge ratio.
+        let mut unit = None;
+        for leg in legs(data) {
+            let orders: Vec<Order> = pending_orders.extract_if(.., |o| o.symbol == leg.symbol).collect();
+            unit = submit_orders(&mut engine, &mut risk, &portfolio, orders.into_iter(), leg, i, unit);
+            let fills = engine.process_bar(&leg.symbol, &leg.candles[i]);
+            risk.record_fills(&fills);
+            apply_fills(&mut portfolio, &mut costs, fills, &leg.candles[i]);
+            portfolio.mark_to_market(&leg.symbol, leg.candles[i].close);
+        }
+        
+        equity_curve.push((candle.timestamp, portfolio.equity()));
+        for leg in legs(data) {
+            risk.on_bar_close(&mut engine, &portfolio, leg, i);
+        }
+        
+        pending_orders = strategy.on_candle(data, i, &portfolio);
+    }
     
-    Ok(BacktestResult {
-        strategy_name: strategy.name().to_string(),
-        total_trades: trades.len(),
-        winning_trades,
-        losing_trades,
-        total_profit_loss,
-        sharpe_ratio,
-        max_drawdown,
-        trades,
-        equity_curve,
-        metrics,
-    })
+    // Liquidate whatever is still open so every position counts as a round trip
+    if let Some(last) = data.candles.last() {
+        let last_bars: Vec<(&str, &Candle)> = legs(data)
+            .filter_map(|leg| leg.candles.last().map(|c| (leg.symbol.as_str(), c)))
+            .collect();
+        liquidate(&mut portfolio, &mut costs, &last_bars, last.timestamp);
+        if let Some(point) = equity_curve.last_mut() {
+            point.1 = portfolio.equity();
+        }
+    }
+    
+    let mut result = build_result(strategy.name(), portfolio, equity_curve);
+    add_order_metrics(&mut result, &engine);
+    risk.add_metrics(&mut result);
+    // Buy-and-hold of the traded symbol unless the caller sets another benchmark
+    set_benchmark(&mut result, data);
+    result
 }
 
-fn calculate_profit_loss(trades: &[Trade]) -> (f64, usize, usize) {
-    let mut total_profit_loss = 0.0;
-    let mut winning_trades = 0;
-    let mut losing_trades = 0;
-    
-    // In a real system, we would match opening and closing trades
-    // This is a simplified version that assumes alternating buy/sell
-    
-    let mut position: Option<(f64, f64)> = None; // (price, size)
-    
-    for trade in trades {
-        match trade.direction {
-            TradeDirection::Long => {
-                // Opening a long position or closing a short position
-                if let Some((entry_price, size)) = position {
-                    // Closing a short position
-                    let profit_loss = (entry_price - trade.price) * size - trade.costs;
-                    total_profit_loss += profit_loss;
-                    
-                    if profit_loss > 0.0 {
-                        winning_trades += 1;
-                    } else {
-                        losing_trades += 1;
-                    }
-                    
-                    position = None;
-                } else {
-                    // Opening a long position
-                    position = Some((trade.price, trade.size));
-                }
-            }
-            TradeDirection::Short => {
-                // Opening a short position or closing a long position
-                if let Some((entry_price, size)) = position {
-                    // Closing a long position
-                    let profit_loss = (trade.price - entry_price) * size - trade.costs;
-                    total_profit_loss += profit_loss;
-                    
-                    if profit_loss > 0.0 {
-                        winning_trades += 1;
-                    } else {
-                        losing_trades += 1;
-                    }
-                    
-                    position = None;
-                } else {
-                    // Opening a short position
-                    position = Some((trade.price, trade.size));
-                }
+// Compare the run against `benchmark`'s bars
+pub fn set_benchmark(result: &mut BacktestResult, benchmark: &MarketData) {
+    result.analytics.benchmark = analytics::benchmark_stats(&result.equity_curve, result.analytics.periods_per_year, benchmark);
+}
+
+// The series a single-strategy run trades: the main one and its pair leg, if any
+fn legs(data: &MarketData) -> impl Iterator<Item = &MarketData> {
+    std::iter::once(data).chain(data.pair.as_deref())
+}
+
+/// Strategy trading one instrument inside a portfolio backtest
+pub struct PortfolioLeg {
+    pub data: MarketData,
+    pub strategy: Box<dyn Strategy>,
+}
+
+/// Multi-symbol event loop over a shared ledger
+///
+/// The legs' candles are aligned on the union of their timestamps. At each timestamp every
+/// leg with a bar matches its orders against the bar and is marked at the close; legs
+/// without a bar keep their last mark. Strategies are then called for the legs that had a bar.
+/// Each leg is first put in time order with repeated timestamps dropped.
+pub fn run_portfolio_backtest(mut legs: Vec<PortfolioLeg>, config: &BacktestConfig) -> PortfolioBacktestResult {
+    // A leg's cursor only moves past a bar matching the current timestamp, so one bar out of
+    // order would stall the leg for the rest of the run
+    for leg in legs.iter_mut() {
+        let dropped = data::sort_candles(&mut leg.data.candles);
+        if dropped > 0 {
+            eprintln!("Dropped {} {} bars repeating an earlier timestamp", dropped, leg.data.symbol);
+        }
+    }
+
+    let mut timestamps: Vec<DateTime<Utc>> = legs.iter()
+        .flat_map(|leg| leg.data.candles.iter().map(|c| c.timestamp))
+        .collect();
+    timestamps.sort();
+    timestamps.dedup();
+    
+    let mut portfolio = Portfolio::new(config.initial_capital);
+    let mut engine = MatchingEngine::new(config.matching.clone());
+    let mut costs = CostModel::from_config(config);
+    let mut risk = RiskManager::new(config);
+    let mut equity_curve = Vec::with_capacity(timestamps.len());
+    let mut cursors = vec![0; legs.len()];
+    let mut pending_orders: Vec<Vec<Order>> = vec![Vec::new(); legs.len()];
+    let mut last_close: Vec<Option<f64>> = vec![None; legs.len()];
+    let mut returns: Vec<Vec<f64>> = vec![Vec::with_capacity(timestamps.len()); legs.len()];
+    let mut pnl: Vec<Vec<f64>> = vec![Vec::with_capacity(timestamps.len()); legs.len()];
+    for leg in legs.iter_mut() {
+        leg.strategy.prepare(&leg.data);
+    }
+    
+    for timestamp in &timestamps {
+        let mut active = Vec::new();
+        
+        for (k, leg) in legs.iter().enumerate() {
+            let Some(candle) = leg.data.candles.get(cursors[k]).filter(|c| c.timestamp == *timestamp) else {
+                returns[k].push(0.0);
+                continue;
+            };
+            
+            submit_orders(&mut engine, &mut risk, &portfolio, pending_orders[k].drain(..), &leg.data, cursors[k], None);
+            let fills = engine.process_bar(&leg.data.symbol, candle);
+            risk.record_fills(&fills);
+            apply_fills(&mut portfolio, &mut costs, fills, candle);
+            portfolio.mark_to_market(&leg.data.symbol, candle.close);
+            risk.on_bar_close(&mut engine, &portfolio, &leg.data, cursors[k]);
+            
+            returns[k].push(last_close[k].map_or(0.0, |prev| candle.close / prev - 1.0));
+            last_close[k] = Some(candle.close);
+            active.push((k, cursors[k]));
+            cursors[k] += 1;
+        }
+        
+        equity_curve.push((*timestamp, portfolio.equity()));
+        for (k, leg) in legs.iter().enumerate() {
+            pnl[k].push(portfolio.symbol_pnl(&leg.data.symbol));
+        }
+        
+        for (k, index) in active {
+            let leg = &mut legs[k];
+            pending_orders[k] = leg.strategy.on_candle(&leg.data, index, &portfolio);
+        }
+    }
+    
+    // Liquidate at each symbol's last mark
+    if let Some(last) = timestamps.last() {
+        let last_bars: Vec<(&str, &Candle)> = legs.iter()
+            .filter_map(|leg| leg.data.candles.last().map(|c| (leg.data.symbol.as_str(), c)))
+            .collect();
+        liquidate(&mut portfolio, &mut costs, &last_bars, *last);
+        if let Some(point) = equity_curve.last_mut() {
+            point.1 = portfolio.equity();
+        }
+        for (k, leg) in legs.iter().enumerate() {
+            if let Some(point) = pnl[k].last_mut() {
+                *point = portfolio.symbol_pnl(&leg.data.symbol);
             }
         }
     }
     
-    (total_profit_loss, winning_trades, losing_trades)
+    let symbols: Vec<String> = legs.iter().map(|leg| leg.data.symbol.clone()).collect();
+    let contributions = symbols.iter().map(|s| (s.clone(), portfolio.symbol_pnl(s))).collect();
+    let pnl_changes: Vec<Vec<f64>> = pnl.iter()
+        .map(|series| series.windows(2).map(|w| w[1] - w[0]).collect())
+        .collect();
+    
+    let name = legs.iter()
+        .map(|leg| format!("{}:{}", leg.data.symbol, leg.strategy.name()))
+        .collect::<Vec<_>>()
+        .join(",");
+    
+    let mut result = build_result(&format!("Portfolio[{}]", name), portfolio, equity_curve);
+    add_order_metrics(&mut result, &engine);
+    risk.add_metrics(&mut result);
+    
+    PortfolioBacktestResult {
+        result,
+        symbols,
+        contributions,
+        return_correlation: statistics::correlation_matrix(&returns),
+        pnl_correlation: statistics::correlation_matrix(&pnl_changes),
+    }
 }
 
-fn generate_equity_curve(trades: &[Trade]) -> Vec<(DateTime<Utc>, f64)> {
-    let mut equity_curve = Vec::new();
-    let mut equity = 10000.0; // Starting capital
-    
-    // Add initial point
-    if !trades.is_empty() {
-        equity_curve.push((trades[0].timestamp, equity));
-    }
-    
-    let mut position: Option<(f64, f64)> = None; // (price, size)
-    
-    for trade in trades {
-        match trade.direction {
-            TradeDirection::Long => {
-                // Opening a long position or closing a short position
-                if let Some((entry_price, size)) = position {
-                    // Closing a short position
-                    let profit_loss = (entry_price - trade.price) * size - trade.costs;
-                    equity += profit_loss;
-                    position = None;
-                } else {
-                    // Opening a long position
-                    position = Some((trade.price, trade.size));
-                    equity -= trade.costs; // Subtract trading costs
-                }
-            }
-            TradeDirection::Short => {
-                // Opening a short position or closing a long position
-                if let Some((entry_price, size)) = position {
-                    // Closing a long position
-                    let profit_loss = (trade.price - entry_price) * size - trade.costs;
-                    equity += profit_loss;
-                    position = None;
-                } else {
-                    // Opening a short position
-                    position = Some((trade.price, trade.size));
-                    equity -= trade.costs; // Subtract trading costs
-                }
-            }
+// Hand new orders to the matching engine through the risk layer: exits it decided on go
+// first, orders opening a position from flat are sized, and every order is held to the limits.
+// Entry sizes are per unit of the strategy's order size: the sizing rule decides what one
+// unit is, unless `unit` fixes it. Returns the unit used when a position was opened.
+fn submit_orders(
+    engine: &mut MatchingEngine,
+    risk: &mut RiskManager,
+    portfolio: &Portfolio,
+    orders: impl Iterator<Item = Order>,
+    data: &MarketData,
+    index: usize,
+    unit: Option<f64>,
+) -> Option<f64> {
+    let price = data.candles[index].open;
+    let exits = risk.take_exits(&data.symbol);
+    let mut position = portfolio.position(&data.symbol).map_or(0.0, |p| p.direction.sign() * p.size);
+    let mut opened = None;
+    
+    for mut order in exits.into_iter().chain(orders) {
+        if position.abs() <= f64::EPSILON {
+            let unit_size = unit.unwrap_or_else(|| risk.entry_size(order.direction, portfolio, data, index, price));
+            order.size *= unit_size;
+            opened = Some(unit_size);
+        } else if order.direction.sign() * position < 0.0 {
+            // The bracket no longer matches once the position changes
+            risk.cancel_bracket(engine, &data.symbol);
+        }
+        
+        if !risk.limit_order(&mut order, position, portfolio, price) {
+            continue;
         }
         
-        // Record equity at each trade
-        equity_curve.push((trade.timestamp, equity));
+        position += order.direction.sign() * order.size;
+        engine.submit(order);
     }
-    
-    equity_curve
+    opened
 }
 
-fn calculate_sharpe_ratio(equity_curve: &[(DateTime<Utc>, f64)]) -> f64 {
-    if equity_curve.len() < 2 {
-        return 0.0;
+// Book matched fills through the run's cost model
+fn apply_fills(portfolio: &mut Portfolio, costs: &mut CostModel, fills: Vec<Fill>, candle: &Candle) {
+    for fill in fills {
+        portfolio.apply_fill(&costs.book(fill, candle));
     }
+}
+
+fn add_order_metrics(result: &mut BacktestResult, engine: &MatchingEngine) {
+    result.metrics.insert("partial_fills".to_string(), engine.partial_fills as f64);
+    result.metrics.insert("cancelled_orders".to_string(), engine.cancelled_orders as f64);
+}
+
+// Close every open position at its last marked price, the close of its symbol's last bar
+fn liquidate(portfolio: &mut Portfolio, costs: &mut CostModel, last_bars: &[(&str, &Candle)], timestamp: DateTime<Utc>) {
+    let open: Vec<Fill> = portfolio.positions.values()
+        .filter(|p| p.size > 0.0)
+        .map(|p| Fill {
+            order_id: 0,
+            symbol: p.symbol.clone(),
+            direction: p.direction.opposite(),
+            timestamp,
+            price: p.current_price,
+            size: p.size,
+            taker: true,
+            partial: false,
+        })
+        .collect();
     
-    // Calculate daily returns
-    let mut returns = Vec::new();
-    for i in 1..equity_curve.len() {
-        let daily_return = (equity_curve[i].1 - equity_curve[i-1].1) / equity_curve[i-1].1;
-        returns.push(daily_return);
+    for fill in open {
+        if let Some((_, candle)) = last_bars.iter().find(|(symbol, _)| *symbol == fill.symbol) {
+            portfolio.apply_fill(&costs.book(fill, candle));
+        }
     }
+}
+
+pub fn build_result(strategy_name: &str, portfolio: Portfolio, equity_curve: Vec<(DateTime<Utc>, f64)>) -> BacktestResult {
+    let positions = portfolio.closed_positions;
+    let winning_trades = positions.iter().filter(|p| p.profit_loss > 0.0).count();
+    let losing_trades = positions.len() - winning_trades;
     
-    // Calculate average return
-    let avg_return = returns.iter().sum::<f64>() / returns.len() as f64;
+    let final_equity = equity_curve.last().map(|p| p.1).unwrap_or(portfolio.initial_capital);
+    let total_profit_loss = final_equity - portfolio.initial_capital;
+    let analytics = analytics::analyze(portfolio.initial_capital, &equity_curve, &positions, &portfolio.trades);
     
-    // Calculate standard deviation of returns
-    let variance = returns.iter()
-        .map(|r| (r - avg_return).powi(2))
-        .sum::<f64>() / returns.len() as f64;
-    let std_dev = variance.sqrt();
+    // Additional metrics
+    let round_trips = positions.len().max(1) as f64;
+    let mut metrics = HashMap::new();
+    metrics.insert("win_rate".to_string(), winning_trades as f64 / round_trips);
+    metrics.insert("avg_trade_profit".to_string(), positions.iter().map(|p| p.profit_loss).sum::<f64>() / round_trips);
+    metrics.insert("realized_pnl".to_string(), portfolio.realized_pnl);
+    metrics.insert("total_costs".to_string(), portfolio.total_costs);
+    metrics.insert("final_equity".to_string(), final_equity);
     
-    // Calculate annualized Sharpe ratio (assuming 252 trading days)
-    // and risk-free rate of 0% for simplicity
-    if std_dev == 0.0 {
-        return 0.0;
+    BacktestResult {
+        strategy_name: strategy_name.to_string(),
+        total_trades: portfolio.trades.len(),
+        winning_trades,
+        losing_trades,
+        total_profit_loss,
+        sharpe_ratio: analytics.sharpe_ratio,
+        max_drawdown: analytics.max_drawdown,
+        trades: portfolio.trades,
+        positions,
+        equity_curve,
+        metrics,
+        analytics,
     }
-    
-    (avg_return / std_dev) * (252.0_f64).sqrt()
 }
 
-fn calculate_max_drawdown(equity_curve: &[(DateTime<Utc>, f64)]) -> f64 {
-    if equity_curve.len() < 2 {
-        return 0.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TradeDirection;
+    use crate::models::fixtures::flat;
+
+    // Buys one unit on its first bar and holds
+    struct BuyOnce {
+        bought: bool,
     }
-    
-    let mut max_drawdown = 0.0;
-    let mut peak = equity_curve[0].1;
-    
-    for (_, equity) in equity_curve {
-        if *equity > peak {
-            peak = *equity;
-        } else {
-            let drawdown = (peak - *equity) / peak;
-            if drawdown > max_drawdown {
-                max_drawdown = drawdown;
+
+    impl Strategy for BuyOnce {
+        fn name(&self) -> &str {
+            "BuyOnce"
+        }
+
+        fn on_candle(&mut self, data: &MarketData, _index: usize, _portfolio: &Portfolio) -> Vec<Order> {
+            if self.bought {
+                return Vec::new();
             }
+            self.bought = true;
+            vec![Order::market(&data.symbol, TradeDirection::Long, 1.0)]
         }
     }
-    
-    max_drawdown
-}
\ No newline at end of file
+
+    fn leg(symbol: &str, candles: Vec<Candle>) -> PortfolioLeg {
+        PortfolioLeg { data: MarketData::new(symbol, "1D", candles), strategy: Box::new(BuyOnce { bought: false }) }
+    }
+
+    #[test]
+    fn legs_with_repeated_or_swapped_bars_keep_trading() {
+        // Rises by one a day, with day 2 repeated at another price and days 3 and 4 swapped
+        let rising = [0, 1, 2, 2, 4, 3, 5, 6].iter().enumerate()
+            .map(|(i, &d)| flat(d, if i == 3 { 50.0 } else { 100.0 + d as f64 }))
+            .collect();
+        let legs = vec![leg("FLAT", (0..7).map(|d| flat(d, 100.0)).collect()), leg("UP", rising)];
+
+        let result = run_portfolio_backtest(legs, &BacktestConfig::default()).result;
+        let equity: Vec<f64> = result.equity_curve.iter().map(|p| p.1).collect();
+        assert_eq!(equity.len(), 7);
+
+        // Bought on day 1, so every later day marks one more unit of gain until the liquidation
+        for d in 2..6 {
+            assert!((equity[d] - equity[d - 1] - 1.0).abs() < 1e-9, "day {}: {:?}", d, equity);
+        }
+    }
+}
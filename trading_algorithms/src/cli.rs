@@ -0,0 +1,1323 @@
+use crate::analytics::PerformanceReport;
+use crate::backtest::{self, PortfolioLeg};
+use crate::config::{BacktestConfig, DataSource, SeriesConfig};
+use crate::costs::{CommissionConfig, CostModel, SlippageConfig};
+use crate::data::{self, DataSchema};
+use crate::execution::{self, AlmgrenChriss, ImpactModel, Trajectory};
+use crate::indicators::{self, IndicatorCheck, IndicatorValue};
+use crate::mock_server::{self, MockServerConfig};
+use crate::matching::MatchingEngine;
+use crate::models::{BacktestResult, MarketData, Order, OrderType, PortfolioBacktestResult, TimeInForce, TradeDirection};
+use crate::optimizer::{self, Objective, ParamRange};
+use crate::registry::{self, Configurable, Descriptor};
+use crate::report::{self, ReportFormat};
+use crate::resample::{self, Session};
+use crate::rules::{self, RuleStrategyConfig};
+use crate::store::{self, DataStore};
+use crate::strategies::{self, StrategyConfig};
+use crate::synthetic::{self, PriceModel, SyntheticConfig};
+use crate::tca::{self, TcaConfig, TcaResult};
+use crate::utils;
+use crate::walk_forward::{self, WalkForwardConfig, WalkForwardResult};
+use chrono::{DateTime, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tokio::net::TcpListener;
+
+#[derive(Parser)]
+#[command(name = "trading_algorithms", about = "Backtest trading strategies and compare execution algorithms")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Backtest a single strategy
+    Backtest {
+        #[command(flatten)]
+        strategy: StrategyArgs,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Work a parent order with execution algorithms and compare their fills
+    Execute {
+        /// Execution algorithm to run as name or name:key=value,... (repeatable)
+        #[arg(long = "algorithm", default_values = ["vwap", "twap", "is", "adaptive"], value_parser = registry::parse_spec)]
+        algorithms: Vec<(String, HashMap<String, f64>)>,
+        /// Parent order size
+        #[arg(long, default_value_t = 100.0)]
+        size: f64,
+        #[arg(long, value_enum, default_value_t = Side::Buy)]
+        side: Side,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Benchmark execution algorithms on randomized parent orders with transaction cost analysis
+    Tca {
+        /// Execution algorithm to run as name or name:key=value,... (repeatable; the first is the baseline)
+        #[arg(long = "algorithm", default_values = ["vwap", "twap", "is", "adaptive"], value_parser = registry::parse_spec)]
+        algorithms: Vec<(String, HashMap<String, f64>)>,
+        /// Number of randomized parent orders
+        #[arg(long, default_value_t = 200)]
+        orders: usize,
+        /// Bars each order is worked over on daily data (intraday data trades whole days)
+        #[arg(long, default_value_t = 10)]
+        window: usize,
+        /// Smallest order as a fraction of its window's volume
+        #[arg(long, default_value_t = 0.01)]
+        min_participation: f64,
+        /// Largest order as a fraction of its window's volume
+        #[arg(long, default_value_t = 0.1)]
+        max_participation: f64,
+        /// Seed for drawing the orders (--seed seeds synthetic data)
+        #[arg(long, default_value_t = 42)]
+        order_seed: u64,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the Almgren-Chriss efficient frontier of expected cost against risk for one order
+    Frontier {
+        /// Almgren-Chriss parameter override as key=value (repeatable; risk_aversion is swept)
+        #[arg(long = "param", value_parser = parse_param)]
+        params: Vec<(String, f64)>,
+        /// Parent order size
+        #[arg(long, default_value_t = 100.0)]
+        size: f64,
+        /// Index of the bar the order starts on (earlier bars calibrate the model)
+        #[arg(long, default_value_t = 100)]
+        bar: usize,
+        /// Bars the order is worked over
+        #[arg(long, default_value_t = 20)]
+        periods: usize,
+        /// Risk aversions on the frontier, log-spaced between the bounds after a risk-neutral 0
+        #[arg(long, default_value_t = 13)]
+        points: usize,
+        #[arg(long, default_value_t = 0.01)]
+        min_risk_aversion: f64,
+        #[arg(long, default_value_t = 10_000.0)]
+        max_risk_aversion: f64,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Backtest several strategies on the same data and compare them
+    Compare {
+        /// Strategy to include as name or name:key=value,... (repeatable)
+        #[arg(long = "strategy", required = true, value_parser = registry::parse_spec)]
+        strategies: Vec<(String, HashMap<String, f64>)>,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Backtest a strategy under several commission and slippage models
+    Costs {
+        #[command(flatten)]
+        strategy: StrategyArgs,
+        /// Commission model (repeatable): none, percent:<rate>, bps:<bps> or per_unit:<amount>
+        #[arg(long = "commission")]
+        commissions: Vec<CommissionConfig>,
+        /// Slippage model (repeatable): none, bps:<bps>, spread:<bps> or sqrt_impact:<coefficient>
+        #[arg(long = "slippage")]
+        slippages: Vec<SlippageConfig>,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Backtest strategies across several symbols on one shared portfolio
+    Portfolio {
+        /// Symbol to trade (repeatable)
+        #[arg(long = "symbol", required = true)]
+        symbols: Vec<String>,
+        /// Strategy per symbol as name or name:key=value,..., in the same order; a single
+        /// strategy trades every symbol
+        #[arg(long = "strategy", default_values = [strategies::DEFAULT_STRATEGY], value_parser = registry::parse_spec)]
+        strategies: Vec<(String, HashMap<String, f64>)>,
+        /// CSV file per symbol, in the same order (synthetic data when omitted)
+        #[arg(long = "data")]
+        data_files: Vec<PathBuf>,
+        /// Backtest config file (TOML or JSON); the flags below override it
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Start date (YYYY-MM-DD)
+        #[arg(long)]
+        start: Option<String>,
+        /// End date (YYYY-MM-DD)
+        #[arg(long)]
+        end: Option<String>,
+        /// Resample every symbol's bars to this timeframe
+        #[arg(long)]
+        timeframe: Option<String>,
+        #[arg(long)]
+        capital: Option<f64>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Search strategy parameters and rank the backtests by an objective
+    Optimize {
+        #[command(flatten)]
+        strategy: StrategyArgs,
+        /// Parameter range as name=min:max:step (repeatable)
+        #[arg(long = "range", required = true)]
+        ranges: Vec<ParamRange>,
+        /// sharpe, total_pnl or calmar
+        #[arg(long, default_value = "sharpe")]
+        objective: Objective,
+        #[arg(long, value_enum, default_value_t = SearchMethod::Grid)]
+        method: SearchMethod,
+        /// Number of parameter sets for random search
+        #[arg(long, default_value_t = 50)]
+        samples: usize,
+        /// Seed for random search (--seed seeds synthetic data)
+        #[arg(long, default_value_t = 42)]
+        search_seed: u64,
+        /// Number of rows in the results table
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Write a heatmap of the first two swept parameters to this PNG
+        #[arg(long)]
+        heatmap: Option<PathBuf>,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Optimize on rolling in-sample windows and evaluate out of sample
+    WalkForward {
+        #[command(flatten)]
+        strategy: StrategyArgs,
+        /// Parameter range as name=min:max:step (repeatable)
+        #[arg(long = "range", required = true)]
+        ranges: Vec<ParamRange>,
+        /// sharpe, total_pnl or calmar
+        #[arg(long, default_value = "sharpe")]
+        objective: Objective,
+        /// Bars in each in-sample (optimization) window
+        #[arg(long, default_value_t = 120)]
+        in_sample: usize,
+        /// Bars in each out-of-sample (evaluation) window
+        #[arg(long, default_value_t = 30)]
+        out_of_sample: usize,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Write seeded synthetic bars to a CSV file
+    Generate {
+        #[arg(long, default_value = "BTC/USD")]
+        symbol: String,
+        /// Start date (YYYY-MM-DD)
+        #[arg(long, default_value = "2023-01-01")]
+        start: String,
+        /// End date (YYYY-MM-DD)
+        #[arg(long, default_value = "2023-12-31")]
+        end: String,
+        /// Price model: gbm, garch, jump_diffusion or regime_switching
+        #[arg(long, default_value = "gbm")]
+        model: String,
+        /// Bar interval, e.g. 1m, 5m, 1h, 1D
+        #[arg(long, default_value = "1D")]
+        interval: String,
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        #[arg(long, default_value_t = 100.0)]
+        initial_price: f64,
+        #[arg(long, default_value = "synthetic.csv")]
+        output: PathBuf,
+    },
+    /// Serve synthetic history over a local OHLCV REST endpoint for offline testing
+    MockServer {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// First day of the served history (YYYY-MM-DD)
+        #[arg(long, default_value = "2023-01-01")]
+        start: String,
+        /// Last day of the served history (YYYY-MM-DD)
+        #[arg(long, default_value = "2023-12-31")]
+        end: String,
+        /// Most bars returned per request
+        #[arg(long, default_value_t = 500)]
+        max_page_size: usize,
+        /// Answer 429 to requests beyond this many per second
+        #[arg(long)]
+        rate_limit: Option<usize>,
+        /// Fraction of requests that fail with 503
+        #[arg(long, default_value_t = 0.0)]
+        failure_rate: f64,
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+    /// Manage the on-disk bar store
+    Store {
+        #[command(subcommand)]
+        action: StoreAction,
+    },
+    /// Aggregate a data file into a coarser timeframe and write it as CSV
+    Resample {
+        /// CSV or Parquet file with the source bars
+        path: PathBuf,
+        /// Target timeframe, e.g. 5m, 1h, 1D, 1W
+        #[arg(long)]
+        timeframe: String,
+        /// Minutes after midnight UTC at which the trading day starts
+        #[arg(long, default_value_t = 0)]
+        session_offset: i64,
+        #[arg(long, default_value = "resampled.csv")]
+        output: PathBuf,
+    },
+    /// Load a data file and report its inferred timeframe and validation issues
+    Inspect {
+        /// CSV or Parquet file to inspect
+        path: PathBuf,
+        /// TOML or JSON file with the data schema (column mapping, timestamp format)
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
+    /// Simulate a single order against the bars and report its fills
+    Order {
+        #[arg(long, value_enum, default_value_t = Side::Buy)]
+        side: Side,
+        #[arg(long, default_value_t = 1.0)]
+        size: f64,
+        #[arg(long = "type", value_enum, default_value_t = OrderKind::Market)]
+        order_type: OrderKind,
+        /// Limit price (limit and stop-limit orders)
+        #[arg(long)]
+        limit: Option<f64>,
+        /// Trigger price (stop and stop-limit orders)
+        #[arg(long)]
+        trigger: Option<f64>,
+        /// Trailing distance in price units (trailing-stop orders)
+        #[arg(long)]
+        trail: Option<f64>,
+        #[arg(long, value_enum, default_value_t = Tif::Gtc)]
+        tif: Tif,
+        /// Index of the bar the order starts working on
+        #[arg(long, default_value_t = 0)]
+        bar: usize,
+        #[command(flatten)]
+        data: DataArgs,
+    },
+    /// Print the streaming indicators on the last bar, or check them against the ta crate
+    Indicators {
+        /// Lookback of the indicators that take one
+        #[arg(long, default_value_t = 14)]
+        period: usize,
+        /// Compare every bar with the ta crate's implementations instead
+        #[arg(long)]
+        check: bool,
+        /// Bars skipped before comparing, while differently seeded averages converge
+        #[arg(long)]
+        burn_in: Option<usize>,
+        /// Largest relative error the check accepts
+        #[arg(long, default_value_t = 1e-6)]
+        tolerance: f64,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// List the strategies and execution algorithms with their parameters
+    List {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Backtest a strategy and plot its equity curve as a PNG
+    Plot {
+        #[command(flatten)]
+        strategy: StrategyArgs,
+        #[command(flatten)]
+        data: DataArgs,
+        #[arg(long, default_value = "equity_curve.png")]
+        output: PathBuf,
+    },
+    /// Backtest a strategy and write an HTML or SVG report with charts and metrics
+    Report {
+        #[command(flatten)]
+        strategy: StrategyArgs,
+        #[command(flatten)]
+        data: DataArgs,
+        /// Indicator to draw over the price chart, e.g. `ema(50)` or `bollinger(20, 2)` (repeatable)
+        #[arg(long = "overlay")]
+        overlays: Vec<String>,
+        /// Report file; `.html` for a page, `.svg` for a single image
+        #[arg(long, default_value = "report.html")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StoreAction {
+    /// Import every bar of a CSV or Parquet file
+    Import {
+        path: PathBuf,
+        #[arg(long)]
+        symbol: String,
+        /// Store directory
+        #[arg(long, default_value = "data/store")]
+        store: PathBuf,
+    },
+    /// List the stored series with their coverage
+    List {
+        /// Store directory
+        #[arg(long, default_value = "data/store")]
+        store: PathBuf,
+    },
+}
+
+#[derive(Args)]
+pub struct StrategyArgs {
+    /// Built-in strategy name, or a TOML/JSON rule strategy file [default: the config's
+    /// [strategy] section, else moving_average_crossover]
+    #[arg(long)]
+    pub strategy: Option<String>,
+    /// Strategy parameter override as key=value (repeatable)
+    #[arg(long = "param", value_parser = parse_param)]
+    pub params: Vec<(String, f64)>,
+}
+
+impl StrategyArgs {
+    // Strategy named on the command line, else the config's [strategy] section, else the
+    // default. The config's parameters only apply to the strategy it names; --param wins.
+    fn resolve(&self, config: &BacktestConfig) -> (String, HashMap<String, f64>) {
+        let configured = config.strategy.as_ref();
+        let name = self
+            .strategy
+            .clone()
+            .or_else(|| configured.map(|s| s.name.clone()))
+            .unwrap_or_else(|| strategies::DEFAULT_STRATEGY.to_string());
+
+        let mut params = configured.filter(|s| s.name == name).map(|s| s.params.clone()).unwrap_or_default();
+        params.extend(self.params.iter().cloned());
+        (name, params)
+    }
+}
+
+#[derive(Args, Default)]
+pub struct DataArgs {
+    /// Backtest config file (TOML or JSON); the flags below override it
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub symbol: Option<String>,
+    /// Start date (YYYY-MM-DD)
+    #[arg(long)]
+    pub start: Option<String>,
+    /// End date (YYYY-MM-DD)
+    #[arg(long)]
+    pub end: Option<String>,
+    /// CSV or Parquet file with OHLCV bars (column layout from the config's schema)
+    #[arg(long = "data")]
+    pub data_file: Option<PathBuf>,
+    /// Resample the bars to this timeframe before running, e.g. 1h or 1D
+    #[arg(long)]
+    pub timeframe: Option<String>,
+    /// Cache data files in this bar store directory instead of re-parsing them every run
+    #[arg(long)]
+    pub store: Option<PathBuf>,
+    #[arg(long)]
+    pub capital: Option<f64>,
+    /// Seed for synthetic data (ignored when loading a data file)
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Second symbol for pairs strategies
+    #[arg(long)]
+    pub pair: Option<String>,
+    /// CSV or Parquet file with the pair symbol's bars (the main data source when omitted)
+    #[arg(long)]
+    pub pair_data: Option<PathBuf>,
+    /// Symbol to measure beta and alpha against (default: buy-and-hold of the traded symbol)
+    #[arg(long)]
+    pub benchmark: Option<String>,
+    /// CSV or Parquet file with the benchmark's bars (the main data source when omitted)
+    #[arg(long)]
+    pub benchmark_data: Option<PathBuf>,
+}
+
+impl DataArgs {
+    // Start from the config file (or defaults) and apply flag overrides
+    pub fn to_config(&self) -> Result<BacktestConfig, Box<dyn Error>> {
+        let mut config = match &self.config {
+            Some(path) => BacktestConfig::from_file(path)?,
+            None => BacktestConfig::default(),
+        };
+
+        if let Some(symbol) = &self.symbol {
+            config.symbol = symbol.clone();
+        }
+        if let Some(start) = &self.start {
+            config.start_date = start.clone();
+        }
+        if let Some(end) = &self.end {
+            config.end_date = end.clone();
+        }
+        if let Some(path) = &self.data_file {
+            config.data_source = file_source(path, &config.data_source);
+        }
+        if let Some(timeframe) = &self.timeframe {
+            config.timeframe = Some(timeframe.clone());
+        }
+        if let Some(store) = &self.store {
+            config.store = Some(store.clone());
+        }
+        if let Some(capital) = self.capital {
+            config.initial_capital = capital;
+        }
+        if let (Some(seed), DataSource::Synthetic { generator }) = (self.seed, &mut config.data_source) {
+            generator.seed = seed;
+        }
+        if let Some(symbol) = &self.pair {
+            config.pair = Some(SeriesConfig { symbol: symbol.clone(), data_source: None });
+        }
+        if let Some(path) = &self.pair_data {
+            let pair = config.pair.as_mut().ok_or("--pair-data needs --pair or a [pair] section in the config")?;
+            pair.data_source = Some(file_source(path, &config.data_source));
+        }
+        if let Some(symbol) = &self.benchmark {
+            config.benchmark = Some(SeriesConfig { symbol: symbol.clone(), data_source: None });
+        }
+        if let Some(path) = &self.benchmark_data {
+            let benchmark = config.benchmark.as_mut().ok_or("--benchmark-data needs --benchmark or a [benchmark] section in the config")?;
+            benchmark.data_source = Some(file_source(path, &config.data_source));
+        }
+
+        Ok(config)
+    }
+}
+
+// Data source reading `path`, keeping any schema the config declared for its own data file
+fn file_source(path: &Path, current: &DataSource) -> DataSource {
+    let schema = match current {
+        DataSource::Csv { schema, .. } | DataSource::Parquet { schema, .. } => schema.clone(),
+        DataSource::Synthetic { .. } | DataSource::Rest(_) => DataSchema::default(),
+    };
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("parquet") => DataSource::Parquet { path: path.to_path_buf(), schema },
+        _ => DataSource::Csv { path: path.to_path_buf(), schema },
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SearchMethod {
+    Grid,
+    Random,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl From<Side> for TradeDirection {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => TradeDirection::Long,
+            Side::Sell => TradeDirection::Short,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OrderKind {
+    Market,
+    Limit,
+    Stop,
+    StopLimit,
+    TrailingStop,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Tif {
+    Gtc,
+    Ioc,
+    Fok,
+}
+
+impl From<Tif> for TimeInForce {
+    fn from(tif: Tif) -> Self {
+        match tif {
+            Tif::Gtc => TimeInForce::GoodTillCancel,
+            Tif::Ioc => TimeInForce::ImmediateOrCancel,
+            Tif::Fok => TimeInForce::FillOrKill,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CostSummary {
+    cost_model: String,
+    trades: usize,
+    total_costs: f64,
+    total_profit_loss: f64,
+    sharpe_ratio: f64,
+}
+
+#[derive(Serialize)]
+struct FrontierSummary {
+    symbol: String,
+    start: DateTime<Utc>,
+    order_size: f64,
+    periods: usize,
+    model: ImpactModel,
+    trajectories: Vec<Trajectory>,
+}
+
+#[derive(Serialize)]
+struct ExecutionSummary {
+    algorithm: String,
+    executed: f64,
+    avg_price: f64,
+    trades: usize,
+    total_cost: f64,
+}
+
+fn parse_param(s: &str) -> Result<(String, f64), String> {
+    let (key, value) = s.split_once('=').ok_or_else(|| format!("expected key=value, got '{}'", s))?;
+    let value = value.parse::<f64>().map_err(|e| format!("invalid value for '{}': {}", key, e))?;
+    Ok((key.to_string(), value))
+}
+
+pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Backtest { strategy, data, format } => {
+            let config = data.to_config()?;
+            let result = run_backtest(&strategy, &config).await?;
+            print_backtest(&result, format)?;
+        }
+        Command::Execute { algorithms, size, side, data, format } => {
+            let config = data.to_config()?;
+            run_execution(&algorithms, size, side.into(), &config, format).await?;
+        }
+        Command::Tca { algorithms, orders, window, min_participation, max_participation, order_seed, data, format } => {
+            let config = data.to_config()?;
+            let market_data = data::load_market_data(&config).await?;
+            let tca_config = TcaConfig { orders, window_bars: window, min_participation, max_participation, seed: order_seed };
+            let result = tca::run_tca(&algorithms, &market_data, &config, &tca_config)?;
+            print_tca(&result, &tca_config, format)?;
+        }
+        Command::Frontier { params, size, bar, periods, points, min_risk_aversion, max_risk_aversion, data, format } => {
+            if !(0.0 < min_risk_aversion && min_risk_aversion <= max_risk_aversion) {
+                return Err("Risk aversion range must satisfy 0 < min <= max".into());
+            }
+            let config = data.to_config()?;
+            let market_data = data::load_market_data(&config).await?;
+            let start = market_data.candles.get(bar).ok_or_else(|| {
+                format!("Bar {} is past the end of the data ({} bars)", bar, market_data.candles.len())
+            })?.timestamp;
+
+            let params = AlmgrenChriss::descriptor().validate(&params.into_iter().collect())?;
+            let algorithm = AlmgrenChriss::from_params(&params);
+            let model = algorithm.calibrate(&market_data, size, start, periods);
+            let step = if points > 1 { (max_risk_aversion / min_risk_aversion).ln() / (points - 1) as f64 } else { 0.0 };
+            let risk_aversions: Vec<f64> = std::iter::once(0.0)
+                .chain((0..points).map(|i| min_risk_aversion * (step * i as f64).exp()))
+                .collect();
+            let frontier = FrontierSummary {
+                symbol: market_data.symbol.clone(),
+                start,
+                order_size: size,
+                periods: periods.max(1),
+                model,
+                trajectories: algorithm.efficient_frontier(&model, size, periods, &risk_aversions),
+            };
+            print_frontier(&frontier, format)?;
+        }
+        Command::Compare { strategies, data, format } => {
+            let config = data.to_config()?;
+            let market_data = data::load_market_data(&config).await?;
+            let benchmark = data::load_benchmark(&config).await?;
+            let mut results = Vec::new();
+            for (name, params) in &strategies {
+                let mut run_config = config.clone();
+                apply_strategy_config(name, &mut run_config)?;
+                let mut strategy = strategies::create_strategy_with_params(name, params)?;
+                let mut result = backtest::run_strategy(strategy.as_mut(), &market_data, &run_config);
+                if let Some(benchmark) = &benchmark {
+                    backtest::set_benchmark(&mut result, benchmark);
+                }
+                results.push(result);
+            }
+            print_comparison(&results, format)?;
+        }
+        Command::Costs { strategy, commissions, slippages, data, format } => {
+            let mut config = data.to_config()?;
+            let (name, params) = strategy.resolve(&config);
+            apply_strategy_config(&name, &mut config)?;
+            let market_data = data::load_market_data(&config).await?;
+
+            // Every commission model against every slippage model; the config's own when none given
+            let commissions = if commissions.is_empty() { vec![config.commission.clone()] } else { commissions };
+            let slippages = if slippages.is_empty() { vec![config.slippage.clone()] } else { slippages };
+
+            let mut summaries = Vec::new();
+            for commission in &commissions {
+                for slippage in &slippages {
+                    let run_config = BacktestConfig { commission: commission.clone(), slippage: slippage.clone(), ..config.clone() };
+                    let mut run_strategy = strategies::create_strategy_with_params(&name, &params)?;
+                    let result = backtest::run_strategy(run_strategy.as_mut(), &market_data, &run_config);
+                    summaries.push(CostSummary {
+                        cost_model: CostModel::from_config(&run_config).name(),
+                        trades: result.total_trades,
+                        total_costs: result.metrics.get("total_costs").copied().unwrap_or(0.0),
+                        total_profit_loss: result.total_profit_loss,
+                        sharpe_ratio: result.sharpe_ratio,
+                    });
+                }
+            }
+            print_costs(&summaries, format)?;
+        }
+        Command::Portfolio { symbols, strategies, data_files, config, start, end, timeframe, capital, format } => {
+            if strategies.len() != 1 && strategies.len() != symbols.len() {
+                return Err("Pass either one --strategy or one per --symbol".into());
+            }
+            if !data_files.is_empty() && data_files.len() != symbols.len() {
+                return Err("Pass one --data file per --symbol".into());
+            }
+
+            let mut legs = Vec::new();
+            for (k, symbol) in symbols.iter().enumerate() {
+                let args = DataArgs {
+                    config: config.clone(),
+                    symbol: Some(symbol.clone()),
+                    start: start.clone(),
+                    end: end.clone(),
+                    data_file: data_files.get(k).cloned(),
+                    timeframe: timeframe.clone(),
+                    capital,
+                    ..DataArgs::default()
+                };
+                let leg_config = args.to_config()?;
+                if leg_config.pair.is_some() {
+                    return Err("Portfolio legs trade one symbol each; backtest pairs strategies with `backtest`".into());
+                }
+                let (name, params) = &strategies[k.min(strategies.len() - 1)];
+                legs.push(PortfolioLeg {
+                    data: data::load_market_data(&leg_config).await?,
+                    strategy: strategies::create_strategy_with_params(name, params)?,
+                });
+            }
+
+            let mut config = DataArgs { config, start, end, timeframe, capital, ..DataArgs::default() }.to_config()?;
+            for (name, _) in &strategies {
+                apply_strategy_config(name, &mut config)?;
+            }
+            let mut result = backtest::run_portfolio_backtest(legs, &config);
+            if let Some(benchmark) = data::load_benchmark(&config).await? {
+                backtest::set_benchmark(&mut result.result, &benchmark);
+            }
+            print_portfolio(&result, format)?;
+        }
+        Command::Optimize { strategy, ranges, objective, method, samples, search_seed, top, heatmap, data, format } => {
+            let mut config = data.to_config()?;
+            let (name, params) = strategy.resolve(&config);
+            apply_strategy_config(&name, &mut config)?;
+            let strategy = StrategyConfig { name, params };
+            let market_data = data::load_market_data(&config).await?;
+            let results = match method {
+                SearchMethod::Grid => optimizer::grid_search(&strategy, &ranges, &market_data, &config, objective)?,
+                SearchMethod::Random => optimizer::random_search(&strategy, &ranges, samples, search_seed, &market_data, &config, objective)?,
+            };
+
+            match format {
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&results)?),
+                OutputFormat::Text => optimizer::print_results_table(&results, top),
+            }
+
+            if let Some(path) = heatmap {
+                let [x, y, ..] = ranges.as_slice() else {
+                    return Err("A heatmap needs at least two --range parameters".into());
+                };
+                optimizer::plot_heatmap(&results, &x.name, &y.name, &path)?;
+                eprintln!("Heatmap written to {}", path.display());
+            }
+        }
+        Command::WalkForward { strategy, ranges, objective, in_sample, out_of_sample, data, format } => {
+            let mut config = data.to_config()?;
+            let (name, params) = strategy.resolve(&config);
+            apply_strategy_config(&name, &mut config)?;
+            let strategy = StrategyConfig { name, params };
+            let market_data = data::load_market_data(&config).await?;
+            let wf = WalkForwardConfig {
+                in_sample_bars: in_sample,
+                out_of_sample_bars: out_of_sample,
+                objective,
+            };
+            let mut result = walk_forward::run_walk_forward(&strategy, &ranges, &market_data, &config, &wf)?;
+            if let Some(benchmark) = data::load_benchmark(&config).await? {
+                backtest::set_benchmark(&mut result.result, &benchmark);
+            }
+            print_walk_forward(&result, format)?;
+        }
+        Command::Generate { symbol, start, end, model, interval, seed, initial_price, output } => {
+            let generator = SyntheticConfig {
+                seed,
+                model: PriceModel::from_name(&model)?,
+                initial_price,
+                bar_interval: interval,
+                ..SyntheticConfig::default()
+            };
+            let market_data = synthetic::generate(&symbol, &start, &end, &generator)?;
+            data::write_csv(&output, &market_data)?;
+            println!("Wrote {} {} bars for {} to {}", market_data.candles.len(), market_data.timeframe, symbol, output.display());
+        }
+        Command::MockServer { port, start, end, max_page_size, rate_limit, failure_rate, seed } => {
+            let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+            println!("Mock OHLCV server listening on http://{}/ohlcv", listener.local_addr()?);
+            let config = MockServerConfig {
+                start_date: start,
+                end_date: end,
+                generator: SyntheticConfig { seed, ..SyntheticConfig::default() },
+                max_page_size,
+                requests_per_second: rate_limit,
+                failure_rate,
+            };
+            mock_server::serve(listener, config).await?;
+        }
+        Command::Store { action } => match action {
+            StoreAction::Import { path, symbol, store } => {
+                let store = DataStore::new(&store);
+                let source = store::file_source(&path)?;
+                let loaded = match path.extension().and_then(|e| e.to_str()) {
+                    Some("parquet") => data::load_parquet_with_schema(&path, &symbol, &DataSchema::default())?,
+                    _ => data::load_csv_with_schema(&path, &symbol, &DataSchema::default())?,
+                };
+                let market_data = data::import_file(&store, &source, loaded)?;
+                println!("Imported {} {} bars for {}", market_data.candles.len(), market_data.timeframe, symbol);
+            }
+            StoreAction::List { store } => {
+                let series = DataStore::new(&store).list()?;
+                if series.is_empty() {
+                    println!("No stored series");
+                }
+                for info in series {
+                    println!("{} {}: {} bars from {}", info.symbol, info.timeframe, info.bars, info.source);
+                    for (start, end) in info.coverage {
+                        println!("  {} to {}", start, end);
+                    }
+                }
+            }
+        },
+        Command::Resample { path, timeframe, session_offset, output } => {
+            let symbol = path.file_stem().and_then(|s| s.to_str()).unwrap_or("data");
+            let (market_data, _) = match path.extension().and_then(|e| e.to_str()) {
+                Some("parquet") => data::load_parquet_with_schema(&path, symbol, &DataSchema::default())?,
+                _ => data::load_csv_with_schema(&path, symbol, &DataSchema::default())?,
+            };
+            let session = Session { offset_minutes: session_offset };
+            let resampled = resample::resample_with_session(&market_data, &timeframe, session)?;
+            data::write_csv(&output, &resampled)?;
+            println!(
+                "Resampled {} {} bars into {} {} bars, written to {}",
+                market_data.candles.len(),
+                market_data.timeframe,
+                resampled.candles.len(),
+                timeframe,
+                output.display()
+            );
+        }
+        Command::Inspect { path, schema } => {
+            let schema: DataSchema = match schema {
+                Some(schema_path) => {
+                    let contents = std::fs::read_to_string(&schema_path)?;
+                    match schema_path.extension().and_then(|e| e.to_str()) {
+                        Some("json") => serde_json::from_str(&contents)?,
+                        _ => toml::from_str(&contents)?,
+                    }
+                }
+                None => DataSchema::default(),
+            };
+            let symbol = path.file_stem().and_then(|s| s.to_str()).unwrap_or("data");
+            let (market_data, issues) = match path.extension().and_then(|e| e.to_str()) {
+                Some("parquet") => data::load_parquet_with_schema(&path, symbol, &schema)?,
+                _ => data::load_csv_with_schema(&path, symbol, &schema)?,
+            };
+
+            println!("File: {}", path.display());
+            println!("Bars: {}", market_data.candles.len());
+            if let (Some(first), Some(last)) = (market_data.candles.first(), market_data.candles.last()) {
+                println!("Range: {} to {}", first.timestamp, last.timestamp);
+            }
+            println!("Timeframe: {}", market_data.timeframe);
+            println!("Issues: {}", issues.len());
+            for issue in &issues {
+                println!("  {}", issue);
+            }
+        }
+        Command::Order { side, size, order_type, limit, trigger, trail, tif, bar, data } => {
+            let config = data.to_config()?;
+            let market_data = data::load_market_data(&config).await?;
+            let order_type = match order_type {
+                OrderKind::Market => OrderType::Market,
+                OrderKind::Limit => OrderType::Limit { price: limit.ok_or("--limit is required")? },
+                OrderKind::Stop => OrderType::Stop { trigger: trigger.ok_or("--trigger is required")? },
+                OrderKind::StopLimit => OrderType::StopLimit {
+                    trigger: trigger.ok_or("--trigger is required")?,
+                    limit: limit.ok_or("--limit is required")?,
+                },
+                OrderKind::TrailingStop => OrderType::TrailingStop { distance: trail.ok_or("--trail is required")? },
+            };
+            let order = Order::new(&market_data.symbol, side.into(), size, order_type).with_time_in_force(tif.into());
+            run_order(order, &market_data, bar, &config);
+        }
+        Command::Indicators { period, check, burn_in, tolerance, data, format } => {
+            let config = data.to_config()?;
+            let market_data = data::load_market_data(&config).await?;
+
+            if !check {
+                let values = indicators::latest_values(&market_data.candles, period);
+                print_indicators(&market_data, &values, format)?;
+                return Ok(());
+            }
+
+            // Exponential averages forget their seed by a factor (1 - 2 / (n + 1)) per bar
+            let burn_in = burn_in.unwrap_or(period.max(26) * 10);
+            let checks = indicators::check_against_ta(&market_data.candles, period, burn_in)?;
+            print_indicator_checks(&checks, tolerance, format)?;
+
+            let failed = checks.iter().filter(|c| c.compared == 0 || c.max_error > tolerance).count();
+            if failed > 0 {
+                return Err(format!("{} indicator(s) differ from the ta crate or were not compared", failed).into());
+            }
+        }
+        Command::List { format } => {
+            print_catalog(&strategies::available_strategies(), &execution::available_algorithms(), format)?;
+        }
+        Command::Plot { strategy, data, output } => {
+            let config = data.to_config()?;
+            let result = run_backtest(&strategy, &config).await?;
+            utils::plot_equity_curve(&result.equity_curve, &output)?;
+            println!("Equity curve for {} written to {}", result.strategy_name, output.display());
+        }
+        Command::Report { strategy, data, overlays, output } => {
+            let format = ReportFormat::from_path(&output)?;
+            let mut config = data.to_config()?;
+            let (name, params) = strategy.resolve(&config);
+            apply_strategy_config(&name, &mut config)?;
+
+            let market_data = data::load_market_data(&config).await?;
+            let overlays = report::overlays(&overlays, &market_data)?;
+            let mut strategy = strategies::create_strategy_with_params(&name, &params)?;
+            let mut result = backtest::run_strategy(strategy.as_mut(), &market_data, &config);
+            if let Some(benchmark) = data::load_benchmark(&config).await? {
+                backtest::set_benchmark(&mut result, &benchmark);
+            }
+
+            report::write_report(&result, &market_data, &overlays, format, &output)?;
+            println!("Report for {} written to {}", result.strategy_name, output.display());
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_backtest(args: &StrategyArgs, config: &BacktestConfig) -> Result<BacktestResult, Box<dyn Error>> {
+    let mut config = config.clone();
+    let (name, params) = args.resolve(&config);
+    apply_strategy_config(&name, &mut config)?;
+    backtest::run_backtest(&name, &params, &config).await
+}
+
+// A rule strategy file brings its own sizing and risk sections. Loading it here also makes
+// a broken file fail the command rather than fall back to the default strategy.
+fn apply_strategy_config(strategy: &str, config: &mut BacktestConfig) -> Result<(), Box<dyn Error>> {
+    if rules::is_rule_file(strategy) {
+        RuleStrategyConfig::from_file(Path::new(strategy))?.apply_to(config);
+    }
+    if strategies::needs_pair(strategy) && config.pair.is_none() {
+        return Err(format!("{} trades two symbols: pass --pair <symbol> or add a [pair] section to the config", strategy).into());
+    }
+    Ok(())
+}
+
+async fn run_execution(
+    algorithms: &[(String, HashMap<String, f64>)],
+    order_size: f64,
+    direction: TradeDirection,
+    config: &BacktestConfig,
+    format: OutputFormat,
+) -> Result<(), Box<dyn Error>> {
+    let market_data = data::load_market_data(config).await?;
+    let (Some(first), Some(last)) = (market_data.candles.first(), market_data.candles.last()) else {
+        return Err("No market data in the requested range".into());
+    };
+    let (start_time, end_time) = (first.timestamp, last.timestamp);
+
+    let mut summaries = Vec::new();
+    for (name, params) in algorithms {
+        let algorithm = execution::create_execution_algorithm(name, params)?;
+        let trades = algorithm.execute(&market_data, order_size, direction, start_time, Some(end_time), config)?;
+
+        let executed = trades.iter().map(|t| t.size).sum::<f64>();
+        let avg_price = trades.iter().map(|t| t.price * t.size).sum::<f64>() / executed;
+        summaries.push(ExecutionSummary {
+            algorithm: algorithm.name().to_string(),
+            executed,
+            avg_price,
+            trades: trades.len(),
+            total_cost: trades.iter().map(|t| t.costs).sum::<f64>(),
+        });
+    }
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&summaries)?),
+        OutputFormat::Text => {
+            println!("Execution Performance Comparison ({} {:.2} {})", market_data.symbol, order_size, match direction {
+                TradeDirection::Long => "buy",
+                TradeDirection::Short => "sell",
+            });
+            println!("===============================");
+            println!("Algorithm      | Executed   | Avg Price    | # Trades | Total Cost");
+            println!("-------------- | ---------- | ------------ | -------- | ----------");
+            for s in &summaries {
+                println!("{:<14} | {:<10.2} | ${:<11.2} | {:<8} | ${:.2}", s.algorithm, s.executed, s.avg_price, s.trades, s.total_cost);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Work one order through the matching engine from bar `start` until it is done
+fn run_order(order: Order, market_data: &MarketData, start: usize, config: &BacktestConfig) {
+    let mut engine = MatchingEngine::new(config.matching.clone());
+    let size = order.size;
+    let id = engine.submit(order);
+    let mut filled = 0.0;
+
+    println!("Order {} for {:.2} {}", id, size, market_data.symbol);
+    for candle in market_data.candles.iter().skip(start) {
+        for fill in engine.process_bar(&market_data.symbol, candle) {
+            filled += fill.size;
+            println!(
+                "  {} order {} filled {:.4} @ {:.4}{}",
+                fill.timestamp,
+                fill.order_id,
+                fill.size,
+                fill.price,
+                if fill.partial { " (partial)" } else { "" }
+            );
+        }
+        if engine.open_orders().is_empty() {
+            break;
+        }
+    }
+
+    let status = if engine.open_orders().is_empty() {
+        if engine.cancelled_orders > 0 { "cancelled" } else { "done" }
+    } else {
+        "still working"
+    };
+    println!("Filled {:.4} of {:.4}, {}", filled, size, status);
+}
+
+fn print_backtest(result: &BacktestResult, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(result)?),
+        OutputFormat::Text => {
+            println!("Backtest results:");
+            println!("  Strategy: {}", result.strategy_name);
+            println!("  Total trades: {}", result.total_trades);
+            println!("  Win rate: {:.2}%", result.metrics.get("win_rate").unwrap_or(&0.0) * 100.0);
+            println!("  Total P&L: ${:.2}", result.total_profit_loss);
+            println!("  Sharpe ratio: {:.2}", result.sharpe_ratio);
+            println!("  Max drawdown: {:.2}%", result.max_drawdown * 100.0);
+            if let (Some(stops), Some(targets)) = (result.metrics.get("stop_loss_exits"), result.metrics.get("take_profit_exits")) {
+                println!("  Risk exits: {} stop-loss, {} take-profit", stops, targets);
+            }
+            print_analytics(&result.analytics);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_analytics(a: &PerformanceReport) {
+    let optional = |value: Option<f64>| value.map_or("n/a".to_string(), |v| format!("{:.2}", v));
+    println!("Performance ({:.0} bars per year):", a.periods_per_year);
+    println!("  Total return: {:.2}%  CAGR: {:.2}%  Volatility: {:.2}%", a.total_return * 100.0, a.cagr * 100.0, a.annual_volatility * 100.0);
+    println!("  Sharpe: {:.2}  Sortino: {:.2}  Calmar: {:.2}  Omega: {}", a.sharpe_ratio, a.sortino_ratio, a.calmar_ratio, optional(a.omega_ratio));
+    println!("  Max drawdown: {:.2}% lasting up to {:.1} days", a.max_drawdown * 100.0, a.max_drawdown_days);
+    println!(
+        "  Profit factor: {}  Expectancy: ${:.2}  Avg win: ${:.2}  Avg loss: ${:.2}  Max consecutive losses: {}",
+        optional(a.profit_factor), a.expectancy, a.avg_win, a.avg_loss, a.max_consecutive_losses,
+    );
+    println!("  Exposure: {:.2}%  Turnover: {:.2}x per year", a.exposure_time * 100.0, a.turnover);
+    if let Some(b) = &a.benchmark {
+        println!(
+            "  vs {}: return {:.2}%, beta {:.2}, alpha {:.2}%, correlation {:.2}",
+            b.symbol, b.benchmark_return * 100.0, b.beta, b.alpha * 100.0, b.correlation,
+        );
+    }
+}
+
+fn print_portfolio(result: &PortfolioBacktestResult, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    if let OutputFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(result)?);
+        return Ok(());
+    }
+
+    print_backtest(&result.result, format)?;
+
+    println!();
+    println!("Symbol       | Net P&L contribution");
+    println!("------------ | --------------------");
+    for symbol in &result.symbols {
+        println!("{:<12} | ${:.2}", symbol, result.contributions.get(symbol).unwrap_or(&0.0));
+    }
+
+    for (title, matrix) in [("Return correlation", &result.return_correlation), ("P&L correlation", &result.pnl_correlation)] {
+        println!();
+        println!("{}", title);
+        println!("{:<12} | {}", "", result.symbols.iter().map(|s| format!("{:>12}", s)).collect::<Vec<_>>().join(" "));
+        for (symbol, row) in result.symbols.iter().zip(matrix.iter()) {
+            println!("{:<12} | {}", symbol, row.iter().map(|c| format!("{:>12.2}", c)).collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_walk_forward(result: &WalkForwardResult, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    if let OutputFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(result)?);
+        return Ok(());
+    }
+
+    println!("Out-of-sample window    | Parameters                     | IS Score | OOS Score");
+    println!("----------------------- | ------------------------------ | -------- | ---------");
+    for w in &result.windows {
+        let params: Vec<String> = w.params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        println!(
+            "{} - {} | {:<30} | {:<8.2} | {:.2}",
+            w.out_of_sample_start.format("%Y-%m-%d"),
+            w.out_of_sample_end.format("%Y-%m-%d"),
+            params.join(" "),
+            w.in_sample_score,
+            w.out_of_sample_score,
+        );
+    }
+    println!("Walk-forward efficiency: {:.2}", result.efficiency);
+    println!();
+
+    print_backtest(&result.result, format)
+}
+
+fn print_tca(result: &TcaResult, tca: &TcaConfig, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    if let OutputFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(result)?);
+        return Ok(());
+    }
+
+    let cell = |s: &tca::Statistic| format!("{:.2} ± {:.2}", s.mean, s.ci95);
+    println!(
+        "Transaction cost analysis: {} orders over {} windows, {:.1}-{:.1}% of window volume",
+        result.orders.len(), result.windows, tca.min_participation * 100.0, tca.max_participation * 100.0,
+    );
+    println!("Costs in bps with 95% confidence intervals, positive is worse than the benchmark");
+    println!("===============================");
+    println!("Algorithm                           | Filled  | Shortfall       | vs Arrival      | vs VWAP         | vs TWAP         | vs Close        | Timing          | Particip. | Sched. dev.");
+    println!("----------------------------------- | ------- | --------------- | --------------- | --------------- | --------------- | --------------- | --------------- | --------- | -----------");
+    for s in &result.summaries {
+        println!(
+            "{:<35} | {:<7} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15} | {:<15} | {:<9} | {:.2}%",
+            s.algorithm,
+            format!("{:.1}%", s.fill_rate * 100.0),
+            cell(&s.implementation_shortfall),
+            cell(&s.vs_arrival),
+            cell(&s.vs_vwap),
+            cell(&s.vs_twap),
+            cell(&s.vs_close),
+            cell(&s.timing_cost),
+            format!("{:.2}%", s.participation_rate * 100.0),
+            s.schedule_deviation * 100.0,
+        );
+    }
+
+    if let Some((baseline, others)) = result.summaries.split_first() {
+        if !others.is_empty() {
+            println!();
+            println!("Shortfall against {} on the same orders:", baseline.algorithm);
+        }
+        for s in others {
+            let Some(difference) = &s.shortfall_vs_baseline else { continue };
+            let significant = difference.mean.abs() > difference.ci95;
+            println!(
+                "  {:<35} {:+.2} ± {:.2} bps{}",
+                s.algorithm,
+                difference.mean,
+                difference.ci95,
+                if significant { "" } else { " (not significant)" },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_frontier(frontier: &FrontierSummary, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    if let OutputFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(frontier)?);
+        return Ok(());
+    }
+
+    let model = &frontier.model;
+    let notional = model.price * frontier.order_size;
+    let bps = |value: f64| if notional > 0.0 { value / notional * 10_000.0 } else { 0.0 };
+    println!(
+        "Almgren-Chriss efficient frontier: {} {:.2} over {} bars from {}",
+        frontier.symbol, frontier.order_size, frontier.periods, frontier.start.format("%Y-%m-%d %H:%M"),
+    );
+    println!(
+        "Arrival ${:.2}, volatility {:.3}% per bar, temporary impact {:.3e}, permanent impact {:.3e}",
+        model.price, model.volatility / model.price * 100.0, model.temporary_impact, model.permanent_impact,
+    );
+    println!("Costs and risk in bps of the order's notional");
+    println!("===============================");
+    println!("Risk aversion | Expected cost | Std dev   | First bar | Half done in");
+    println!("------------- | ------------- | --------- | --------- | ------------");
+    for t in &frontier.trajectories {
+        // Bars until at least half the order is done
+        let half = t.holdings.iter().position(|x| *x <= frontier.order_size / 2.0).unwrap_or(t.holdings.len());
+        println!(
+            "{:<13} | {:<13.2} | {:<9.2} | {:<9} | {} bars",
+            format!("{:.*}", if t.risk_aversion >= 1.0 { 1 } else { 3 }, t.risk_aversion),
+            bps(t.expected_cost),
+            bps(t.variance.sqrt()),
+            format!("{:.1}%", t.trades.first().map_or(0.0, |n| n / frontier.order_size * 100.0)),
+            half,
+        );
+    }
+
+    Ok(())
+}
+
+fn print_costs(summaries: &[CostSummary], format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(summaries)?),
+        OutputFormat::Text => {
+            println!("Cost model                                 | # Trades | Total Costs  | Total P&L    | Sharpe");
+            println!("------------------------------------------ | -------- | ------------ | ------------ | ------");
+            for s in summaries {
+                println!(
+                    "{:<42} | {:<8} | ${:<11.2} | ${:<11.2} | {:.2}",
+                    s.cost_model, s.trades, s.total_costs, s.total_profit_loss, s.sharpe_ratio,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_indicators(market_data: &MarketData, values: &[IndicatorValue], format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(values)?),
+        OutputFormat::Text => {
+            if let Some(last) = market_data.candles.last() {
+                println!("{} at {} (close {:.4})", market_data.symbol, last.timestamp, last.close);
+            }
+            for v in values {
+                match v.value {
+                    Some(value) => println!("{:<36} {:.4}", v.indicator, value),
+                    None => println!("{:<36} warming up", v.indicator),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_indicator_checks(checks: &[IndicatorCheck], tolerance: f64, format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(checks)?),
+        OutputFormat::Text => {
+            println!("Indicator                      | ta reference              | Bars     | Max Error  | Status");
+            println!("------------------------------ | ------------------------- | -------- | ---------- | ------");
+            for c in checks {
+                let status = if c.compared > 0 && c.max_error <= tolerance { "ok" } else { "FAIL" };
+                println!("{:<30} | {:<25} | {:<8} | {:<10.2e} | {}", c.indicator, c.reference, c.compared, c.max_error, status);
+            }
+            println!("Not in ta: WMA, KAMA (checked via its efficiency ratio), ADX, VWAP, Ichimoku, SuperTrend");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Catalog<'a> {
+    strategies: &'a [Descriptor],
+    execution_algorithms: &'a [Descriptor],
+}
+
+fn print_catalog(strategies: &[Descriptor], algorithms: &[Descriptor], format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&Catalog { strategies, execution_algorithms: algorithms })?);
+        }
+        OutputFormat::Text => {
+            for (title, descriptors) in [("Strategies", strategies), ("Execution algorithms", algorithms)] {
+                println!("{}", title);
+                println!("{}", "=".repeat(title.len()));
+                for d in descriptors {
+                    let aliases = if d.aliases.is_empty() { String::new() } else { format!(" (alias: {})", d.aliases.join(", ")) };
+                    println!("{}{} - {}", d.name, aliases, d.description);
+                    for p in &d.params {
+                        println!("  {:<22} {:<8} default {:<8} {:<14} {}", p.name, p.kind.to_string(), p.default, p.range(), p.description);
+                    }
+                }
+                println!();
+            }
+            println!("Strategies also accept a TOML/JSON rule strategy file; its [params] are its parameters.");
+        }
+    }
+
+    Ok(())
+}
+
+fn print_comparison(results: &[BacktestResult], format: OutputFormat) -> Result<(), Box<dyn Error>> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(results)?),
+        OutputFormat::Text => {
+            println!("Strategy                  | # Trades | Win Rate | Total P&L    | CAGR     | Sharpe | Sortino | Max DD  | Beta");
+            println!("------------------------- | -------- | -------- | ------------ | -------- | ------ | ------- | ------- | -----");
+            for r in results {
+                println!(
+                    "{:<25} | {:<8} | {:<8} | ${:<11.2} | {:<8} | {:<6.2} | {:<7.2} | {:<7} | {}",
+                    r.strategy_name,
+                    r.total_trades,
+                    format!("{:.2}%", r.metrics.get("win_rate").unwrap_or(&0.0) * 100.0),
+                    r.total_profit_loss,
+                    format!("{:.2}%", r.analytics.cagr * 100.0),
+                    r.sharpe_ratio,
+                    r.analytics.sortino_ratio,
+                    format!("{:.2}%", r.max_drawdown * 100.0),
+                    r.analytics.benchmark.as_ref().map_or("n/a".to_string(), |b| format!("{:.2}", b.beta)),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use crate::costs::{CommissionConfig, SlippageConfig};
+use crate::data::DataSchema;
+use crate::matching::MatchingConfig;
+use crate::provider::RestConfig;
+use crate::risk::{PositionSizing, RiskConfig};
+use crate::strategies::{self, StrategyConfig};
+use crate::synthetic::SyntheticConfig;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Everything a backtest run needs, optionally including the strategy to run
+///
+/// Can be loaded from a TOML or JSON file; missing fields fall back to the defaults
+/// (BTC/USD over 2023 on synthetic data with 10,000 starting capital).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BacktestConfig {
+    pub symbol: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub data_source: DataSource,
+    pub timeframe: Option<String>, // Resample the loaded bars to this timeframe, e.g. "1h"
+    pub store: Option<PathBuf>, // Directory of the on-disk bar store that caches data files
+    pub initial_capital: f64,
+    pub commission: CommissionConfig,
+    pub slippage: SlippageConfig,
+    pub position_sizing: PositionSizing,
+    pub risk: RiskConfig,
+    pub matching: MatchingConfig,
+    pub strategy: Option<StrategyConfig>, // Strategy used when the command line does not name one
+    pub pair: Option<SeriesConfig>, // Second leg for pairs strategies, aligned bar for bar
+    pub benchmark: Option<SeriesConfig>, // Series for beta and alpha (default: the traded symbol)
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "BTC/USD".to_string(),
+            start_date: "2023-01-01".to_string(),
+            end_date: "2023-12-31".to_string(),
+            data_source: DataSource::Synthetic { generator: SyntheticConfig::default() },
+            timeframe: None,
+            store: None,
+            initial_capital: 10000.0,
+            commission: CommissionConfig::Percent { rate: 0.001 },
+            slippage: SlippageConfig::None,
+            position_sizing: PositionSizing::Fixed { size: 1.0 },
+            risk: RiskConfig::default(),
+            matching: MatchingConfig::default(),
+            strategy: None,
+            pair: None,
+            benchmark: None,
+        }
+    }
+}
+
+impl BacktestConfig {
+    // Load a config from a `.toml` or `.json` file
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+
+        let config: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&contents)?,
+            Some("json") => serde_json::from_str(&contents)?,
+            _ => return Err(format!("Unsupported config format: {}", path.display()).into()),
+        };
+
+        // Catch unknown strategies and bad parameters at load time rather than mid-run
+        if let Some(strategy) = &config.strategy {
+            strategies::describe_strategy(&strategy.name)?.validate(&strategy.params)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Another instrument loaded next to the main series, such as a pair leg or a benchmark
+///
+/// Loaded for the same date range and timeframe as the main series, from `data_source` or
+/// else from the main data source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesConfig {
+    pub symbol: String,
+    pub data_source: Option<DataSource>,
+}
+
+/// Where the backtest candles come from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DataSource {
+    Synthetic {
+        #[serde(default)]
+        generator: SyntheticConfig,
+    },
+    Csv {
+        path: PathBuf,
+        #[serde(default)]
+        schema: DataSchema,
+    },
+    Parquet {
+        path: PathBuf,
+        #[serde(default)]
+        schema: DataSchema,
+    },
+    Rest(RestConfig),
+}
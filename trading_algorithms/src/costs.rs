@@ -0,0 +1,400 @@
+use crate::config::BacktestConfig;
+use crate::matching::Fill;
+use crate::models::{Candle, Trade};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Fee charged by the venue on a fill
+pub trait CommissionModel {
+    fn name(&self) -> String;
+
+    // Commission for `size` units at `price`; `traded_notional` is what the account
+    // has already traded during the run, for volume-tiered fee schedules
+    fn commission(&self, price: f64, size: f64, taker: bool, traded_notional: f64) -> f64;
+}
+
+/// Price concession paid by fills that take liquidity
+pub trait SlippageModel {
+    fn name(&self) -> String;
+
+    // Per-unit price move against the trader for a taker fill of `size` at `price` on `candle`
+    fn slippage(&self, price: f64, size: f64, candle: &Candle) -> f64;
+}
+
+/// Commission schedule, as written in a backtest config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommissionConfig {
+    None,
+    Percent { rate: f64 }, // Fraction of traded notional
+    Bps { bps: f64 },
+    #[serde(alias = "per_share")]
+    PerUnit {
+        amount: f64,
+        #[serde(default)]
+        minimum: f64, // Smallest commission charged per fill
+    },
+    Tiered { tiers: Vec<FeeTier> },
+}
+
+/// One step of a maker/taker fee schedule
+///
+/// A tier applies once the notional traded so far reaches `min_notional`. Negative maker
+/// fees are rebates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeTier {
+    #[serde(default)]
+    pub min_notional: f64,
+    pub maker_bps: f64,
+    pub taker_bps: f64,
+}
+
+/// Slippage model, as written in a backtest config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlippageConfig {
+    None,
+    Bps { bps: f64 },
+    Spread { spread_bps: f64 }, // Quoted bid/ask spread; takers pay half of it
+    SquareRootImpact { coefficient: f64 },
+}
+
+// Commission and slippage specs on the command line look like "bps:5" or "none"
+fn parse_spec(s: &str) -> Result<(&str, Option<f64>), String> {
+    match s.split_once(':') {
+        Some((kind, value)) => {
+            let value = value.parse::<f64>().map_err(|e| format!("invalid value in '{}': {}", s, e))?;
+            Ok((kind, Some(value)))
+        }
+        None => Ok((s, None)),
+    }
+}
+
+impl FromStr for CommissionConfig {
+    type Err = String;
+
+    // none, percent:<rate>, bps:<bps> or per_unit:<amount>
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_spec(s)? {
+            ("none", None) => Ok(CommissionConfig::None),
+            ("percent", Some(rate)) => Ok(CommissionConfig::Percent { rate }),
+            ("bps", Some(bps)) => Ok(CommissionConfig::Bps { bps }),
+            ("per_unit" | "per_share", Some(amount)) => Ok(CommissionConfig::PerUnit { amount, minimum: 0.0 }),
+            _ => Err(format!("unknown commission '{}', expected none, percent:<rate>, bps:<bps> or per_unit:<amount>", s)),
+        }
+    }
+}
+
+impl FromStr for SlippageConfig {
+    type Err = String;
+
+    // none, bps:<bps>, spread:<spread bps> or sqrt_impact:<coefficient>
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_spec(s)? {
+            ("none", None) => Ok(SlippageConfig::None),
+            ("bps", Some(bps)) => Ok(SlippageConfig::Bps { bps }),
+            ("spread", Some(spread_bps)) => Ok(SlippageConfig::Spread { spread_bps }),
+            ("sqrt_impact" | "square_root_impact", Some(coefficient)) => Ok(SlippageConfig::SquareRootImpact { coefficient }),
+            _ => Err(format!("unknown slippage '{}', expected none, bps:<bps>, spread:<bps> or sqrt_impact:<coefficient>", s)),
+        }
+    }
+}
+
+pub struct NoCommission;
+
+impl CommissionModel for NoCommission {
+    fn name(&self) -> String {
+        "no_commission".to_string()
+    }
+
+    fn commission(&self, _price: f64, _size: f64, _taker: bool, _traded_notional: f64) -> f64 {
+        0.0
+    }
+}
+
+/// Same fee in basis points of notional for every fill
+pub struct FixedBpsCommission {
+    pub bps: f64,
+}
+
+impl CommissionModel for FixedBpsCommission {
+    fn name(&self) -> String {
+        format!("commission_{}bps", self.bps)
+    }
+
+    fn commission(&self, price: f64, size: f64, _taker: bool, _traded_notional: f64) -> f64 {
+        price * size * self.bps / 10000.0
+    }
+}
+
+/// Flat fee per unit (share, contract, coin) with a per-fill minimum
+pub struct PerShareCommission {
+    pub amount: f64,
+    pub minimum: f64,
+}
+
+impl CommissionModel for PerShareCommission {
+    fn name(&self) -> String {
+        format!("commission_{}_per_unit", self.amount)
+    }
+
+    fn commission(&self, _price: f64, size: f64, _taker: bool, _traded_notional: f64) -> f64 {
+        (size * self.amount).max(self.minimum)
+    }
+}
+
+/// Maker/taker fees that step down as traded notional grows
+pub struct TieredCommission {
+    tiers: Vec<FeeTier>,
+}
+
+impl TieredCommission {
+    pub fn new(mut tiers: Vec<FeeTier>) -> Self {
+        tiers.sort_by(|a, b| a.min_notional.total_cmp(&b.min_notional));
+        Self { tiers }
+    }
+}
+
+impl CommissionModel for TieredCommission {
+    fn name(&self) -> String {
+        format!("commission_tiered_{}", self.tiers.len())
+    }
+
+    fn commission(&self, price: f64, size: f64, taker: bool, traded_notional: f64) -> f64 {
+        // Below the first threshold the first tier still applies
+        let Some(tier) = self.tiers.iter()
+            .rev()
+            .find(|t| t.min_notional <= traded_notional)
+            .or(self.tiers.first()) else {
+            return 0.0;
+        };
+        let bps = if taker { tier.taker_bps } else { tier.maker_bps };
+        price * size * bps / 10000.0
+    }
+}
+
+pub struct NoSlippage;
+
+impl SlippageModel for NoSlippage {
+    fn name(&self) -> String {
+        "no_slippage".to_string()
+    }
+
+    fn slippage(&self, _price: f64, _size: f64, _candle: &Candle) -> f64 {
+        0.0
+    }
+}
+
+/// Constant slippage in basis points of the fill price
+pub struct FixedBpsSlippage {
+    pub bps: f64,
+}
+
+impl SlippageModel for FixedBpsSlippage {
+    fn name(&self) -> String {
+        format!("slippage_{}bps", self.bps)
+    }
+
+    fn slippage(&self, price: f64, _size: f64, _candle: &Candle) -> f64 {
+        price * self.bps / 10000.0
+    }
+}
+
+/// Bar prices are mid prices, so crossing the spread costs half of it
+pub struct SpreadSlippage {
+    pub spread_bps: f64,
+}
+
+impl SlippageModel for SpreadSlippage {
+    fn name(&self) -> String {
+        format!("half_spread_{}bps", self.spread_bps)
+    }
+
+    fn slippage(&self, price: f64, _size: f64, _candle: &Candle) -> f64 {
+        price * self.spread_bps / 2.0 / 10000.0
+    }
+}
+
+/// Square-root market impact: `coefficient * volatility * sqrt(size / bar volume)`
+///
+/// Volatility is the Parkinson estimate from the fill bar's high and low, so impact grows
+/// both with the order's share of the bar's volume and with how much the bar moved.
+pub struct SquareRootImpact {
+    pub coefficient: f64,
+}
+
+impl SlippageModel for SquareRootImpact {
+    fn name(&self) -> String {
+        format!("sqrt_impact_{}", self.coefficient)
+    }
+
+    fn slippage(&self, price: f64, size: f64, candle: &Candle) -> f64 {
+        if candle.volume <= 0.0 || candle.low <= 0.0 {
+            return 0.0;
+        }
+
+        let volatility = (candle.high / candle.low).ln() / (4.0 * 2f64.ln()).sqrt();
+        price * self.coefficient * volatility * (size / candle.volume).sqrt()
+    }
+}
+
+/// Factory function to create the commission model a config describes
+pub fn create_commission_model(config: &CommissionConfig) -> Box<dyn CommissionModel> {
+    match config {
+        CommissionConfig::None => Box::new(NoCommission),
+        CommissionConfig::Percent { rate } => Box::new(FixedBpsCommission { bps: rate * 10000.0 }),
+        CommissionConfig::Bps { bps } => Box::new(FixedBpsCommission { bps: *bps }),
+        CommissionConfig::PerUnit { amount, minimum } => Box::new(PerShareCommission { amount: *amount, minimum: *minimum }),
+        CommissionConfig::Tiered { tiers } => Box::new(TieredCommission::new(tiers.clone())),
+    }
+}
+
+/// Factory function to create the slippage model a config describes
+pub fn create_slippage_model(config: &SlippageConfig) -> Box<dyn SlippageModel> {
+    match config {
+        SlippageConfig::None => Box::new(NoSlippage),
+        SlippageConfig::Bps { bps } => Box::new(FixedBpsSlippage { bps: *bps }),
+        SlippageConfig::Spread { spread_bps } => Box::new(SpreadSlippage { spread_bps: *spread_bps }),
+        SlippageConfig::SquareRootImpact { coefficient } => Box::new(SquareRootImpact { coefficient: *coefficient }),
+    }
+}
+
+/// The cost assumptions of one run, turning matched fills into booked trades
+///
+/// Backtests and execution algorithms both book their fills through this, so a run's
+/// commission and slippage come from the config alone.
+pub struct CostModel {
+    commission: Box<dyn CommissionModel>,
+    slippage: Box<dyn SlippageModel>,
+    traded_notional: f64,
+}
+
+impl CostModel {
+    pub fn new(commission: Box<dyn CommissionModel>, slippage: Box<dyn SlippageModel>) -> Self {
+        Self {
+            commission,
+            slippage,
+            traded_notional: 0.0,
+        }
+    }
+
+    pub fn from_config(config: &BacktestConfig) -> Self {
+        Self::new(create_commission_model(&config.commission), create_slippage_model(&config.slippage))
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}+{}", self.commission.name(), self.slippage.name())
+    }
+
+    // Book `fill` from `candle`: fills that took liquidity slip against the trader,
+    // and every fill pays commission at the price it got
+    pub fn book(&mut self, fill: Fill, candle: &Candle) -> Trade {
+        let price = if fill.taker {
+            fill.price + fill.direction.sign() * self.slippage.slippage(fill.price, fill.size, candle)
+        } else {
+            fill.price
+        };
+        let costs = self.commission.commission(price, fill.size, fill.taker, self.traded_notional);
+        self.traded_notional += price * fill.size;
+
+        Trade {
+            timestamp: fill.timestamp,
+            symbol: fill.symbol,
+            direction: fill.direction,
+            price,
+            size: fill.size,
+            costs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TradeDirection;
+    use crate::models::fixtures::{bar, day};
+
+    fn fill(direction: TradeDirection, price: f64, size: f64, taker: bool) -> Fill {
+        Fill { order_id: 1, symbol: "TEST".to_string(), direction, timestamp: day(0), price, size, taker, partial: false }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn fixed_bps_commission_is_a_share_of_notional() {
+        assert_close(FixedBpsCommission { bps: 5.0 }.commission(100.0, 10.0, true, 0.0), 0.5);
+        // A percent rate of 0.001 is 10 bps, maker or taker
+        let percent = create_commission_model(&CommissionConfig::Percent { rate: 0.001 });
+        assert_close(percent.commission(100.0, 10.0, true, 0.0), 1.0);
+        assert_close(percent.commission(100.0, 10.0, false, 1e9), 1.0);
+    }
+
+    #[test]
+    fn per_share_commission_has_a_minimum() {
+        let per_share = PerShareCommission { amount: 0.01, minimum: 1.0 };
+        assert_close(per_share.commission(100.0, 50.0, true, 0.0), 1.0);
+        assert_close(per_share.commission(100.0, 500.0, true, 0.0), 5.0);
+        assert_close(per_share.commission(1.0, 500.0, true, 0.0), 5.0);
+    }
+
+    #[test]
+    fn tiered_commission_charges_makers_and_takers_by_traded_notional() {
+        let tiered = TieredCommission::new(vec![
+            FeeTier { min_notional: 1_000_000.0, maker_bps: -1.0, taker_bps: 5.0 },
+            FeeTier { min_notional: 0.0, maker_bps: 2.0, taker_bps: 10.0 },
+        ]);
+        assert_close(tiered.commission(100.0, 10.0, true, 0.0), 1.0);
+        assert_close(tiered.commission(100.0, 10.0, false, 0.0), 0.2);
+        assert_close(tiered.commission(100.0, 10.0, true, 1_000_000.0), 0.5);
+        // Maker rebate
+        assert_close(tiered.commission(100.0, 10.0, false, 2_000_000.0), -0.1);
+
+        // Booking picks the fee from the fill's side of the spread and the notional traded before it
+        let mut costs = CostModel::new(Box::new(tiered), Box::new(NoSlippage));
+        let candle = bar(0, 100.0, 101.0, 99.0, 100.0);
+        assert_close(costs.book(fill(TradeDirection::Long, 100.0, 10.0, false), &candle).costs, 0.2);
+        assert_close(costs.book(fill(TradeDirection::Long, 100.0, 10_000.0, true), &candle).costs, 1_000.0);
+        assert_close(costs.book(fill(TradeDirection::Short, 100.0, 10.0, true), &candle).costs, 0.5);
+        assert_close(costs.book(fill(TradeDirection::Short, 100.0, 10.0, false), &candle).costs, -0.1);
+    }
+
+    #[test]
+    fn takers_pay_half_the_spread_and_makers_none() {
+        let mut costs = CostModel::new(Box::new(FixedBpsCommission { bps: 10.0 }), Box::new(SpreadSlippage { spread_bps: 20.0 }));
+        let candle = bar(0, 100.0, 101.0, 99.0, 100.0);
+
+        let buy = costs.book(fill(TradeDirection::Long, 100.0, 10.0, true), &candle);
+        assert_close(buy.price, 100.1);
+        assert_close(buy.costs, 100.1 * 10.0 * 0.001);
+        assert_close(costs.book(fill(TradeDirection::Short, 100.0, 10.0, true), &candle).price, 99.9);
+        assert_close(costs.book(fill(TradeDirection::Long, 100.0, 10.0, false), &candle).price, 100.0);
+
+        assert_close(FixedBpsSlippage { bps: 5.0 }.slippage(200.0, 1.0, &candle), 0.1);
+    }
+
+    #[test]
+    fn square_root_impact_grows_with_participation_and_range() {
+        // A high/low range whose Parkinson volatility is 2%
+        let high = 100.0 * (0.02 * (4.0 * 2f64.ln()).sqrt()).exp();
+        let candle = Candle { volume: 10_000.0, ..bar(0, 100.0, high, 100.0, 100.0) };
+        let impact = SquareRootImpact { coefficient: 0.5 };
+
+        // 0.5 * 2% * sqrt(1%) of the price
+        assert_close(impact.slippage(100.0, 100.0, &candle), 0.1);
+        assert_close(impact.slippage(100.0, 400.0, &candle), 0.2);
+        assert_close(impact.slippage(100.0, 100.0, &Candle { volume: 0.0, ..candle.clone() }), 0.0);
+        assert_close(impact.slippage(100.0, 100.0, &bar(0, 100.0, 100.0, 100.0, 100.0)), 0.0);
+    }
+
+    #[test]
+    fn specs_parse_from_the_command_line() {
+        assert!(matches!("bps:5".parse(), Ok(CommissionConfig::Bps { bps }) if bps == 5.0));
+        assert!(matches!("per_share:0.01".parse(), Ok(CommissionConfig::PerUnit { amount, minimum }) if amount == 0.01 && minimum == 0.0));
+        assert!(matches!("spread:4".parse(), Ok(SlippageConfig::Spread { spread_bps }) if spread_bps == 4.0));
+        assert!(matches!("none".parse(), Ok(SlippageConfig::None)));
+        assert!("bps".parse::<CommissionConfig>().is_err());
+        assert!("sqrt_impact:x".parse::<SlippageConfig>().is_err());
+    }
+}
@@ -1,122 +1,762 @@
+use crate::config::{BacktestConfig, DataSource, SeriesConfig};
 use crate::models::{Candle, MarketData};
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
-use csv::ReaderBuilder;
+use crate::provider::{DataProvider, RestProvider};
+use crate::resample;
+use crate::store::{self, DataStore};
+use crate::synthetic;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use csv::{ReaderBuilder, Writer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::path::Path;
 
-pub async fn fetch_historical_data(
-    symbol: &str,
-    start_date: &str,
-    end_date: &str,
-) -> Result<MarketData, Box<dyn Error>> {
-    // In a real application, this would use an API to fetch real market data
-    // For now, we'll simulate loading data
+// Load the candles a backtest config asks for, restricted to its date range and resampled
+// to its timeframe, together with the second leg when the config names a pair
+pub async fn load_market_data(config: &BacktestConfig) -> Result<MarketData, Box<dyn Error>> {
+    let data = load_series(config).await?;
+    match &config.pair {
+        Some(pair) => align_pair(data, load_related(config, pair).await?),
+        None => Ok(data),
+    }
+}
+
+// Load the config's benchmark series, if it names one
+pub async fn load_benchmark(config: &BacktestConfig) -> Result<Option<MarketData>, Box<dyn Error>> {
+    match &config.benchmark {
+        Some(benchmark) => Ok(Some(load_related(config, benchmark).await?)),
+        None => Ok(None),
+    }
+}
+
+// Load another symbol over the main series' date range and timeframe
+async fn load_related(config: &BacktestConfig, series: &SeriesConfig) -> Result<MarketData, Box<dyn Error>> {
+    let related = BacktestConfig {
+        symbol: series.symbol.clone(),
+        data_source: series.data_source.clone().unwrap_or_else(|| config.data_source.clone()),
+        ..config.clone()
+    };
+    load_series(&related).await
+}
+
+// Keep the bars both legs have, so index i is the same timestamp in either series. Repeated
+// timestamps keep their first bar; the loaders only warn about them, and a repeat in either
+// leg would otherwise shift every later bar of one series against the other.
+fn align_pair(mut data: MarketData, mut pair: MarketData) -> Result<MarketData, Box<dyn Error>> {
+    for series in [&mut data, &mut pair] {
+        let duplicates = dedup_timestamps(&mut series.candles);
+        if duplicates > 0 {
+            eprintln!("Dropped {} {} bars repeating an earlier timestamp", duplicates, series.symbol);
+        }
+    }
+
+    let timestamps: HashSet<DateTime<Utc>> = data.candles.iter().map(|c| c.timestamp).collect();
+    pair.candles.retain(|c| timestamps.contains(&c.timestamp));
+    let shared: HashSet<DateTime<Utc>> = pair.candles.iter().map(|c| c.timestamp).collect();
+
+    let dropped = data.candles.len() - shared.len();
+    if dropped > 0 {
+        eprintln!("Dropped {} {} bars without a matching {} bar", dropped, data.symbol, pair.symbol);
+    }
+    data.candles.retain(|c| shared.contains(&c.timestamp));
+
+    // Both legs now hold the same timestamps; only a different order could still misalign them
+    if data.candles.iter().zip(&pair.candles).any(|(a, b)| a.timestamp != b.timestamp) {
+        return Err(format!("{} and {} bars are not in the same time order", data.symbol, pair.symbol).into());
+    }
+    data.pair = Some(Box::new(pair));
+    Ok(data)
+}
+
+// Drop bars whose timestamp an earlier bar already has, returning how many were dropped
+fn dedup_timestamps(candles: &mut Vec<Candle>) -> usize {
+    let before = candles.len();
+    let mut seen = HashSet::new();
+    candles.retain(|c| seen.insert(c.timestamp));
+    before - candles.len()
+}
+
+// Put bars in time order and drop repeated timestamps, keeping the bar that came first;
+// returns how many were dropped
+pub fn sort_candles(candles: &mut Vec<Candle>) -> usize {
+    candles.sort_by_key(|c| c.timestamp);
+    dedup_timestamps(candles)
+}
+
+// Load one symbol's series
+async fn load_series(config: &BacktestConfig) -> Result<MarketData, Box<dyn Error>> {
+    let data = match &config.data_source {
+        DataSource::Synthetic { generator } => {
+            synthetic::generate(&config.symbol, &config.start_date, &config.end_date, generator)?
+        }
+        DataSource::Csv { path, schema } => load_file(path, config, |p| load_csv_with_schema(p, &config.symbol, schema))?,
+        DataSource::Parquet { path, schema } => load_file(path, config, |p| load_parquet_with_schema(p, &config.symbol, schema))?,
+        DataSource::Rest(rest) => load_from_provider(&RestProvider::new(rest.clone()), &rest.interval, config).await?,
+    };
+    
+    match &config.timeframe {
+        Some(timeframe) if *timeframe != data.timeframe => resample::resample(&data, timeframe),
+        _ => Ok(data),
+    }
+}
+
+// Read the config's date range from a data file, through the on-disk store when one is configured
+fn load_file<F>(path: &Path, config: &BacktestConfig, read: F) -> Result<MarketData, Box<dyn Error>>
+where
+    F: Fn(&Path) -> Result<(MarketData, Vec<DataIssue>), Box<dyn Error>>,
+{
+    let (start, end) = parse_date_range(&config.start_date, &config.end_date)?;
+    let parse = || -> Result<MarketData, Box<dyn Error>> {
+        let (data, issues) = read(path)?;
+        report_issues(&data.symbol, &issues);
+        Ok(data)
+    };
     
-    println!("Fetching historical data for {} from {} to {}", symbol, start_date, end_date);
+    let Some(root) = &config.store else {
+        let mut data = parse()?;
+        data.candles.retain(|c| c.timestamp >= start && c.timestamp <= end);
+        return Ok(data);
+    };
     
-    // For demo purposes, we'll generate some dummy data
-    let candles = generate_dummy_data(symbol, start_date, end_date)?;
+    let store = DataStore::new(root);
+    let source = store::file_source(path)?;
+    // The file is parsed at most once; every range the store is missing is sliced from it
+    let mut file = None;
+    let timeframe = match store.find_timeframe(&config.symbol, &source)? {
+        Some(timeframe) => timeframe,
+        None => {
+            // First time this file is seen: import all of it
+            let data = import_file(&store, &source, read(path)?)?;
+            let timeframe = data.timeframe.clone();
+            file = Some(data);
+            timeframe
+        }
+    };
     
-    Ok(MarketData {
-        symbol: symbol.to_string(),
-        timeframe: "1D".to_string(),
-        candles,
+    store.backfill(&config.symbol, &timeframe, &source, start, end, |from, to| {
+        let data = match file.take() {
+            Some(data) => data,
+            None => parse()?,
+        };
+        let candles = data.candles.iter().filter(|c| c.timestamp >= from && c.timestamp <= to).cloned().collect();
+        file = Some(data);
+        Ok(candles)
     })
 }
 
-pub fn load_csv_data(csv_path: &Path, symbol: &str) -> Result<MarketData, Box<dyn Error>> {
+// Fetch the config's date range from a provider, through the on-disk store when one is configured
+pub async fn load_from_provider<P: DataProvider>(provider: &P, timeframe: &str, config: &BacktestConfig) -> Result<MarketData, Box<dyn Error>> {
+    let (start, end) = parse_date_range(&config.start_date, &config.end_date)?;
+    
+    let Some(root) = &config.store else {
+        eprintln!("Fetching {} {} bars from {}", config.symbol, timeframe, provider.name());
+        let candles = provider.fetch_candles(&config.symbol, timeframe, start, end).await?;
+        return Ok(MarketData::new(&config.symbol, timeframe, candles));
+    };
+    
+    // Only ask the provider for what the store does not have yet
+    let store = DataStore::new(root);
+    let source = provider.source();
+    for (from, to) in store.missing_ranges(&config.symbol, timeframe, &source, start, end)? {
+        eprintln!("Fetching {} {} bars from {} for {} to {}", config.symbol, timeframe, provider.name(), from, to);
+        let candles = provider.fetch_candles(&config.symbol, timeframe, from, to).await?;
+        store.insert(&config.symbol, timeframe, &source, from, to, candles)?;
+    }
+    
+    store.query(&config.symbol, timeframe, start, end)
+}
+
+// Store every bar of a freshly read file as covering its first to last timestamp
+pub fn import_file(store: &DataStore, source: &str, (data, issues): (MarketData, Vec<DataIssue>)) -> Result<MarketData, Box<dyn Error>> {
+    report_issues(&data.symbol, &issues);
+    if let (Some(first), Some(last)) = (data.candles.first(), data.candles.last()) {
+        store.insert(&data.symbol, &data.timeframe, source, first.timestamp, last.timestamp, data.candles.clone())?;
+    }
+    Ok(data)
+}
+
+// Parse "YYYY-MM-DD" bounds into an inclusive UTC range covering both whole days
+pub fn parse_date_range(start_date: &str, end_date: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), Box<dyn Error>> {
+    let start = NaiveDateTime::parse_from_str(&format!("{} 00:00:00", start_date), "%Y-%m-%d %H:%M:%S")?;
+    let end = NaiveDateTime::parse_from_str(&format!("{} 23:59:59", end_date), "%Y-%m-%d %H:%M:%S")?;
+    
+    Ok((
+        DateTime::<Utc>::from_naive_utc_and_offset(start, Utc),
+        DateTime::<Utc>::from_naive_utc_and_offset(end, Utc),
+    ))
+}
+
+/// How to read OHLCV bars from a vendor file
+///
+/// Columns are matched by header name (case-insensitive) or by zero-based index. When the
+/// configured name is missing, common aliases are tried (e.g. `date` or `time` for the
+/// timestamp). The timeframe is inferred from bar spacing unless given explicitly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataSchema {
+    pub delimiter: char,
+    pub has_headers: bool,
+    pub timestamp_format: TimestampFormat,
+    pub columns: ColumnMapping,
+    pub timeframe: Option<String>,
+}
+
+impl Default for DataSchema {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_headers: true,
+            timestamp_format: TimestampFormat::Auto,
+            columns: ColumnMapping::default(),
+            timeframe: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub timestamp: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            timestamp: "timestamp".to_string(),
+            open: "open".to_string(),
+            high: "high".to_string(),
+            low: "low".to_string(),
+            close: "close".to_string(),
+            volume: "volume".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    Auto, // Detected from the first row
+    EpochSeconds,
+    EpochMillis,
+    Iso8601, // RFC 3339, "YYYY-MM-DD HH:MM:SS" or plain dates
+    Custom(String), // chrono format string
+}
+
+/// Problem found while validating loaded bars
+#[derive(Debug, Clone, Serialize)]
+pub enum DataIssue {
+    NonMonotonic { index: usize, timestamp: DateTime<Utc> },
+    Duplicate { index: usize, timestamp: DateTime<Utc> },
+    Gap { index: usize, from: DateTime<Utc>, to: DateTime<Utc>, missing_bars: usize },
+    HighBelowLow { index: usize, timestamp: DateTime<Utc> },
+    OutsideRange { index: usize, timestamp: DateTime<Utc> }, // open or close outside [low, high]
+    InvalidValue { index: usize, timestamp: DateTime<Utc> }, // non-positive price or negative volume
+}
+
+impl fmt::Display for DataIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataIssue::NonMonotonic { index, timestamp } => write!(f, "bar {} ({}) is earlier than the bar before it", index, timestamp),
+            DataIssue::Duplicate { index, timestamp } => write!(f, "bar {} ({}) repeats the previous timestamp", index, timestamp),
+            DataIssue::Gap { index, from, to, missing_bars } => write!(f, "gap of ~{} bars before bar {} ({} -> {})", missing_bars, index, from, to),
+            DataIssue::HighBelowLow { index, timestamp } => write!(f, "bar {} ({}) has high < low", index, timestamp),
+            DataIssue::OutsideRange { index, timestamp } => write!(f, "bar {} ({}) has open/close outside [low, high]", index, timestamp),
+            DataIssue::InvalidValue { index, timestamp } => write!(f, "bar {} ({}) has a non-positive price or negative volume", index, timestamp),
+        }
+    }
+}
+
+// Load a CSV file using `schema`, returning the bars and any validation issues
+pub fn load_csv_with_schema(csv_path: &Path, symbol: &str, schema: &DataSchema) -> Result<(MarketData, Vec<DataIssue>), Box<dyn Error>> {
     let file = File::open(csv_path)?;
     let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(b',')
+        .has_headers(schema.has_headers)
+        .delimiter(schema.delimiter as u8)
+        .trim(csv::Trim::All)
         .from_reader(file);
     
+    let headers: Vec<String> = if schema.has_headers {
+        reader.headers()?.iter().map(|h| h.to_string()).collect()
+    } else {
+        Vec::new()
+    };
+    
+    let columns = &schema.columns;
+    let timestamp_col = resolve_column(&headers, &columns.timestamp, &["timestamp", "time", "date", "datetime", "ts", "open_time"])?;
+    let open_col = resolve_column(&headers, &columns.open, &["open", "o"])?;
+    let high_col = resolve_column(&headers, &columns.high, &["high", "h"])?;
+    let low_col = resolve_column(&headers, &columns.low, &["low", "l"])?;
+    let close_col = resolve_column(&headers, &columns.close, &["close", "c", "adj_close"])?;
+    let volume_col = resolve_column(&headers, &columns.volume, &["volume", "vol", "v"]).ok();
+    
     let mut candles = Vec::new();
+    let mut format = schema.timestamp_format.clone();
     
-    for result in reader.records() {
+    for (row, result) in reader.records().enumerate() {
         let record = result?;
-        if record.len() < 6 {
-            continue;
-        }
+        let field = |col: usize| record.get(col).ok_or_else(|| format!("row {}: missing column {}", row + 1, col));
+        let number = |col: usize| -> Result<f64, Box<dyn Error>> {
+            let raw = field(col)?;
+            raw.parse::<f64>().map_err(|e| format!("row {}: invalid number '{}': {}", row + 1, raw, e).into())
+        };
         
-        // Parse CSV fields (assuming format: timestamp,open,high,low,close,volume)
-        let timestamp = record[0].parse::<i64>()?;
-        let timestamp = Utc.timestamp_opt(timestamp, 0).single()
-            .ok_or_else(|| "Invalid timestamp")?;
-            
-        let open = record[1].parse::<f64>()?;
-        let high = record[2].parse::<f64>()?;
-        let low = record[3].parse::<f64>()?;
-        let close = record[4].parse::<f64>()?;
-        let volume = record[5].parse::<f64>()?;
+        let raw_timestamp = field(timestamp_col)?;
+        if format == TimestampFormat::Auto {
+            format = detect_timestamp_format(raw_timestamp);
+        }
         
         candles.push(Candle {
-            timestamp,
-            open,
-            high,
-            low,
-            close,
-            volume,
+            timestamp: parse_timestamp(raw_timestamp, &format)
+                .map_err(|e| format!("row {}: {}", row + 1, e))?,
+            open: number(open_col)?,
+            high: number(high_col)?,
+            low: number(low_col)?,
+            close: number(close_col)?,
+            volume: match volume_col {
+                Some(col) => number(col)?,
+                None => 0.0,
+            },
         });
     }
     
-    Ok(MarketData {
-        symbol: symbol.to_string(),
-        timeframe: "1D".to_string(),
-        candles,
-    })
+    Ok(finish_loading(symbol, candles, schema))
 }
 
-fn generate_dummy_data(
-    _symbol: &str,
-    start_date: &str,
-    end_date: &str,
-) -> Result<Vec<Candle>, Box<dyn Error>> {
-    let start = NaiveDateTime::parse_from_str(&format!("{} 00:00:00", start_date), "%Y-%m-%d %H:%M:%S")?;
-    let end = NaiveDateTime::parse_from_str(&format!("{} 23:59:59", end_date), "%Y-%m-%d %H:%M:%S")?;
-    
-    let start_timestamp = DateTime::<Utc>::from_naive_utc_and_offset(start, Utc);
-    let end_timestamp = DateTime::<Utc>::from_naive_utc_and_offset(end, Utc);
+#[cfg(feature = "parquet")]
+// Load a Parquet file using `schema`, returning the bars and any validation issues
+pub fn load_parquet_with_schema(path: &Path, symbol: &str, schema: &DataSchema) -> Result<(MarketData, Vec<DataIssue>), Box<dyn Error>> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
     
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let columns = &schema.columns;
     let mut candles = Vec::new();
-    let mut current_price = 100.0;
-    let mut current_time = start_timestamp;
-    
-    let day_seconds = 24 * 60 * 60;
-    
-    while current_time <= end_timestamp {
-        // Generate some random price movement
-        let change_percent = (rand() * 2.0 - 1.0) * 0.02; // -2% to +2%
-        let open = current_price;
-        let close = open * (1.0 + change_percent);
-        let high = open.max(close) * (1.0 + rand() * 0.01);
-        let low = open.min(close) * (1.0 - rand() * 0.01);
-        let volume = 10000.0 + rand() * 90000.0;
+    let mut format = schema.timestamp_format.clone();
+    
+    for (row, result) in reader.get_row_iter(None)?.enumerate() {
+        let record = result?;
+        let fields: Vec<(String, &Field)> = record.get_column_iter()
+            .map(|(name, field)| (name.to_lowercase(), field))
+            .collect();
+        let find = |name: &str, aliases: &[&str]| {
+            std::iter::once(name.to_lowercase())
+                .chain(aliases.iter().map(|a| a.to_string()))
+                .find_map(|key| fields.iter().find(|(n, _)| *n == key).map(|(_, f)| *f))
+        };
+        let number = |name: &str, aliases: &[&str]| -> Result<f64, Box<dyn Error>> {
+            match find(name, aliases) {
+                Some(Field::Double(v)) => Ok(*v),
+                Some(Field::Float(v)) => Ok(*v as f64),
+                Some(Field::Long(v)) => Ok(*v as f64),
+                Some(Field::Int(v)) => Ok(*v as f64),
+                Some(Field::Str(v)) => Ok(v.parse::<f64>()?),
+                other => Err(format!("row {}: column '{}' is missing or not numeric: {:?}", row + 1, name, other).into()),
+            }
+        };
+        
+        let timestamp = match find(&columns.timestamp, &["timestamp", "time", "date", "datetime", "ts", "open_time"]) {
+            Some(Field::TimestampMillis(ms)) => Utc.timestamp_millis_opt(*ms).single().ok_or("Invalid timestamp")?,
+            Some(Field::TimestampMicros(us)) => Utc.timestamp_micros(*us).single().ok_or("Invalid timestamp")?,
+            Some(Field::Date(days)) => Utc.timestamp_opt(*days as i64 * 86400, 0).single().ok_or("Invalid timestamp")?,
+            Some(Field::Long(v)) => {
+                let raw = v.to_string();
+                if format == TimestampFormat::Auto {
+                    format = detect_timestamp_format(&raw);
+                }
+                parse_timestamp(&raw, &format)?
+            }
+            Some(Field::Str(raw)) => {
+                if format == TimestampFormat::Auto {
+                    format = detect_timestamp_format(raw);
+                }
+                parse_timestamp(raw, &format)?
+            }
+            other => return Err(format!("row {}: unsupported timestamp value {:?}", row + 1, other).into()),
+        };
         
         candles.push(Candle {
-            timestamp: current_time,
-            open,
-            high,
-            low,
-            close,
-            volume,
+            timestamp,
+            open: number(&columns.open, &["open", "o"])?,
+            high: number(&columns.high, &["high", "h"])?,
+            low: number(&columns.low, &["low", "l"])?,
+            close: number(&columns.close, &["close", "c", "adj_close"])?,
+            volume: number(&columns.volume, &["volume", "vol", "v"]).unwrap_or(0.0),
         });
-        
-        current_price = close;
-        current_time = current_time + chrono::Duration::seconds(day_seconds);
     }
     
-    Ok(candles)
+    Ok(finish_loading(symbol, candles, schema))
+}
+
+#[cfg(not(feature = "parquet"))]
+pub fn load_parquet_with_schema(path: &Path, _symbol: &str, _schema: &DataSchema) -> Result<(MarketData, Vec<DataIssue>), Box<dyn Error>> {
+    Err(format!("Cannot read {}: built without the `parquet` feature", path.display()).into())
+}
+
+// Write bars as a CSV with RFC 3339 timestamps, readable with the default schema
+pub fn write_csv(path: &Path, data: &MarketData) -> Result<(), Box<dyn Error>> {
+    let mut writer = Writer::from_path(path)?;
+    writer.write_record(["timestamp", "open", "high", "low", "close", "volume"])?;
+    
+    for candle in &data.candles {
+        writer.write_record([
+            candle.timestamp.to_rfc3339(),
+            candle.open.to_string(),
+            candle.high.to_string(),
+            candle.low.to_string(),
+            candle.close.to_string(),
+            candle.volume.to_string(),
+        ])?;
+    }
+    
+    writer.flush()?;
+    Ok(())
 }
 
-// Simple deterministic random number generator for demo purposes
-fn rand() -> f64 {
-    use std::time::SystemTime;
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .subsec_nanos() as f64;
+// Infer the timeframe and validate freshly loaded bars
+fn finish_loading(symbol: &str, candles: Vec<Candle>, schema: &DataSchema) -> (MarketData, Vec<DataIssue>) {
+    let bar_seconds = infer_bar_seconds(&candles);
+    let timeframe = schema.timeframe.clone()
+        .or_else(|| bar_seconds.map(timeframe_label))
+        .unwrap_or_else(|| "1D".to_string());
+    let issues = validate_candles(&candles, bar_seconds);
     
-    (now / 1_000_000_000.0).fract()
-}
\ No newline at end of file
+    (MarketData::new(symbol, &timeframe, candles), issues)
+}
+
+fn report_issues(symbol: &str, issues: &[DataIssue]) {
+    if issues.is_empty() {
+        return;
+    }
+    
+    eprintln!("Warning: {} data has {} issue(s)", symbol, issues.len());
+    for issue in issues.iter().take(10) {
+        eprintln!("  {}", issue);
+    }
+    if issues.len() > 10 {
+        eprintln!("  ... and {} more", issues.len() - 10);
+    }
+}
+
+// Find a column by index, configured header name, or one of its aliases
+fn resolve_column(headers: &[String], name: &str, aliases: &[&str]) -> Result<usize, Box<dyn Error>> {
+    if let Ok(index) = name.parse::<usize>() {
+        return Ok(index);
+    }
+    
+    std::iter::once(name)
+        .chain(aliases.iter().copied())
+        .find_map(|candidate| headers.iter().position(|h| h.eq_ignore_ascii_case(candidate)))
+        .ok_or_else(|| format!("Column '{}' not found in header {:?}", name, headers).into())
+}
+
+// Guess the timestamp format from a sample value
+pub fn detect_timestamp_format(sample: &str) -> TimestampFormat {
+    match sample.trim().parse::<i64>() {
+        // Epoch milliseconds pass 1e11 in 1973, epoch seconds only in 5138
+        Ok(value) if value.abs() >= 100_000_000_000 => TimestampFormat::EpochMillis,
+        Ok(_) => TimestampFormat::EpochSeconds,
+        Err(_) => TimestampFormat::Iso8601,
+    }
+}
+
+pub fn parse_timestamp(raw: &str, format: &TimestampFormat) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    let raw = raw.trim();
+    let invalid = || format!("invalid timestamp '{}'", raw);
+    
+    match format {
+        TimestampFormat::Auto => parse_timestamp(raw, &detect_timestamp_format(raw)),
+        TimestampFormat::EpochSeconds => Ok(Utc.timestamp_opt(raw.parse::<i64>()?, 0).single().ok_or_else(invalid)?),
+        TimestampFormat::EpochMillis => Ok(Utc.timestamp_millis_opt(raw.parse::<i64>()?).single().ok_or_else(invalid)?),
+        TimestampFormat::Iso8601 => {
+            if let Ok(timestamp) = DateTime::parse_from_rfc3339(raw) {
+                return Ok(timestamp.with_timezone(&Utc));
+            }
+            for pattern in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+                if let Ok(timestamp) = NaiveDateTime::parse_from_str(raw, pattern) {
+                    return Ok(timestamp.and_utc());
+                }
+            }
+            let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| invalid())?;
+            Ok(date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?.and_utc())
+        }
+        TimestampFormat::Custom(pattern) => {
+            if let Ok(timestamp) = DateTime::parse_from_str(raw, pattern) {
+                return Ok(timestamp.with_timezone(&Utc));
+            }
+            if let Ok(timestamp) = NaiveDateTime::parse_from_str(raw, pattern) {
+                return Ok(timestamp.and_utc());
+            }
+            let date = NaiveDate::parse_from_str(raw, pattern).map_err(|_| invalid())?;
+            Ok(date.and_hms_opt(0, 0, 0).ok_or_else(invalid)?.and_utc())
+        }
+    }
+}
+
+// Typical spacing between bars in seconds (median of positive gaps)
+pub fn infer_bar_seconds(candles: &[Candle]) -> Option<i64> {
+    let mut spacings: Vec<i64> = candles.windows(2)
+        .map(|w| (w[1].timestamp - w[0].timestamp).num_seconds())
+        .filter(|s| *s > 0)
+        .collect();
+    
+    if spacings.is_empty() {
+        return None;
+    }
+    
+    spacings.sort_unstable();
+    Some(spacings[spacings.len() / 2])
+}
+
+// Human-readable timeframe such as "5m", "1h" or "1D" for a bar length in seconds
+pub fn timeframe_label(seconds: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const WEEK: i64 = 7 * DAY;
+    
+    if seconds % WEEK == 0 {
+        format!("{}W", seconds / WEEK)
+    } else if seconds % DAY == 0 {
+        format!("{}D", seconds / DAY)
+    } else if seconds % HOUR == 0 {
+        format!("{}h", seconds / HOUR)
+    } else if seconds % MINUTE == 0 {
+        format!("{}m", seconds / MINUTE)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+// Bar length in seconds for a timeframe label such as "30s", "5m", "1h", "1D" or "1W"
+pub fn timeframe_seconds(label: &str) -> Option<i64> {
+    let label = label.trim();
+    let split = label.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = label.split_at(split);
+    let count = if count.is_empty() { 1 } else { count.parse::<i64>().ok()? };
+    
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" | "H" => 60 * 60,
+        "d" | "D" => 24 * 60 * 60,
+        "w" | "W" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    
+    (count > 0).then_some(count * unit_seconds)
+}
+
+// Check bar ordering, spacing and OHLC consistency
+pub fn validate_candles(candles: &[Candle], bar_seconds: Option<i64>) -> Vec<DataIssue> {
+    let mut issues = Vec::new();
+    
+    for (index, candle) in candles.iter().enumerate() {
+        let timestamp = candle.timestamp;
+        
+        if candle.high < candle.low {
+            issues.push(DataIssue::HighBelowLow { index, timestamp });
+        } else if candle.open > candle.high || candle.open < candle.low || candle.close > candle.high || candle.close < candle.low {
+            issues.push(DataIssue::OutsideRange { index, timestamp });
+        }
+        
+        if candle.open <= 0.0 || candle.high <= 0.0 || candle.low <= 0.0 || candle.close <= 0.0 || candle.volume < 0.0 {
+            issues.push(DataIssue::InvalidValue { index, timestamp });
+        }
+        
+        let Some(previous) = index.checked_sub(1).map(|i| &candles[i]) else {
+            continue;
+        };
+        let spacing = (timestamp - previous.timestamp).num_seconds();
+        
+        if spacing < 0 {
+            issues.push(DataIssue::NonMonotonic { index, timestamp });
+        } else if spacing == 0 {
+            issues.push(DataIssue::Duplicate { index, timestamp });
+        } else if let Some(bar) = bar_seconds.filter(|b| *b > 0) {
+            // Anything longer than 1.5 bars is a gap
+            if spacing * 2 > bar * 3 {
+                issues.push(DataIssue::Gap {
+                    index,
+                    from: previous.timestamp,
+                    to: timestamp,
+                    missing_bars: (spacing / bar - 1).max(1) as usize,
+                });
+            }
+        }
+    }
+    
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{bar, day, flat, scratch_dir};
+    use chrono::{Datelike, Duration};
+    use std::cell::Cell;
+
+    // Flat daily bars given as (days after 2023-01-01, close)
+    fn series(symbol: &str, bars: &[(i64, f64)]) -> MarketData {
+        MarketData::new(symbol, "1D", bars.iter().map(|&(day, close)| flat(day, close)).collect())
+    }
+
+    #[test]
+    fn align_pair_drops_gaps_and_duplicates() {
+        // The main series repeats day 2 and misses day 4; the pair repeats day 3 and misses day 1
+        let data = series("A", &[(0, 10.0), (1, 11.0), (2, 12.0), (2, 99.0), (3, 13.0), (5, 15.0)]);
+        let pair = series("B", &[(0, 20.0), (2, 22.0), (3, 23.0), (3, 98.0), (4, 24.0), (5, 25.0)]);
+
+        let aligned = align_pair(data, pair).unwrap();
+        let pair = aligned.pair.as_ref().unwrap();
+
+        let closes = |d: &MarketData| d.candles.iter().map(|c| c.close).collect::<Vec<_>>();
+        assert_eq!(closes(&aligned), vec![10.0, 12.0, 13.0, 15.0]);
+        assert_eq!(closes(pair), vec![20.0, 22.0, 23.0, 25.0]);
+        for (a, b) in aligned.candles.iter().zip(&pair.candles) {
+            assert_eq!(a.timestamp, b.timestamp);
+        }
+    }
+
+    #[test]
+    fn align_pair_rejects_series_in_different_order() {
+        let data = series("A", &[(0, 10.0), (1, 11.0)]);
+        let pair = series("B", &[(1, 21.0), (0, 20.0)]);
+
+        assert!(align_pair(data, pair).is_err());
+    }
+
+    #[test]
+    fn load_file_parses_the_file_once_for_all_missing_ranges() {
+        let dir = scratch_dir("load-file-once");
+        let path = dir.join("bars.csv");
+        std::fs::write(&path, "").unwrap();
+        let config = BacktestConfig {
+            symbol: "TEST".to_string(),
+            start_date: "2023-01-01".to_string(),
+            end_date: "2023-01-10".to_string(),
+            store: Some(dir.join("store")),
+            ..BacktestConfig::default()
+        };
+
+        // The file only covers days 2 to 5, so the store misses a range on either side
+        let parses = Cell::new(0);
+        let read = |_: &Path| {
+            parses.set(parses.get() + 1);
+            Ok((series("TEST", &[(2, 12.0), (3, 13.0), (4, 14.0), (5, 15.0)]), Vec::new()))
+        };
+
+        let data = load_file(&path, &config, read).unwrap();
+        assert_eq!(data.candles.len(), 4);
+        assert_eq!(parses.get(), 1);
+
+        let data = load_file(&path, &config, read).unwrap();
+        assert_eq!(data.candles.len(), 4);
+        assert_eq!(parses.get(), 1);
+    }
+
+    #[test]
+    fn epoch_millis_start_at_1e11() {
+        assert_eq!(detect_timestamp_format("1672531200"), TimestampFormat::EpochSeconds);
+        assert_eq!(detect_timestamp_format("99999999999"), TimestampFormat::EpochSeconds);
+        assert_eq!(detect_timestamp_format("100000000000"), TimestampFormat::EpochMillis);
+        assert_eq!(detect_timestamp_format(" 1672531200000 "), TimestampFormat::EpochMillis);
+        assert_eq!(detect_timestamp_format("2023-01-01"), TimestampFormat::Iso8601);
+
+        assert_eq!(parse_timestamp("1672531200", &TimestampFormat::Auto).unwrap(), day(0));
+        assert_eq!(parse_timestamp("1672531200000", &TimestampFormat::Auto).unwrap(), day(0));
+        // Read as seconds, a millisecond timestamp lands some 50,000 years out
+        assert_eq!(parse_timestamp("1672531200000", &TimestampFormat::EpochSeconds).unwrap().year(), 54970);
+    }
+
+    #[test]
+    fn iso_and_custom_timestamps() {
+        let iso = ["2023-01-01T00:00:00Z", "2023-01-01T02:00:00+02:00", "2023-01-01T00:00:00.000", "2023-01-01 00:00:00", "2023-01-01 00:00", "2023-01-01"];
+        for raw in iso {
+            assert_eq!(parse_timestamp(raw, &TimestampFormat::Iso8601).unwrap(), day(0), "{}", raw);
+        }
+        assert!(parse_timestamp("01/01/2023", &TimestampFormat::Iso8601).is_err());
+
+        let custom = |pattern: &str| TimestampFormat::Custom(pattern.to_string());
+        assert_eq!(parse_timestamp("02/01/2023 13:30", &custom("%d/%m/%Y %H:%M")).unwrap(), day(1) + Duration::minutes(13 * 60 + 30));
+        assert_eq!(parse_timestamp("02/01/2023", &custom("%d/%m/%Y")).unwrap(), day(1));
+        assert_eq!(parse_timestamp("02/01/2023 01:00 +0100", &custom("%d/%m/%Y %H:%M %z")).unwrap(), day(1));
+        assert!(parse_timestamp("2023-01-02", &custom("%d/%m/%Y")).is_err());
+    }
+
+    #[test]
+    fn csv_columns_resolve_through_aliases() {
+        let path = scratch_dir("csv-aliases").join("bars.csv");
+        std::fs::write(&path, "Date;O;H;L;C;Vol\n02/01/2023;10;12;9;11;5\n03/01/2023;11;13;10;12;6\n").unwrap();
+        let schema = DataSchema {
+            delimiter: ';',
+            timestamp_format: TimestampFormat::Custom("%d/%m/%Y".to_string()),
+            ..DataSchema::default()
+        };
+
+        let (data, issues) = load_csv_with_schema(&path, "TEST", &schema).unwrap();
+        assert!(issues.is_empty());
+        assert_eq!(data.timeframe, "1D");
+        let rows: Vec<_> = data.candles.iter().map(|c| (c.timestamp, c.open, c.high, c.low, c.close, c.volume)).collect();
+        assert_eq!(rows, vec![(day(1), 10.0, 12.0, 9.0, 11.0, 5.0), (day(2), 11.0, 13.0, 10.0, 12.0, 6.0)]);
+    }
+
+    #[test]
+    fn bar_length_is_the_median_spacing() {
+        let hours = |offsets: &[i64]| -> Vec<Candle> {
+            offsets.iter().map(|h| Candle { timestamp: day(0) + Duration::hours(*h), ..flat(0, 10.0) }).collect()
+        };
+
+        // A weekend-sized gap and a repeated bar do not move the median off one hour
+        assert_eq!(infer_bar_seconds(&hours(&[0, 1, 2, 2, 50, 51, 52])), Some(3600));
+        assert_eq!(infer_bar_seconds(&hours(&[0, 4, 8, 9])), Some(4 * 3600));
+        assert_eq!(infer_bar_seconds(&hours(&[0])), None);
+        assert_eq!(infer_bar_seconds(&hours(&[3, 3])), None);
+    }
+
+    #[test]
+    fn timeframe_labels_use_the_largest_whole_unit() {
+        let labels: Vec<String> = [45, 60, 90, 300, 3600, 4 * 3600, 86400, 2 * 86400, 7 * 86400].into_iter().map(timeframe_label).collect();
+        assert_eq!(labels, ["45s", "1m", "90s", "5m", "1h", "4h", "1D", "2D", "1W"]);
+
+        for label in &labels {
+            assert_eq!(timeframe_seconds(label).map(timeframe_label).as_ref(), Some(label));
+        }
+        assert_eq!(timeframe_seconds("15min"), Some(900));
+        assert_eq!(timeframe_seconds("h"), Some(3600));
+        assert_eq!(timeframe_seconds("0h"), None);
+        assert_eq!(timeframe_seconds("1y"), None);
+    }
+
+    #[test]
+    fn validation_reports_every_issue() {
+        let candles = vec![
+            bar(0, 10.0, 11.0, 9.0, 10.0),
+            bar(1, 10.0, 9.0, 11.0, 10.0),
+            bar(2, 12.0, 11.0, 9.0, 10.0),
+            flat(3, 0.0),
+            flat(3, 10.0),
+            flat(2, 10.0),
+            Candle { volume: -1.0, ..flat(6, 10.0) },
+        ];
+        let issues = validate_candles(&candles, Some(86400));
+
+        assert_eq!(issues.len(), 7, "{:?}", issues);
+        assert!(matches!(issues[0], DataIssue::HighBelowLow { index: 1, .. }));
+        assert!(matches!(issues[1], DataIssue::OutsideRange { index: 2, .. }));
+        assert!(matches!(issues[2], DataIssue::InvalidValue { index: 3, .. }));
+        assert!(matches!(issues[3], DataIssue::Duplicate { index: 4, .. }));
+        assert!(matches!(issues[4], DataIssue::NonMonotonic { index: 5, .. }));
+        assert!(matches!(issues[5], DataIssue::InvalidValue { index: 6, .. }));
+        let DataIssue::Gap { index, from, to, missing_bars } = issues[6] else {
+            panic!("expected a gap, got {:?}", issues[6]);
+        };
+        assert_eq!((index, from, to, missing_bars), (6, day(2), day(6), 3));
+    }
+}
@@ -1,61 +1,305 @@
-use crate::models::{Candle, MarketData, Trade, TradeDirection};
-use chrono::{DateTime, Utc};
+use crate::config::BacktestConfig;
+use crate::costs::CostModel;
+use crate::matching::{Fill, MatchingEngine};
+use crate::models::{Candle, MarketData, Order, Trade, TradeDirection};
+use crate::registry::{self, Configurable, Descriptor, ParamSpec, Params, Registration};
+use chrono::{DateTime, Timelike, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::error::Error;
+use std::ops::Range;
+
+const DAY_SECONDS: usize = 24 * 60 * 60;
+const MIN_CHILD_SIZE: f64 = 1e-9;
 
 /// Execution Algorithm trait for implementing various order execution strategies
+///
+/// Algorithms decide a schedule of child orders; the children are worked through the
+/// matching engine, so they fill at bar opens and may fill partially under a volume cap,
+/// and are booked with the commission and slippage models of `config`. With a
+/// `PassivePlacement` the children rest as limit orders instead and only cross the spread
+/// to catch up with the schedule.
 pub trait ExecutionAlgorithm {
     fn name(&self) -> &str;
-    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>) -> Result<Vec<Trade>, Box<dyn Error>>;
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>>;
+}
+
+// Work the parent order along `schedule`, a child size for `candles[index]` per entry, and
+// book the resulting fills, taking at most `volume_cap` of any bar's volume. Without a
+// placement every child is a market order at the bar's open, and unfilled child size keeps
+// working on later bars. With a passive placement each bar rests one limit order for
+// whatever the schedule has due so far; what it does not fill rolls over into the next
+// bar's limit, a market order catches up once the shortfall against the schedule reaches
+// `catch_up` of the order, and the last bar sends the rest to market.
+fn execute_schedule(
+    candles: &[&Candle],
+    symbol: &str,
+    direction: TradeDirection,
+    schedule: &[(usize, f64)],
+    placement: Option<&PassivePlacement>,
+    volume_cap: Option<f64>,
+    config: &BacktestConfig,
+) -> Vec<Trade> {
+    // The algorithm's own cap on its share of each bar's volume tightens the engine's
+    let mut matching = config.matching.clone();
+    if let Some(cap) = volume_cap {
+        matching.max_volume_participation = Some(matching.max_volume_participation.map_or(cap, |p| p.min(cap)));
+    }
+    let participation = matching.max_volume_participation;
+
+    let mut engine = MatchingEngine::new(matching);
+    let mut costs = CostModel::from_config(config);
+    let mut trades = Vec::new();
+    let slice = |index: usize| schedule.iter().filter(move |(i, size)| *i == index && *size > 0.0).map(|(_, size)| *size);
+
+    let Some(placement) = placement else {
+        for (index, candle) in candles.iter().enumerate() {
+            for size in slice(index) {
+                engine.submit(Order::market(symbol, direction, size));
+            }
+            
+            for fill in engine.process_bar(symbol, candle) {
+                trades.push(costs.book(fill, candle));
+            }
+        }
+        return trades;
+    };
+
+    // Seeded per order, so a run repeats exactly but orders do not share their fill draws
+    let start = candles.first().map_or(0, |c| c.timestamp.timestamp() as u64);
+    let mut rng = StdRng::seed_from_u64(placement.seed ^ start);
+
+    let total: f64 = schedule.iter().map(|(_, size)| size.max(0.0)).sum();
+    let (mut due, mut filled) = (0.0, 0.0);
+    for (index, candle) in candles.iter().enumerate() {
+        let last = index + 1 == candles.len();
+        let working: f64 = engine.open_orders().iter().map(|w| w.remaining()).sum();
+
+        // Behind what the schedule had due before this bar: take liquidity for the gap
+        let behind = due - filled - working;
+        let aggressive = if last {
+            total - filled - working
+        } else if behind > placement.catch_up * total {
+            behind
+        } else {
+            0.0
+        };
+        if aggressive > MIN_CHILD_SIZE {
+            engine.submit(Order::market(symbol, direction, aggressive));
+        }
+
+        due += slice(index).sum::<f64>();
+        let passive = if last { 0.0 } else { (due - filled - working - aggressive.max(0.0)).max(0.0) };
+
+        let mut available = participation.map_or(f64::INFINITY, |p| p * candle.volume);
+        for fill in engine.process_bar(symbol, candle) {
+            available -= fill.size;
+            filled += fill.size;
+            trades.push(costs.book(fill, candle));
+        }
+
+        if passive > MIN_CHILD_SIZE {
+            let limit = placement.limit_price(direction, candle.open, index.checked_sub(1).map(|i| candles[i]));
+            let probability = placement.fill_probability(direction, limit, candle);
+            // Volume spreads evenly over the range, so only the share traded beyond the limit can fill it
+            let size = passive.min(probability * candle.volume).min(available.max(0.0));
+            if rng.gen_bool(probability) && size > MIN_CHILD_SIZE {
+                filled += size;
+                // The limit lives for this bar only and never rests in the matching engine
+                let fill = Fill {
+                    order_id: 0,
+                    symbol: symbol.to_string(),
+                    direction,
+                    timestamp: candle.timestamp,
+                    price: limit,
+                    size,
+                    taker: false,
+                    partial: size < passive - MIN_CHILD_SIZE,
+                };
+                trades.push(costs.book(fill, candle));
+            }
+        }
+    }
+    
+    trades
+}
+
+/// Passive child order placement shared by the execution algorithms
+///
+/// Each bar's child rests as a limit `limit_offset` of the previous bar's high-low range
+/// inside the open (below it for buys, above it for sells), so it fills at its limit as a
+/// maker. Bars only show their range, so whether a limit fills is drawn from a generator
+/// seeded with `seed` and the order's start: it fills with probability equal to the fraction
+/// of the bar's range beyond the limit, and then for at most the volume traded there
+/// (assuming volume is spread evenly over the range).
+#[derive(Debug, Clone, Copy)]
+pub struct PassivePlacement {
+    pub limit_offset: f64,
+    pub catch_up: f64, // Shortfall against the schedule, as a fraction of the order, that crosses the spread
+    pub seed: u64,
+}
+
+impl PassivePlacement {
+    // Placement parameters every execution algorithm accepts
+    fn params() -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::integer("passive", 0, 0, 1, "1 works children as limit orders inside the bar instead of market orders"),
+            ParamSpec::float("limit_offset", 0.25, 0.0, 1.0, "How far inside the open passive limits rest, as a fraction of the previous bar's range"),
+            ParamSpec::float("catch_up", 0.1, 0.0, 1.0, "Shortfall against the schedule, as a fraction of the order, that sends a market order"),
+            ParamSpec::integer("fill_seed", 42, 0, u32::MAX as usize, "Seed for drawing whether passive limits fill"),
+        ]
+    }
+
+    fn from_params(params: &Params) -> Option<Self> {
+        (params.integer("passive") == 1).then(|| Self {
+            limit_offset: params.float("limit_offset"),
+            catch_up: params.float("catch_up"),
+            seed: params.integer("fill_seed") as u64,
+        })
+    }
+
+    // Limit price of a child placed at `open`, inside by a fraction of the previous bar's range
+    pub fn limit_price(&self, direction: TradeDirection, open: f64, previous: Option<&Candle>) -> f64 {
+        let range = previous.map_or(0.0, |c| c.high - c.low);
+        open - direction.sign() * self.limit_offset * range
+    }
+
+    // Probability that a limit at `limit` fills on `candle`
+    pub fn fill_probability(&self, direction: TradeDirection, limit: f64, candle: &Candle) -> f64 {
+        let range = candle.high - candle.low;
+        let beyond = match direction {
+            TradeDirection::Long => limit - candle.low,
+            TradeDirection::Short => candle.high - limit,
+        };
+        if beyond < 0.0 {
+            return 0.0;
+        }
+
+        // A bar that never moved traded all its volume at the limit
+        if range > 0.0 { (beyond / range).min(1.0) } else { 1.0 }
+    }
 }
 
 /// Volume-Weighted Average Price (VWAP) execution algorithm
 /// 
-/// VWAP tries to execute orders close to the volume-weighted average price
-/// by distributing the order in proportion to expected volume profile.
+/// VWAP tries to execute orders close to the volume-weighted average price by trading along
+/// a forecast of the intraday volume curve. The forecast is the average share of a day's
+/// volume traded in each time-of-day bucket over the `lookback_days` days before the order,
+/// so the schedule never looks at the volume of the bars it trades on. Each bucket's slice
+/// is spread evenly over its bars and capped at `participation_rate` of the bucket's forecast
+/// volume, with what the cap holds back carried into later buckets and any rest sent with the
+/// last bar. With `reforecast`, the day's volume surprise so far (realized over forecast
+/// volume of the bars already closed) scales the forecast of the next bucket.
+#[allow(clippy::upper_case_acronyms)]
 pub struct VWAP {
     pub name: String,
     pub num_buckets: usize,
-    pub participation_rate: f64, // Target participation rate (0.0-1.0)
+    pub participation_rate: f64, // Highest share of a bucket's forecast volume to trade (0.0-1.0)
+    pub lookback_days: usize,
+    pub reforecast: bool,
+    pub placement: Option<PassivePlacement>,
 }
 
 impl VWAP {
-    pub fn new(num_buckets: usize, participation_rate: f64) -> Self {
+    pub fn new(num_buckets: usize, participation_rate: f64, lookback_days: usize, reforecast: bool) -> Self {
         Self {
-            name: format!("VWAP_{}_buckets_{:.2}rate", num_buckets, participation_rate),
-            num_buckets,
+            name: format!(
+                "VWAP_{}_buckets_{:.2}rate{}",
+                num_buckets,
+                participation_rate,
+                if reforecast { "_reforecast" } else { "" },
+            ),
+            num_buckets: num_buckets.max(1),
             participation_rate: participation_rate.clamp(0.0, 1.0),
+            lookback_days: lookback_days.max(1),
+            reforecast,
+            placement: None,
         }
     }
 
-    // Calculate historical volume profile from past data
-    #[allow(dead_code)]
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
+    }
+
+    // Time-of-day bucket of `timestamp`, the buckets splitting the UTC day evenly
+    fn bucket(&self, timestamp: DateTime<Utc>) -> usize {
+        let seconds = timestamp.num_seconds_from_midnight() as usize;
+        (seconds * self.num_buckets / DAY_SECONDS).min(self.num_buckets - 1)
+    }
+
+    // Calculate historical volume profile from past data, one `MarketData` per day: the
+    // average share of a day's volume traded in each bucket. Without any day that traded,
+    // volume is assumed flat through the day.
     fn calculate_volume_profile(&self, historical_data: &[MarketData]) -> Vec<f64> {
-        // Initialize volume buckets
         let mut volume_profile = vec![0.0; self.num_buckets];
-        let mut total_volume = 0.0;
-        
-        // Aggregate volumes across historical days
+        let mut days = 0;
+
         for data in historical_data {
-            let candles_per_bucket = (data.candles.len() as f64 / self.num_buckets as f64).ceil() as usize;
-            
-            for (i, candle) in data.candles.iter().enumerate() {
-                let bucket_idx = i / candles_per_bucket;
-                if bucket_idx < self.num_buckets {
-                    volume_profile[bucket_idx] += candle.volume;
-                    total_volume += candle.volume;
-                }
+            let total_volume = data.candles.iter().map(|c| c.volume).sum::<f64>();
+            if total_volume <= 0.0 {
+                continue;
             }
-        }
-        
-        // Normalize volume profile
-        if total_volume > 0.0 {
-            for vol in &mut volume_profile {
-                *vol /= total_volume;
+            for candle in &data.candles {
+                volume_profile[self.bucket(candle.timestamp)] += candle.volume / total_volume;
             }
+            days += 1;
+        }
+
+        if days == 0 {
+            return vec![1.0 / self.num_buckets as f64; self.num_buckets];
+        }
+        for share in &mut volume_profile {
+            *share /= days as f64;
         }
-        
         volume_profile
     }
+
+    // Up to `lookback_days` whole days of `data` before the day of `start_time`, oldest first
+    fn prior_days(&self, data: &MarketData, start_time: DateTime<Utc>) -> Vec<MarketData> {
+        let candles = &data.candles;
+        let end = candles.partition_point(|c| c.timestamp.date_naive() < start_time.date_naive());
+
+        let mut days = Vec::new();
+        let mut day_start = 0;
+        for i in 1..=end {
+            if i == end || candles[i].timestamp.date_naive() != candles[day_start].timestamp.date_naive() {
+                days.push(day_start..i);
+                day_start = i;
+            }
+        }
+
+        let skip = days.len().saturating_sub(self.lookback_days);
+        days.into_iter().skip(skip).map(|range| data.slice(range)).collect()
+    }
+}
+
+impl Configurable for VWAP {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("vwap", "Trades along a volume curve forecast from the previous days", vec![
+            ParamSpec::integer("num_buckets", 10, 1, 10000, "Time-of-day buckets the volume curve is forecast in"),
+            ParamSpec::float("participation_rate", 0.3, 0.0, 1.0, "Highest share of a bucket's forecast volume to trade"),
+            ParamSpec::integer("lookback_days", 20, 1, 1000, "Previous days the volume curve is averaged over"),
+            ParamSpec::integer("reforecast", 0, 0, 1, "1 scales each bucket's forecast by the day's volume surprise so far"),
+        ])
+        .with_params(PassivePlacement::params())
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(
+            params.integer("num_buckets"),
+            params.float("participation_rate"),
+            params.integer("lookback_days"),
+            params.integer("reforecast") == 1,
+        )
+        .with_placement(PassivePlacement::from_params(params))
+    }
 }
 
 impl ExecutionAlgorithm for VWAP {
@@ -63,12 +307,11 @@ impl ExecutionAlgorithm for VWAP {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
         let candles = &data.candles;
         
         if candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
 
         // Filter candles within the trading window
@@ -79,77 +322,86 @@ impl ExecutionAlgorithm for VWAP {
             .collect();
             
         if trading_candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
+        }
+
+        // Forecast the volume curve and the bars a day has in each bucket from the previous days
+        let history = self.prior_days(data, start_time);
+        let profile = self.calculate_volume_profile(&history);
+        let mut bars_per_bucket = vec![0.0; self.num_buckets];
+        for candle in history.iter().flat_map(|day| &day.candles) {
+            bars_per_bucket[self.bucket(candle.timestamp)] += 1.0 / history.len() as f64;
+        }
+        let mut daily_volume = history.iter().flat_map(|day| &day.candles).map(|c| c.volume).sum::<f64>() / history.len().max(1) as f64;
+
+        // Runs of consecutive bars in the same day and bucket, each expected to see its
+        // bucket's share of the day's volume in proportion to how much of the bucket it covers
+        let key = |candle: &Candle| (candle.timestamp.date_naive(), self.bucket(candle.timestamp));
+        let mut groups: Vec<Range<usize>> = Vec::new();
+        for (index, candle) in trading_candles.iter().enumerate() {
+            match groups.last_mut() {
+                Some(group) if key(trading_candles[group.start]) == key(candle) => group.end = index + 1,
+                _ => groups.push(index..index + 1),
+            }
+        }
+        let mut forecast: Vec<f64> = groups.iter()
+            .map(|group| {
+                let bucket = self.bucket(trading_candles[group.start].timestamp);
+                let coverage = if bars_per_bucket[bucket] > 0.0 { (group.len() as f64 / bars_per_bucket[bucket]).min(1.0) } else { 1.0 };
+                profile[bucket] * coverage
+            })
+            .collect();
+        if forecast.iter().sum::<f64>() <= 0.0 {
+            // The window's buckets never traded before: spread evenly, without a volume scale
+            forecast = groups.iter().map(|group| group.len() as f64).collect();
+            daily_volume = 0.0;
         }
 
-        // Use actual volume profile from data if available, otherwise use equal distribution
-        let buckets = self.num_buckets.min(trading_candles.len());
-        let candles_per_bucket = (trading_candles.len() as f64 / buckets as f64).ceil() as usize;
-        
-        let mut total_volume = trading_candles.iter().map(|c| c.volume).sum::<f64>();
         let mut remaining_size = order_size;
+        let mut schedule = Vec::new();
+        let (mut realized, mut expected) = (0.0_f64, 0.0_f64); // Today's volume so far and its forecast
         
-        // Generate trades based on volume profile
-        for (i, candle_chunk) in trading_candles.chunks(candles_per_bucket).enumerate() {
-            let bucket_volume = candle_chunk.iter().map(|c| c.volume).sum::<f64>();
-            let volume_ratio = bucket_volume / total_volume;
+        for (g, group) in groups.iter().enumerate() {
+            let day = trading_candles[group.start].timestamp.date_naive();
+            if g > 0 && trading_candles[groups[g - 1].start].timestamp.date_naive() != day {
+                (realized, expected) = (0.0, 0.0);
+            }
+
+            // Volume surprise of today's closed bars, bounded so one odd bar cannot swamp the curve
+            let surprise = if self.reforecast && expected > 0.0 { (realized / expected).clamp(0.25, 4.0) } else { 1.0 };
+            let weight = forecast[g] * surprise;
+            let later = forecast[g + 1..].iter().sum::<f64>();
             
-            // Calculate size to execute in this bucket
-            let bucket_size = order_size * volume_ratio * self.participation_rate;
-            let size_to_execute = remaining_size.min(bucket_size);
+            // Trade the bucket's share of what is left, within the participation cap; the
+            // last bucket takes whatever remains
+            let mut size_to_execute = if later > 0.0 { remaining_size * weight / (weight + later) } else { remaining_size };
+            if daily_volume > 0.0 && g + 1 < groups.len() {
+                size_to_execute = size_to_execute.min(self.participation_rate * weight * daily_volume);
+            }
             
             if size_to_execute > 0.0 {
-                // Distribute size within the bucket proportionally to each candle's volume
-                for candle in candle_chunk {
-                    let candle_volume_ratio = candle.volume / bucket_volume;
-                    let candle_size = size_to_execute * candle_volume_ratio;
-                    
-                    if candle_size > 0.0 {
-                        let costs = candle.close * candle_size * 0.001; // 0.1% commission
-                        
-                        trades.push(Trade {
-                            timestamp: candle.timestamp,
-                            symbol: data.symbol.clone(),
-                            direction,
-                            price: candle.close,
-                            size: candle_size,
-                            costs,
-                        });
-                        
-                        remaining_size -= candle_size;
-                    }
+                for index in group.clone() {
+                    schedule.push((index, size_to_execute / group.len() as f64));
                 }
+                remaining_size -= size_to_execute;
             }
-            
-            // Adjust total volume for next buckets
-            total_volume -= bucket_volume;
-        }
-        
-        // If there's any remaining size due to rounding, execute at the last candle
-        if remaining_size > 0.01 {
-            let last_candle = trading_candles.last().unwrap();
-            let costs = last_candle.close * remaining_size * 0.001;
-            
-            trades.push(Trade {
-                timestamp: last_candle.timestamp,
-                symbol: data.symbol.clone(),
-                direction,
-                price: last_candle.close,
-                size: remaining_size,
-                costs,
-            });
+
+            realized += trading_candles[group.clone()].iter().map(|c| c.volume).sum::<f64>();
+            expected += forecast[g] * daily_volume;
         }
 
-        Ok(trades)
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &schedule, self.placement.as_ref(), None, config))
     }
 }
 
 /// Time-Weighted Average Price (TWAP) execution algorithm
 /// 
 /// TWAP evenly distributes the order over time in fixed-size chunks.
+#[allow(clippy::upper_case_acronyms)]
 pub struct TWAP {
     pub name: String,
     pub num_slices: usize,
+    pub placement: Option<PassivePlacement>,
 }
 
 impl TWAP {
@@ -157,8 +409,32 @@ impl TWAP {
         Self {
             name: format!("TWAP_{}_slices", num_slices),
             num_slices,
+            placement: None,
         }
     }
+
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
+    }
+}
+
+impl Configurable for TWAP {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("twap", "Trades equal slices at evenly spaced times", vec![
+            ParamSpec::integer("num_slices", 12, 1, 10000, "Child orders the parent is split into"),
+        ])
+        .with_params(PassivePlacement::params())
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("num_slices"))
+        .with_placement(PassivePlacement::from_params(params))
+    }
 }
 
 impl ExecutionAlgorithm for TWAP {
@@ -166,12 +442,11 @@ impl ExecutionAlgorithm for TWAP {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
         let candles = &data.candles;
         
         if candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
 
         // Filter candles within the trading window
@@ -182,7 +457,7 @@ impl ExecutionAlgorithm for TWAP {
             .collect();
             
         if trading_candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
 
         // Calculate slices (use min of requested slices or available candles)
@@ -190,61 +465,44 @@ impl ExecutionAlgorithm for TWAP {
         let slice_size = order_size / num_slices as f64;
         let candles_per_slice = (trading_candles.len() as f64 / num_slices as f64).ceil() as usize;
         
-        // Generate trades based on time slices
+        // Schedule one child order per time slice
         let mut remaining_size = order_size;
+        let mut schedule = Vec::new();
         
-        for (i, candles_chunk) in trading_candles.chunks(candles_per_slice).enumerate() {
+        for (chunk_idx, candles_chunk) in trading_candles.chunks(candles_per_slice).enumerate() {
             if candles_chunk.is_empty() || remaining_size <= 0.0 {
                 break;
             }
             
             // Use the middle candle of each chunk for execution
-            let candle_idx = candles_chunk.len() / 2;
-            let candle = candles_chunk[candle_idx];
-            
+            let candle_idx = chunk_idx * candles_per_slice + candles_chunk.len() / 2;
             let slice_execution_size = slice_size.min(remaining_size);
-            let costs = candle.close * slice_execution_size * 0.001; // 0.1% commission
-            
-            trades.push(Trade {
-                timestamp: candle.timestamp,
-                symbol: data.symbol.clone(),
-                direction,
-                price: candle.close,
-                size: slice_execution_size,
-                costs,
-            });
+            schedule.push((candle_idx, slice_execution_size));
             
             remaining_size -= slice_execution_size;
         }
         
-        // If there's any remaining size due to rounding, execute at the last candle
+        // If there's any remaining size due to rounding, send it with the last candle
         if remaining_size > 0.01 {
-            let last_candle = trading_candles.last().unwrap();
-            let costs = last_candle.close * remaining_size * 0.001;
-            
-            trades.push(Trade {
-                timestamp: last_candle.timestamp,
-                symbol: data.symbol.clone(),
-                direction,
-                price: last_candle.close,
-                size: remaining_size,
-                costs,
-            });
+            schedule.push((trading_candles.len() - 1, remaining_size));
         }
 
-        Ok(trades)
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &schedule, self.placement.as_ref(), None, config))
     }
 }
 
 /// Implementation Shortfall (IS) execution algorithm
 /// 
-/// Implementation Shortfall aims to minimize the difference between the decision price
-/// and the average execution price, balancing market impact and timing risk.
+/// Implementation Shortfall aims to limit the difference between the decision price and the
+/// average execution price by front-loading the order: a share scaled by urgency trades at
+/// once and the rest decays geometrically at a rate set by risk aversion and volatility.
+/// This is a heuristic; `AlmgrenChriss` computes the cost and risk optimal trajectory.
 pub struct ImplementationShortfall {
     pub name: String,
     pub urgency: f64, // 0.0 (passive) to 1.0 (urgent)
     pub initial_pct: f64, // Initial execution percentage
     pub risk_aversion: f64,
+    pub placement: Option<PassivePlacement>,
 }
 
 impl ImplementationShortfall {
@@ -257,32 +515,28 @@ impl ImplementationShortfall {
             urgency,
             initial_pct,
             risk_aversion,
+            placement: None,
         }
     }
-    
-    // Calculate market impact cost based on order size and liquidity
-    fn estimate_market_impact(&self, price: f64, size: f64, avg_volume: f64) -> f64 {
-        // Simple market impact model: impact = price * size^0.6 / (avg_volume * 10)
-        let market_impact = price * (size.powf(0.6) / (avg_volume * 10.0));
-        market_impact.min(price * 0.01) // Cap impact at 1% of price
+
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
     }
     
-    // Calculate the optimal trading schedule based on Almgren-Chriss model
-    fn calculate_trading_schedule(&self, 
-        order_size: f64, 
-        num_periods: usize,
-        volatility: f64, 
-        avg_volume: f64,
-        avg_price: f64
-    ) -> Vec<f64> {
+    // Front-loaded schedule: the urgency-scaled initial share, then a geometric decay of what
+    // is left, faster for higher risk aversion and volatility, with the rest in the last period
+    fn front_loaded_schedule(&self, order_size: f64, num_periods: usize, volatility: f64) -> Vec<f64> {
         let mut schedule = Vec::with_capacity(num_periods);
         
-        // Simplified Almgren-Chriss model parameters
+        // Fixed scale of the decay rate
         let market_impact_factor: f64 = 0.1;
         let temp_impact_factor: f64 = 0.05;
         let tau = self.risk_aversion * volatility.powi(2);
-        
-        // Calculate κ (kappa) parameter
         let kappa = (market_impact_factor / (temp_impact_factor * 0.5)).sqrt();
         
         // Calculate remaining size at each period
@@ -321,17 +575,33 @@ impl ImplementationShortfall {
     }
 }
 
+impl Configurable for ImplementationShortfall {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("implementation_shortfall", "Front-loads trading to limit drift from the decision price", vec![
+            ParamSpec::float("urgency", 0.5, 0.0, 1.0, "0 trades passively, 1 trades as fast as possible"),
+            ParamSpec::float("initial_pct", 0.2, 0.0, 1.0, "Fraction of the order traded immediately, scaled by urgency"),
+            ParamSpec::float("risk_aversion", 0.3, 0.0, 100.0, "Weight of timing risk against market impact"),
+        ])
+        .with_params(PassivePlacement::params())
+        .with_alias("is")
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.float("urgency"), params.float("initial_pct"), params.float("risk_aversion"))
+        .with_placement(PassivePlacement::from_params(params))
+    }
+}
+
 impl ExecutionAlgorithm for ImplementationShortfall {
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
         let candles = &data.candles;
         
         if candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
 
         // Filter candles within the trading window
@@ -342,13 +612,9 @@ impl ExecutionAlgorithm for ImplementationShortfall {
             .collect();
             
         if trading_candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
         
-        // Calculate average volume and volatility for market impact estimation
-        let avg_volume: f64 = trading_candles.iter().map(|c| c.volume).sum::<f64>() / trading_candles.len() as f64;
-        let avg_price: f64 = trading_candles.iter().map(|c| c.close).sum::<f64>() / trading_candles.len() as f64;
-        
         // Calculate price volatility
         let returns: Vec<f64> = trading_candles.windows(2)
             .map(|w| (w[1].close - w[0].close) / w[0].close)
@@ -369,17 +635,12 @@ impl ExecutionAlgorithm for ImplementationShortfall {
         let num_periods = num_periods.max(2); // Ensure at least 2 periods
         
         // Calculate trading schedule
-        let schedule = self.calculate_trading_schedule(
-            order_size, 
-            num_periods,
-            volatility,
-            avg_volume,
-            avg_price
-        );
+        let schedule = self.front_loaded_schedule(order_size, num_periods, volatility);
         
-        // Execute trades according to schedule
+        // Place child orders according to schedule
         let candles_per_period = trading_candles.len() / num_periods;
         let candles_per_period = candles_per_period.max(1);
+        let mut child_orders = Vec::new();
         
         for (i, size_to_execute) in schedule.iter().enumerate() {
             if *size_to_execute <= 0.0 {
@@ -397,32 +658,10 @@ impl ExecutionAlgorithm for ImplementationShortfall {
             };
             
             // Ensure we don't go out of bounds
-            let candle_idx = candle_idx.min(trading_candles.len() - 1);
-            let candle = trading_candles[candle_idx];
-            
-            // Calculate market impact
-            let base_price = candle.close;
-            let impact = self.estimate_market_impact(base_price, *size_to_execute, avg_volume);
-            
-            // Adjust price based on market impact and direction
-            let execution_price = match direction {
-                TradeDirection::Long => base_price + impact, // Buy price is higher due to impact
-                TradeDirection::Short => base_price - impact, // Sell price is lower due to impact
-            };
-            
-            let costs = execution_price * *size_to_execute * 0.001; // 0.1% commission
-            
-            trades.push(Trade {
-                timestamp: candle.timestamp,
-                symbol: data.symbol.clone(),
-                direction,
-                price: execution_price,
-                size: *size_to_execute,
-                costs,
-            });
+            child_orders.push((candle_idx.min(trading_candles.len() - 1), *size_to_execute));
         }
-
-        Ok(trades)
+        
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &child_orders, self.placement.as_ref(), None, config))
     }
 }
 
@@ -436,6 +675,7 @@ pub struct AdaptiveMarketExecution {
     pub max_participation_rate: f64,
     pub volatility_factor: f64,
     pub momentum_lookback: usize,
+    pub placement: Option<PassivePlacement>,
 }
 
 impl AdaptiveMarketExecution {
@@ -453,8 +693,18 @@ impl AdaptiveMarketExecution {
             max_participation_rate: max_rate.clamp(0.0, 1.0),
             volatility_factor,
             momentum_lookback: momentum_lookback.max(5),
+            placement: None,
         }
     }
+
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
+    }
     
     // Calculate price momentum 
     fn calculate_momentum(&self, candles: &[&Candle], current_idx: usize) -> f64 {
@@ -518,17 +768,41 @@ impl AdaptiveMarketExecution {
     }
 }
 
+impl Configurable for AdaptiveMarketExecution {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("adaptive", "Varies its participation rate with momentum and volatility", vec![
+            ParamSpec::float("base_rate", 0.3, 0.0, 1.0, "Participation rate in neutral conditions"),
+            ParamSpec::float("min_rate", 0.1, 0.0, 1.0, "Lowest participation rate"),
+            ParamSpec::float("max_rate", 0.6, 0.0, 1.0, "Highest participation rate"),
+            ParamSpec::float("volatility_factor", 0.5, 0.0, 10.0, "How strongly volatility slows trading down"),
+            ParamSpec::integer("momentum_lookback", 10, 5, 1000, "Bars in the momentum estimate"),
+        ])
+        .with_params(PassivePlacement::params())
+        .with_check(|p| registry::at_most(p, "min_rate", "max_rate"))
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(
+            params.float("base_rate"),
+            params.float("min_rate"),
+            params.float("max_rate"),
+            params.float("volatility_factor"),
+            params.integer("momentum_lookback"),
+        )
+        .with_placement(PassivePlacement::from_params(params))
+    }
+}
+
 impl ExecutionAlgorithm for AdaptiveMarketExecution {
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
         let candles = &data.candles;
         
         if candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
 
         // Filter candles within the trading window
@@ -539,17 +813,18 @@ impl ExecutionAlgorithm for AdaptiveMarketExecution {
             .collect();
             
         if trading_candles.is_empty() {
-            return Ok(trades);
+            return Ok(Vec::new());
         }
 
         let mut remaining_size = order_size;
-        let avg_price = trading_candles.iter().map(|c| c.close).sum::<f64>() / trading_candles.len() as f64;
         
         // Use a moving window for volatility and momentum calculations
         let min_window = self.momentum_lookback + 1;
         
-        // Process each candle where we have enough history for our indicators
-        for i in min_window..trading_candles.len() {
+        // Decide after each candle's close where we have enough history for our indicators;
+        // the child order goes out with the next candle
+        let mut schedule = Vec::new();
+        for i in min_window..trading_candles.len().saturating_sub(1) {
             if remaining_size <= 0.0 {
                 break;
             }
@@ -570,52 +845,754 @@ impl ExecutionAlgorithm for AdaptiveMarketExecution {
             let candle_volume_share = candle.volume / 
                 trading_candles[i-min_window..=i].iter().map(|c| c.volume).sum::<f64>();
                 
-            // Calculate size to execute with this candle
+            // Calculate size to execute with the next candle
             let size_to_execute = (remaining_size * candle_volume_share * participation_rate)
                 .min(remaining_size);
                 
             if size_to_execute > 0.01 { // Minimum execution size
-                let costs = candle.close * size_to_execute * 0.001; // 0.1% commission
-                
-                trades.push(Trade {
-                    timestamp: candle.timestamp,
-                    symbol: data.symbol.clone(),
-                    direction,
-                    price: candle.close,
-                    size: size_to_execute,
-                    costs,
-                });
-                
+                schedule.push((i + 1, size_to_execute));
                 remaining_size -= size_to_execute;
             }
         }
         
-        // If there's any remaining size, execute at the last candle
+        // If there's any remaining size, send it with the last candle
         if remaining_size > 0.01 {
-            let last_candle = trading_candles.last().unwrap();
-            let costs = last_candle.close * remaining_size * 0.001;
+            schedule.push((trading_candles.len() - 1, remaining_size));
+        }
+
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &schedule, self.placement.as_ref(), None, config))
+    }
+}
+
+/// Price impact and risk coefficients of the Almgren-Chriss model, per bar
+///
+/// In absolute units for one order: price per unit of the asset, with time measured in bars.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ImpactModel {
+    pub price: f64, // Arrival price the costs are relative to
+    pub volatility: f64, // σ: standard deviation of the price change over one bar
+    pub temporary_impact: f64, // η: price concession per unit traded per bar
+    pub permanent_impact: f64, // γ: lasting price move per unit traded
+}
+
+/// An optimal Almgren-Chriss schedule with its expected cost and variance
+#[derive(Debug, Clone, Serialize)]
+pub struct Trajectory {
+    pub risk_aversion: f64, // Relative risk aversion the schedule is optimal for
+    pub holdings: Vec<f64>, // Units left to trade before each bar, ending at zero
+    pub trades: Vec<f64>, // Units traded in each bar
+    pub expected_cost: f64, // Expected implementation shortfall against arrival, in price times units
+    pub variance: f64, // Variance of that shortfall from price moves while trading
+}
+
+/// Almgren-Chriss optimal execution
+///
+/// Minimizes expected cost plus risk aversion times variance of the implementation shortfall
+/// for linear temporary and permanent impact, giving the trajectory
+/// `x_j = X sinh(κ(N - j)) / sinh(κN)` with one child order per bar of the window. Zero risk
+/// aversion trades in equal slices; higher values front-load to cut the exposure to price
+/// moves. The parameters are relative, so one setting suits orders of any size and price:
+/// impact is a fraction of the price for trading one average bar's volume, volatility is
+/// per bar, and risk aversion weighs variance in squared fractions of the order's notional
+/// against cost in fractions of it. Volatility (unless given) and the average bar volume
+/// are estimated on the `lookback` bars before the order; without any, volatility defaults
+/// to 1% and the order's own average rate stands in for the bar volume.
+pub struct AlmgrenChriss {
+    pub name: String,
+    pub risk_aversion: f64,
+    pub temporary_impact: f64,
+    pub permanent_impact: f64,
+    pub volatility: f64, // Per-bar return volatility, or 0 to estimate it
+    pub lookback: usize,
+    pub placement: Option<PassivePlacement>,
+}
+
+impl AlmgrenChriss {
+    pub fn new(risk_aversion: f64, temporary_impact: f64, permanent_impact: f64, volatility: f64, lookback: usize) -> Self {
+        Self {
+            name: format!("AlmgrenChriss_{}", risk_aversion),
+            risk_aversion: risk_aversion.max(0.0),
+            temporary_impact: temporary_impact.max(0.0),
+            permanent_impact: permanent_impact.max(0.0),
+            volatility: volatility.max(0.0),
+            lookback: lookback.max(2),
+            placement: None,
+        }
+    }
+
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
+    }
+
+    // Impact model for an order of `order_size` over `periods` bars from `start_time`,
+    // estimated on the bars before it
+    pub fn calibrate(&self, data: &MarketData, order_size: f64, start_time: DateTime<Utc>, periods: usize) -> ImpactModel {
+        let candles = &data.candles;
+        let end = candles.partition_point(|c| c.timestamp < start_time);
+        let history = &candles[end.saturating_sub(self.lookback)..end];
+        let price = candles.get(end).or(candles.last()).map_or(0.0, |c| c.open);
+
+        let returns: Vec<f64> = history.windows(2).filter(|w| w[0].close > 0.0).map(|w| w[1].close / w[0].close - 1.0).collect();
+        let volatility = if self.volatility > 0.0 {
+            self.volatility
+        } else if returns.len() >= 2 {
+            let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+            (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64).sqrt()
+        } else {
+            0.01
+        };
+
+        let bar_volume = if history.is_empty() {
+            order_size / periods.max(1) as f64
+        } else {
+            history.iter().map(|c| c.volume).sum::<f64>() / history.len() as f64
+        };
+        let per_unit = if bar_volume > 0.0 { price / bar_volume } else { 0.0 };
+
+        ImpactModel {
+            price,
+            volatility: volatility * price,
+            temporary_impact: self.temporary_impact * per_unit,
+            permanent_impact: self.permanent_impact * per_unit,
+        }
+    }
+
+    // Optimal schedule of `order_size` over `periods` bars for a relative `risk_aversion`
+    pub fn trajectory(&self, model: &ImpactModel, order_size: f64, periods: usize, risk_aversion: f64) -> Trajectory {
+        let n = periods.max(1);
+        let notional = model.price * order_size;
+        let lambda = if notional > 0.0 { risk_aversion / notional } else { 0.0 };
+        // Temporary impact net of the half bar of permanent impact every trade pays itself
+        let eta = model.temporary_impact - model.permanent_impact / 2.0;
+
+        let holdings: Vec<f64> = if eta <= 0.0 {
+            // Trading faster costs nothing extra, so the whole order goes at once
+            (0..=n).map(|j| if j == 0 { order_size } else { 0.0 }).collect()
+        } else {
+            let kappa_tilde_squared = lambda * model.volatility.powi(2) / eta;
+            if kappa_tilde_squared <= 0.0 {
+                (0..=n).map(|j| order_size * (1.0 - j as f64 / n as f64)).collect()
+            } else {
+                // sinh(κ(N - j)) / sinh(κN), written with decaying exponentials so long
+                // windows cannot overflow
+                let kappa = (1.0 + kappa_tilde_squared / 2.0).acosh();
+                let decay = |bars: f64| (-2.0 * kappa * bars).exp();
+                (0..=n)
+                    .map(|j| order_size * (-kappa * j as f64).exp() * (1.0 - decay((n - j) as f64)) / (1.0 - decay(n as f64)))
+                    .collect()
+            }
+        };
+
+        let trades: Vec<f64> = holdings.windows(2).map(|w| w[0] - w[1]).collect();
+        Trajectory {
+            risk_aversion,
+            expected_cost: 0.5 * model.permanent_impact * order_size.powi(2) + eta.max(0.0) * trades.iter().map(|v| v * v).sum::<f64>(),
+            variance: model.volatility.powi(2) * holdings[1..].iter().map(|x| x * x).sum::<f64>(),
+            holdings,
+            trades,
+        }
+    }
+
+    // Optimal schedules for each relative risk aversion, from cheapest and riskiest to
+    // costliest and safest
+    pub fn efficient_frontier(&self, model: &ImpactModel, order_size: f64, periods: usize, risk_aversions: &[f64]) -> Vec<Trajectory> {
+        let mut frontier: Vec<Trajectory> = risk_aversions.iter().map(|lambda| self.trajectory(model, order_size, periods, *lambda)).collect();
+        frontier.sort_by(|a, b| a.risk_aversion.total_cmp(&b.risk_aversion));
+        frontier
+    }
+}
+
+impl Configurable for AlmgrenChriss {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("almgren_chriss", "Trades along the Almgren-Chriss cost and risk optimal trajectory", vec![
+            ParamSpec::float("risk_aversion", 10.0, 0.0, 1_000_000.0, "Weight of shortfall variance against expected cost; 0 trades evenly"),
+            ParamSpec::float("temporary_impact", 0.1, 0.0, 100.0, "Price concession, as a fraction of price, for trading one average bar's volume in a bar"),
+            ParamSpec::float("permanent_impact", 0.01, 0.0, 100.0, "Lasting price move, as a fraction of price, per average bar's volume traded"),
+            ParamSpec::float("volatility", 0.0, 0.0, 1.0, "Per-bar return volatility; 0 estimates it from the bars before the order"),
+            ParamSpec::integer("lookback", 100, 2, 100000, "Bars before the order the volatility and average volume are estimated on"),
+        ])
+        .with_params(PassivePlacement::params())
+        .with_alias("ac")
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(
+            params.float("risk_aversion"),
+            params.float("temporary_impact"),
+            params.float("permanent_impact"),
+            params.float("volatility"),
+            params.integer("lookback"),
+        )
+        .with_placement(PassivePlacement::from_params(params))
+    }
+}
+
+impl ExecutionAlgorithm for AlmgrenChriss {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
+        let candles = &data.candles;
+        
+        if candles.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Filter candles within the trading window
+        let end_time = end_time.unwrap_or_else(|| candles.last().unwrap().timestamp);
+        let trading_candles: Vec<&Candle> = candles
+            .iter()
+            .filter(|c| c.timestamp >= start_time && c.timestamp <= end_time)
+            .collect();
             
-            trades.push(Trade {
-                timestamp: last_candle.timestamp,
-                symbol: data.symbol.clone(),
-                direction,
-                price: last_candle.close,
-                size: remaining_size,
-                costs,
-            });
+        if trading_candles.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = self.calibrate(data, order_size, start_time, trading_candles.len());
+        let trajectory = self.trajectory(&model, order_size, trading_candles.len(), self.risk_aversion);
+        let schedule: Vec<(usize, f64)> = trajectory.trades.into_iter().enumerate().collect();
+
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &schedule, self.placement.as_ref(), None, config))
+    }
+}
+
+/// Participation of volume (POV) execution algorithm
+///
+/// Trades a fixed share of the market's realized volume: before each bar it sends what keeps
+/// its fills at `participation_rate` of the volume traded since the order arrived, counting
+/// the last bar's volume as the forecast for the coming one. Each child stays between
+/// `min_rate` and `max_rate` of that forecast, so the algorithm catches up or backs off
+/// gradually, and no bar fills more than `volume_cap` of its actual volume. POV follows the
+/// market rather than the clock, so an order too large for the window's volume is left
+/// partly unfilled. The bar before the window forecasts the first bar; without one there is
+/// no forecast yet, so the first bar trades nothing.
+#[allow(clippy::upper_case_acronyms)]
+pub struct POV {
+    pub name: String,
+    pub participation_rate: f64,
+    pub min_rate: f64,
+    pub max_rate: f64,
+    pub volume_cap: f64, // Largest share of any bar's volume the children may fill
+    pub placement: Option<PassivePlacement>,
+}
+
+impl POV {
+    pub fn new(participation_rate: f64, min_rate: f64, max_rate: f64, volume_cap: f64) -> Self {
+        let max_rate = max_rate.clamp(0.0, 1.0);
+        Self {
+            name: format!("POV_{:.2}rate", participation_rate),
+            participation_rate: participation_rate.clamp(0.0, 1.0),
+            min_rate: min_rate.clamp(0.0, max_rate),
+            max_rate,
+            volume_cap: volume_cap.clamp(0.0, 1.0),
+            placement: None,
+        }
+    }
+
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
+    }
+}
+
+impl Configurable for POV {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("pov", "Trades a fixed share of the realized market volume", vec![
+            ParamSpec::float("participation_rate", 0.1, 0.0, 1.0, "Target share of the volume traded since the order arrived"),
+            ParamSpec::float("min_rate", 0.05, 0.0, 1.0, "Smallest child as a share of the forecast bar volume"),
+            ParamSpec::float("max_rate", 0.2, 0.0, 1.0, "Largest child as a share of the forecast bar volume"),
+            ParamSpec::float("volume_cap", 0.25, 0.001, 1.0, "Largest share of any bar's actual volume the children may fill"),
+        ])
+        .with_params(PassivePlacement::params())
+        .with_check(|p| registry::at_most(p, "min_rate", "max_rate"))
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(
+            params.float("participation_rate"),
+            params.float("min_rate"),
+            params.float("max_rate"),
+            params.float("volume_cap"),
+        )
+        .with_placement(PassivePlacement::from_params(params))
+    }
+}
+
+impl ExecutionAlgorithm for POV {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
+        let candles = &data.candles;
+        
+        if candles.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Filter candles within the trading window
+        let end_time = end_time.unwrap_or_else(|| candles.last().unwrap().timestamp);
+        let trading_candles: Vec<&Candle> = candles
+            .iter()
+            .filter(|c| c.timestamp >= start_time && c.timestamp <= end_time)
+            .collect();
+            
+        if trading_candles.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // The bar before the window forecasts the first bar's volume, when there is one
+        let first = candles.partition_point(|c| c.timestamp < start_time);
+        let mut forecast = first.checked_sub(1).map_or(0.0, |i| candles[i].volume);
+
+        let (mut realized, mut scheduled) = (0.0, 0.0);
+        let mut schedule = Vec::new();
+        for (index, candle) in trading_candles.iter().enumerate() {
+            if scheduled >= order_size {
+                break;
+            }
+
+            let due = self.participation_rate * (realized + forecast) - scheduled;
+            let size = due
+                .clamp(self.min_rate * forecast, self.max_rate * forecast)
+                .min(order_size - scheduled);
+            if size > 0.0 {
+                schedule.push((index, size));
+                scheduled += size;
+            }
+
+            realized += candle.volume;
+            forecast = candle.volume;
+        }
+
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &schedule, self.placement.as_ref(), Some(self.volume_cap), config))
+    }
+}
+
+/// Arrival price execution algorithm
+///
+/// Works the order against its arrival price, the window's first open. It trades at the
+/// even pace that finishes the order by the end of the window, scaled by `aggression` while
+/// the last close is at least `threshold_bps` better than arrival (below it for buys, above
+/// it for sells) and by `patience` otherwise, waiting for the price to come back. The last
+/// bar sends the rest. No bar fills more than `volume_cap` of its volume, and what the cap
+/// holds back keeps working on later bars.
+pub struct ArrivalPrice {
+    pub name: String,
+    pub aggression: f64,
+    pub patience: f64,
+    pub threshold_bps: f64,
+    pub volume_cap: f64, // Largest share of any bar's volume the children may fill
+    pub placement: Option<PassivePlacement>,
+}
+
+impl ArrivalPrice {
+    pub fn new(aggression: f64, patience: f64, threshold_bps: f64, volume_cap: f64) -> Self {
+        Self {
+            name: format!("ArrivalPrice_{:.1}x", aggression),
+            aggression: aggression.max(0.0),
+            patience: patience.max(0.0),
+            threshold_bps: threshold_bps.max(0.0),
+            volume_cap: volume_cap.clamp(0.0, 1.0),
+            placement: None,
+        }
+    }
+
+    // Work the children as passive limits instead of market orders
+    pub fn with_placement(mut self, placement: Option<PassivePlacement>) -> Self {
+        if placement.is_some() {
+            self.name.push_str("_passive");
+        }
+        self.placement = placement;
+        self
+    }
+}
+
+impl Configurable for ArrivalPrice {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("arrival_price", "Trades faster while the price is better than on arrival", vec![
+            ParamSpec::float("aggression", 3.0, 0.0, 100.0, "Multiple of the even pace traded while the price is favorable"),
+            ParamSpec::float("patience", 0.5, 0.0, 100.0, "Multiple of the even pace traded while it is not"),
+            ParamSpec::float("threshold_bps", 5.0, 0.0, 10000.0, "How far better than arrival the price must be to count as favorable"),
+            ParamSpec::float("volume_cap", 0.25, 0.001, 1.0, "Largest share of any bar's volume the children may fill"),
+        ])
+        .with_params(PassivePlacement::params())
+        .with_alias("ap")
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(
+            params.float("aggression"),
+            params.float("patience"),
+            params.float("threshold_bps"),
+            params.float("volume_cap"),
+        )
+        .with_placement(PassivePlacement::from_params(params))
+    }
+}
+
+impl ExecutionAlgorithm for ArrivalPrice {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, data: &MarketData, order_size: f64, direction: TradeDirection, start_time: DateTime<Utc>, end_time: Option<DateTime<Utc>>, config: &BacktestConfig) -> Result<Vec<Trade>, Box<dyn Error>> {
+        let candles = &data.candles;
+        
+        if candles.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Filter candles within the trading window
+        let end_time = end_time.unwrap_or_else(|| candles.last().unwrap().timestamp);
+        let trading_candles: Vec<&Candle> = candles
+            .iter()
+            .filter(|c| c.timestamp >= start_time && c.timestamp <= end_time)
+            .collect();
+            
+        if trading_candles.is_empty() {
+            return Ok(Vec::new());
         }
 
-        Ok(trades)
+        let arrival = trading_candles[0].open;
+        let mut remaining = order_size;
+        let mut schedule = Vec::new();
+        for index in 0..trading_candles.len() {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let bars_left = trading_candles.len() - index;
+            let size = if bars_left == 1 {
+                remaining
+            } else {
+                // Improvement on arrival as of the last close, positive when favorable
+                let price = index.checked_sub(1).map_or(arrival, |i| trading_candles[i].close);
+                let improvement = -direction.sign() * (price / arrival - 1.0) * 10_000.0;
+                let pace = if improvement >= self.threshold_bps { self.aggression } else { self.patience };
+                (pace * remaining / bars_left as f64).min(remaining)
+            };
+
+            if size > 0.0 {
+                schedule.push((index, size));
+                remaining -= size;
+            }
+        }
+
+        Ok(execute_schedule(&trading_candles, &data.symbol, direction, &schedule, self.placement.as_ref(), Some(self.volume_cap), config))
     }
 }
 
-/// Factory function to create execution algorithms by name
-pub fn create_execution_algorithm(name: &str) -> Box<dyn ExecutionAlgorithm> {
-    match name {
-        "vwap" => Box::new(VWAP::new(10, 0.3)),
-        "twap" => Box::new(TWAP::new(12)),
-        "implementation_shortfall" | "is" => Box::new(ImplementationShortfall::new(0.5, 0.2, 0.3)),
-        "adaptive" => Box::new(AdaptiveMarketExecution::new(0.3, 0.1, 0.6, 0.5, 10)),
-        _ => Box::new(TWAP::new(10)), // Default
+// Execution algorithm boxed as a trait object
+fn register<A: ExecutionAlgorithm + Configurable + 'static>() -> Registration<dyn ExecutionAlgorithm> {
+    Registration { descriptor: A::descriptor(), build: |params| Box::new(A::from_params(params)) }
+}
+
+fn registry() -> Vec<Registration<dyn ExecutionAlgorithm>> {
+    vec![
+        register::<VWAP>(),
+        register::<TWAP>(),
+        register::<ImplementationShortfall>(),
+        register::<AdaptiveMarketExecution>(),
+        register::<AlmgrenChriss>(),
+        register::<POV>(),
+        register::<ArrivalPrice>(),
+    ]
+}
+
+// Name, description and parameters of every execution algorithm
+pub fn available_algorithms() -> Vec<Descriptor> {
+    registry().into_iter().map(|r| r.descriptor).collect()
+}
+
+/// Factory function to create execution algorithms by name, overriding default parameters from `params`
+pub fn create_execution_algorithm(name: &str, params: &HashMap<String, f64>) -> Result<Box<dyn ExecutionAlgorithm>, Box<dyn Error>> {
+    Ok(registry::find(&registry(), "execution algorithm", name)?.create(params)?)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{bar, day};
+    use chrono::Duration;
+
+    const SYMBOL: &str = "TEST";
+
+    // Buy `size` on every bar with the given placement and return (day, price, size) per trade
+    fn work(bars: &[Candle], size: f64, placement: &PassivePlacement) -> Vec<(i64, f64, f64)> {
+        let candles: Vec<&Candle> = bars.iter().collect();
+        let schedule: Vec<(usize, f64)> = (0..bars.len()).map(|i| (i, size)).collect();
+        let start = bars[0].timestamp;
+
+        execute_schedule(&candles, SYMBOL, TradeDirection::Long, &schedule, Some(placement), None, &BacktestConfig::default())
+            .iter()
+            .map(|t| ((t.timestamp - start).num_days(), t.price, t.size))
+            .collect()
+    }
+
+    #[test]
+    fn catches_up_at_the_threshold_and_sends_the_rest_on_the_last_bar() {
+        // Every bar opens at its low, so a limit below the open never fills
+        let bars: Vec<Candle> = (0..10).map(|d| bar(d, 100.0 + d as f64, 101.0 + d as f64, 100.0 + d as f64, 100.0 + d as f64)).collect();
+        let placement = PassivePlacement { limit_offset: 0.25, catch_up: 0.25, seed: 1 };
+
+        // 10 due per bar against a catch-up threshold of 25: behind by 30 before bars 3 and 6
+        assert_eq!(work(&bars, 10.0, &placement), vec![(3, 103.0, 30.0), (6, 106.0, 30.0), (9, 109.0, 40.0)]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn limit_at_the_high_always_fills_passively() {
+        let bars: Vec<Candle> = (0..5).map(|d| bar(d, 100.0, 100.0, 98.0, 100.0)).collect();
+        let placement = PassivePlacement { limit_offset: 0.0, catch_up: 0.1, seed: 1 };
+
+        // The last bar sends its slice to market, at the same open
+        assert_eq!(work(&bars, 10.0, &placement), vec![(0, 100.0, 10.0), (1, 100.0, 10.0), (2, 100.0, 10.0), (3, 100.0, 10.0), (4, 100.0, 10.0)]);
+    }
+
+    #[test]
+    fn fills_are_drawn_from_the_seed() {
+        // Limits rest at 99, halfway down each bar's range, so they fill on about half the bars
+        let bars: Vec<Candle> = (0..60).map(|d| bar(d, 100.0, 100.0, 98.0, 100.0)).collect();
+        let placement = PassivePlacement { limit_offset: 0.5, catch_up: 1.0, seed: 7 };
+
+        let trades = work(&bars, 1.0, &placement);
+        assert_eq!(trades, work(&bars, 1.0, &placement));
+
+        let passive = trades.iter().filter(|(_, price, _)| *price == 99.0).count();
+        assert!((15..45).contains(&passive), "{passive} of 59 limits filled");
+        assert_ne!(trades, work(&bars, 1.0, &PassivePlacement { seed: 8, ..placement }));
+    }
+
+    fn impact() -> ImpactModel {
+        ImpactModel { price: 100.0, volatility: 1.0, temporary_impact: 0.01, permanent_impact: 0.002 }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9 * expected.abs().max(1.0), "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn trajectory_follows_the_closed_form() {
+        let (model, ac) = (impact(), AlmgrenChriss::new(0.0, 0.1, 0.01, 0.0, 100));
+        let (order_size, periods, risk_aversion) = (1_000.0, 10, 50.0);
+        let trajectory = ac.trajectory(&model, order_size, periods, risk_aversion);
+
+        // κ from cosh(κ) = 1 + κ̃²/2 with κ̃² = λσ²/η̃, where λ is per unit of notional
+        let eta = model.temporary_impact - model.permanent_impact / 2.0;
+        let lambda = risk_aversion / (model.price * order_size);
+        let kappa = (1.0 + lambda * model.volatility.powi(2) / eta / 2.0).acosh();
+        for (j, holding) in trajectory.holdings.iter().enumerate() {
+            let n = periods as f64;
+            assert_close(*holding, order_size * (kappa * (n - j as f64)).sinh() / (kappa * n).sinh());
+        }
+
+        assert_eq!(trajectory.trades.len(), periods);
+        assert_close(trajectory.trades.iter().sum(), order_size);
+        assert!(trajectory.holdings.last().unwrap().abs() < 1e-9);
+        assert_close(
+            trajectory.expected_cost,
+            0.5 * model.permanent_impact * order_size.powi(2) + eta * trajectory.trades.iter().map(|v| v * v).sum::<f64>(),
+        );
+        assert_close(trajectory.variance, model.volatility.powi(2) * trajectory.holdings[1..].iter().map(|x| x * x).sum::<f64>());
+    }
+
+    #[test]
+    fn risk_neutral_trajectory_is_twap() {
+        let ac = AlmgrenChriss::new(0.0, 0.1, 0.01, 0.0, 100);
+        for risk_aversion in [0.0, 1e-12] {
+            let trajectory = ac.trajectory(&impact(), 1_000.0, 8, risk_aversion);
+            for trade in &trajectory.trades {
+                assert!((trade - 125.0).abs() < 1e-6, "{:?}", trajectory.trades);
+            }
+        }
+
+        // Equal slices cost ½γX² + η̃X²/N
+        let trajectory = ac.trajectory(&impact(), 1_000.0, 8, 0.0);
+        assert_close(trajectory.expected_cost, 0.5 * 0.002 * 1e6 + 0.009 * 1e6 / 8.0);
+    }
+
+    #[test]
+    fn higher_risk_aversion_front_loads_along_the_frontier() {
+        let ac = AlmgrenChriss::new(0.0, 0.1, 0.01, 0.0, 100);
+        let frontier = ac.efficient_frontier(&impact(), 1_000.0, 10, &[100.0, 0.0, 10.0, 1_000.0]);
+        let risk_aversions: Vec<f64> = frontier.iter().map(|t| t.risk_aversion).collect();
+        assert_eq!(risk_aversions, vec![0.0, 10.0, 100.0, 1_000.0]);
+
+        for pair in frontier.windows(2) {
+            let (calm, averse) = (&pair[0], &pair[1]);
+            assert!(averse.trades[0] > calm.trades[0]);
+            assert!(averse.expected_cost > calm.expected_cost);
+            assert!(averse.variance < calm.variance);
+            // Every later holding is lower too
+            assert!(averse.holdings[1..].iter().zip(&calm.holdings[1..]).all(|(a, c)| a <= c));
+        }
+    }
+
+    #[test]
+    fn calibration_scales_relative_parameters_to_the_order() {
+        // Volume of 500 a bar before the order, which arrives at the open of bar 4
+        let candles: Vec<Candle> = (0..6).map(|d| Candle { volume: 500.0, ..bar(d, 50.0, 51.0, 49.0, 50.0) }).collect();
+        let data = MarketData::new("TEST", "1D", candles);
+        let ac = AlmgrenChriss::new(1.0, 0.1, 0.01, 0.02, 3);
+
+        let model = ac.calibrate(&data, 1_000.0, data.candles[4].timestamp, 10);
+        assert_close(model.price, 50.0);
+        assert_close(model.volatility, 0.02 * 50.0);
+        assert_close(model.temporary_impact, 0.1 * 50.0 / 500.0);
+        assert_close(model.permanent_impact, 0.01 * 50.0 / 500.0);
+
+        // Without a volatility it is the sample deviation of the returns before the order
+        let closes = [100.0, 110.0, 99.0, 108.9, 100.0];
+        let candles = closes.iter().enumerate().map(|(d, &c)| bar(d as i64, c, c, c, c)).collect();
+        let data = MarketData::new("TEST", "1D", candles);
+        let model = AlmgrenChriss::new(1.0, 0.1, 0.01, 0.0, 4).calibrate(&data, 10.0, data.candles[4].timestamp, 5);
+        let returns = [0.1, -0.1, 0.1];
+        let mean = returns.iter().sum::<f64>() / 3.0;
+        let deviation = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 2.0).sqrt();
+        assert!((model.volatility - deviation * 100.0).abs() < 1e-6, "{}", model.volatility);
+    }
+
+    // Daily bars from day 0 trading `volumes`, each opening and closing at its `closes` price
+    fn bars_with(volumes: &[f64], closes: &[f64]) -> MarketData {
+        let candles = volumes.iter().zip(closes).enumerate()
+            .map(|(d, (&volume, &close))| Candle { volume, ..bar(d as i64, close, close, close, close) })
+            .collect();
+        MarketData::new(SYMBOL, "1D", candles)
+    }
+
+    // Units filled on each bar of `data`
+    fn filled_per_bar(data: &MarketData, trades: &[Trade]) -> Vec<f64> {
+        data.candles.iter().map(|c| trades.iter().filter(|t| t.timestamp == c.timestamp).map(|t| t.size).sum()).collect()
+    }
+
+    fn run(algorithm: &dyn ExecutionAlgorithm, data: &MarketData, size: f64, direction: TradeDirection, start: usize) -> Vec<f64> {
+        let trades = algorithm.execute(data, size, direction, data.candles[start].timestamp, None, &BacktestConfig::default()).unwrap();
+        filled_per_bar(data, &trades)
+    }
+
+    fn assert_all_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "expected {:?}, got {:?}", expected, actual);
+        }
+    }
+
+    #[test]
+    fn volume_capped_algorithms_never_fill_more_than_their_share_of_a_bar() {
+        // Volume collapses after busy bars, so forecasts from the last bar overshoot
+        let volumes = [1_000.0, 50.0, 2_000.0, 10.0, 10.0, 3_000.0, 5.0, 400.0];
+        let data = bars_with(&volumes, &[100.0; 8]);
+        let algorithms: Vec<Box<dyn ExecutionAlgorithm>> = vec![
+            Box::new(POV::new(0.5, 0.1, 1.0, 0.2)),
+            Box::new(ArrivalPrice::new(3.0, 0.5, 5.0, 0.2)),
+        ];
+
+        for algorithm in &algorithms {
+            for direction in [TradeDirection::Long, TradeDirection::Short] {
+                let filled = run(algorithm.as_ref(), &data, 5_000.0, direction, 0);
+                assert!(filled.iter().sum::<f64>() > 0.0);
+                for (size, volume) in filled.iter().zip(volumes) {
+                    assert!(*size <= 0.2 * volume + 1e-9, "{} filled {} of {}", algorithm.name(), size, volume);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pov_children_stay_within_the_rate_bounds_of_the_last_bar() {
+        // Bar 0 comes before the window and forecasts bar 1
+        let volumes = [100.0, 200.0, 50.0, 400.0, 100.0];
+        let data = bars_with(&volumes, &[100.0; 5]);
+
+        // Far behind a 90% target, every child is the largest allowed: 20% of the last bar
+        assert_all_close(&run(&POV::new(0.9, 0.05, 0.2, 1.0), &data, 1_000.0, TradeDirection::Long, 1), &[0.0, 20.0, 40.0, 10.0, 80.0]);
+        // Far ahead of a 1% target, every child is the smallest allowed: 5% of the last bar
+        assert_all_close(&run(&POV::new(0.01, 0.05, 0.2, 1.0), &data, 1_000.0, TradeDirection::Long, 1), &[0.0, 5.0, 10.0, 2.5, 20.0]);
+        // Without a bar before the window there is no forecast for the first bar
+        assert_all_close(&run(&POV::new(0.01, 0.05, 0.2, 1.0), &data, 1_000.0, TradeDirection::Long, 0), &[0.0, 5.0, 10.0, 2.5, 20.0]);
+    }
+
+    #[test]
+    fn arrival_price_speeds_up_while_the_price_is_favorable() {
+        // Arrival at the first open of 100; the first close is 1% below it, later ones 1% above
+        let mut data = bars_with(&[1e6; 4], &[99.0, 101.0, 101.0, 101.0]);
+        data.candles[0].open = 100.0;
+        let algorithm = ArrivalPrice::new(2.0, 0.5, 5.0, 0.25);
+
+        // Each bar is paced on the close before it: a buy sees the lower close as favorable
+        let first = 0.5 * 100.0 / 4.0;
+        let second = 2.0 * (100.0 - first) / 3.0;
+        let third = 0.5 * (100.0 - first - second) / 2.0;
+        assert_all_close(&run(&algorithm, &data, 100.0, TradeDirection::Long, 0), &[first, second, third, 100.0 - first - second - third]);
+
+        // A sell sees it as unfavorable and waits, then finishes once the price is above arrival
+        let second = 0.5 * (100.0 - first) / 3.0;
+        let third = 2.0 * (100.0 - first - second) / 2.0;
+        assert_all_close(&run(&algorithm, &data, 100.0, TradeDirection::Short, 0), &[first, second, third, 0.0]);
+    }
+
+    // Bars of `hours` each, one row of volumes per day from day 0
+    fn intraday(hours: i64, days: &[Vec<f64>]) -> MarketData {
+        let candles = days.iter().enumerate()
+            .flat_map(|(d, volumes)| volumes.iter().enumerate().map(move |(i, &volume)| Candle {
+                timestamp: day(d as i64) + Duration::hours(hours * i as i64),
+                volume,
+                ..bar(0, 100.0, 100.0, 100.0, 100.0)
+            }))
+            .collect();
+        MarketData::new(SYMBOL, &format!("{}h", hours), candles)
+    }
+
+    // Units a VWAP buy of 1 fills on each bar of the last day of `data`
+    fn vwap_day(vwap: &VWAP, data: &MarketData, bars: usize) -> Vec<f64> {
+        let start = data.candles.len() - bars;
+        run(vwap, data, 1.0, TradeDirection::Long, start)[start..].to_vec()
+    }
+
+    #[test]
+    fn vwap_forecasts_from_prior_days_only() {
+        let usual = vec![40.0, 30.0, 20.0, 10.0];
+        let quiet_open = intraday(6, &[usual.clone(), usual.clone(), vec![1.0, 1.0, 1.0, 1_000.0]]);
+        let busy_open = intraday(6, &[usual.clone(), usual, vec![1_000.0, 1.0, 1.0, 1.0]]);
+        let vwap = VWAP::new(4, 0.3, 20, false);
+
+        // The order follows yesterday's curve whatever today's bars trade
+        assert_all_close(&vwap_day(&vwap, &quiet_open, 4), &[0.4, 0.3, 0.2, 0.1]);
+        assert_all_close(&vwap_day(&vwap, &busy_open, 4), &[0.4, 0.3, 0.2, 0.1]);
+    }
+
+    #[test]
+    fn vwap_scales_a_partly_covered_bucket() {
+        // Flat hourly volume, so each 6-hour bucket forecasts a quarter of the day
+        let data = intraday(1, &[vec![10.0; 24], vec![10.0; 24]]);
+        let vwap = VWAP::new(4, 0.3, 20, false);
+
+        // Starting at 03:00, the first bucket has half its bars left and half its share
+        let filled = vwap_day(&vwap, &data, 21);
+        assert!((filled[..3].iter().sum::<f64>() - 0.125 / 0.875).abs() < 1e-9, "{:?}", filled);
+    }
+
+    #[test]
+    fn vwap_reforecast_scales_the_next_bucket_by_the_volume_surprise() {
+        let usual = vec![40.0, 30.0, 20.0, 10.0];
+        let vwap = VWAP::new(4, 0.3, 20, true);
+
+        // Twice the forecast volume doubles the weight of the next bucket: 0.6 of the 0.9 left
+        let busy = intraday(6, &[usual.clone(), usual.clone(), vec![80.0, 60.0, 20.0, 10.0]]);
+        assert_all_close(&vwap_day(&vwap, &busy, 4), &[0.4, 0.4, 0.16, 0.04]);
+
+        // A surprise of 25 times the forecast counts as 4
+        let spike = intraday(6, &[usual.clone(), usual, vec![1_000.0, 30.0, 20.0, 10.0]]);
+        assert!((vwap_day(&vwap, &spike, 4)[1] - 0.6 * 1.2 / 1.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,1120 @@
+use crate::models::Candle;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::VecDeque;
+use ta::Next;
+
+/// Technical indicator updated one bar at a time
+///
+/// Every `update` costs O(1) (amortized for rolling highs and lows), so a strategy can feed
+/// each bar of a backtest without rescanning its lookback window. The output is `None`
+/// until the indicator has seen enough bars.
+pub trait Indicator {
+    type Output;
+    fn update(&mut self, candle: &Candle) -> Option<Self::Output>;
+}
+
+/// Middle, upper and lower line of a channel indicator
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bands {
+    pub middle: f64,
+    pub upper: f64,
+    pub lower: f64,
+}
+
+fn typical_price(candle: &Candle) -> f64 {
+    (candle.high + candle.low + candle.close) / 3.0
+}
+
+// Sliding window with a running sum and sum of squares
+#[derive(Debug, Clone)]
+struct RollingWindow {
+    period: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl RollingWindow {
+    fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            values: VecDeque::with_capacity(period.max(1) + 1),
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    // Add a value, returning the one that fell out of a full window
+    fn push(&mut self, value: f64) -> Option<f64> {
+        self.values.push_back(value);
+        self.sum += value;
+        self.sum_squares += value * value;
+
+        if self.values.len() > self.period {
+            let old = self.values.pop_front()?;
+            self.sum -= old;
+            self.sum_squares -= old * old;
+            return Some(old);
+        }
+        None
+    }
+
+    fn is_full(&self) -> bool {
+        self.values.len() == self.period
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.values.len() as f64
+    }
+
+    // Population standard deviation of the window
+    fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        (self.sum_squares / self.values.len() as f64 - mean * mean).max(0.0).sqrt()
+    }
+}
+
+// Highest (or lowest) value of the last `period` values, via a monotonic queue
+#[derive(Debug, Clone)]
+struct RollingExtreme {
+    period: usize,
+    highest: bool,
+    queue: VecDeque<(usize, f64)>,
+    count: usize,
+}
+
+impl RollingExtreme {
+    fn max(period: usize) -> Self {
+        Self { period: period.max(1), highest: true, queue: VecDeque::new(), count: 0 }
+    }
+
+    fn min(period: usize) -> Self {
+        Self { period: period.max(1), highest: false, queue: VecDeque::new(), count: 0 }
+    }
+
+    fn push(&mut self, value: f64) -> Option<f64> {
+        let dominated = |kept: f64| if self.highest { kept <= value } else { kept >= value };
+        while self.queue.back().is_some_and(|(_, kept)| dominated(*kept)) {
+            self.queue.pop_back();
+        }
+        self.queue.push_back((self.count, value));
+        self.count += 1;
+
+        while self.queue.front().is_some_and(|(i, _)| *i + self.period < self.count) {
+            self.queue.pop_front();
+        }
+
+        if self.count < self.period {
+            return None;
+        }
+        self.queue.front().map(|(_, v)| *v)
+    }
+}
+
+// Wilder's smoothing (alpha = 1/period), seeded with the average of the first `period` values
+#[derive(Debug, Clone)]
+struct WilderAverage {
+    period: usize,
+    seed: Vec<f64>,
+    value: Option<f64>,
+}
+
+impl WilderAverage {
+    fn new(period: usize) -> Self {
+        Self { period: period.max(1), seed: Vec::new(), value: None }
+    }
+
+    fn push(&mut self, input: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(prev) => Some(prev + (input - prev) / self.period as f64),
+            None => {
+                self.seed.push(input);
+                (self.seed.len() == self.period).then(|| self.seed.iter().sum::<f64>() / self.period as f64)
+            }
+        };
+        self.value
+    }
+}
+
+// True range, using the high-low range on the first bar
+#[derive(Debug, Clone, Default)]
+struct TrueRange {
+    prev_close: Option<f64>,
+}
+
+impl TrueRange {
+    fn push(&mut self, candle: &Candle) -> f64 {
+        let range = match self.prev_close {
+            Some(prev) => (candle.high - candle.low).max((candle.high - prev).abs()).max((candle.low - prev).abs()),
+            None => candle.high - candle.low,
+        };
+        self.prev_close = Some(candle.close);
+        range
+    }
+}
+
+/// Simple moving average of the close
+#[derive(Debug, Clone)]
+pub struct Sma {
+    window: RollingWindow,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Self { window: RollingWindow::new(period) }
+    }
+
+    pub fn push(&mut self, value: f64) -> Option<f64> {
+        self.window.push(value);
+        self.window.is_full().then(|| self.window.mean())
+    }
+}
+
+impl Indicator for Sma {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        self.push(candle.close)
+    }
+}
+
+/// Exponential moving average of the close, seeded with the SMA of the first `period` values
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    seed: Sma,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Self { period: period.max(1), seed: Sma::new(period), value: None }
+    }
+
+    pub fn push(&mut self, input: f64) -> Option<f64> {
+        let k = 2.0 / (self.period as f64 + 1.0);
+        self.value = match self.value {
+            Some(prev) => Some(input * k + prev * (1.0 - k)),
+            None => self.seed.push(input),
+        };
+        self.value
+    }
+}
+
+impl Indicator for Ema {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        self.push(candle.close)
+    }
+}
+
+/// Linearly weighted moving average of the close, newest bar weighted `period`
+#[derive(Debug, Clone)]
+pub struct Wma {
+    window: RollingWindow,
+    weighted_sum: f64,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        Self { window: RollingWindow::new(period), weighted_sum: 0.0 }
+    }
+
+    pub fn push(&mut self, value: f64) -> Option<f64> {
+        // Once the window is full, every older value loses one unit of weight
+        let previous_sum = self.window.sum;
+        let evicted = self.window.push(value);
+        let n = self.window.values.len() as f64;
+        self.weighted_sum = match evicted {
+            Some(_) => self.weighted_sum - previous_sum + n * value,
+            None => self.weighted_sum + n * value,
+        };
+
+        let weights = n * (n + 1.0) / 2.0;
+        self.window.is_full().then(|| self.weighted_sum / weights)
+    }
+}
+
+impl Indicator for Wma {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        self.push(candle.close)
+    }
+}
+
+/// Kaufman's adaptive moving average
+///
+/// The smoothing constant moves between the `fast` and `slow` EMA constants with the
+/// efficiency ratio (net change over path length) of the last `period` bars.
+#[derive(Debug, Clone)]
+pub struct Kama {
+    period: usize,
+    fast: f64,
+    slow: f64,
+    closes: VecDeque<f64>,
+    path: f64, // Sum of absolute changes over the window
+    value: Option<f64>,
+}
+
+impl Kama {
+    pub fn new(period: usize, fast_period: usize, slow_period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            fast: 2.0 / (fast_period as f64 + 1.0),
+            slow: 2.0 / (slow_period as f64 + 1.0),
+            closes: VecDeque::with_capacity(period + 2),
+            path: 0.0,
+            value: None,
+        }
+    }
+
+    pub fn efficiency_ratio(&self) -> Option<f64> {
+        if self.closes.len() <= self.period {
+            return None;
+        }
+        let change = (self.closes[self.period] - self.closes[0]).abs();
+        Some(if self.path > 0.0 { change / self.path } else { 0.0 })
+    }
+}
+
+impl Indicator for Kama {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        if let Some(last) = self.closes.back() {
+            self.path += (candle.close - last).abs();
+        }
+        self.closes.push_back(candle.close);
+        if self.closes.len() > self.period + 1
+            && let (Some(old), Some(next)) = (self.closes.pop_front(), self.closes.front()) {
+            self.path -= (next - old).abs();
+        }
+
+        let er = self.efficiency_ratio()?;
+        let sc = (er * (self.fast - self.slow) + self.slow).powi(2);
+        let prev = self.value.unwrap_or(self.closes[self.period - 1]);
+        self.value = Some(prev + sc * (candle.close - prev));
+        self.value
+    }
+}
+
+/// Relative strength index with Wilder's smoothing of gains and losses
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    gains: WilderAverage,
+    losses: WilderAverage,
+    prev_close: Option<f64>,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Self { gains: WilderAverage::new(period), losses: WilderAverage::new(period), prev_close: None }
+    }
+}
+
+impl Indicator for Rsi {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let prev = self.prev_close.replace(candle.close)?;
+        let change = candle.close - prev;
+        let gain = self.gains.push(change.max(0.0));
+        let loss = self.losses.push((-change).max(0.0));
+
+        let (gain, loss) = (gain?, loss?);
+        if loss == 0.0 {
+            return Some(if gain == 0.0 { 50.0 } else { 100.0 });
+        }
+        Some(100.0 - 100.0 / (1.0 + gain / loss))
+    }
+}
+
+/// Bollinger bands: SMA of the close plus and minus `multiplier` population standard deviations
+#[derive(Debug, Clone)]
+pub struct Bollinger {
+    window: RollingWindow,
+    multiplier: f64,
+}
+
+impl Bollinger {
+    pub fn new(period: usize, multiplier: f64) -> Self {
+        Self { window: RollingWindow::new(period), multiplier }
+    }
+}
+
+impl Indicator for Bollinger {
+    type Output = Bands;
+
+    fn update(&mut self, candle: &Candle) -> Option<Bands> {
+        self.window.push(candle.close);
+        if !self.window.is_full() {
+            return None;
+        }
+
+        let middle = self.window.mean();
+        let width = self.window.std_dev() * self.multiplier;
+        Some(Bands { middle, upper: middle + width, lower: middle - width })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MacdOutput {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/// Moving average convergence divergence: fast EMA minus slow EMA, with an EMA signal line
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast_period: usize, slow_period: usize, signal_period: usize) -> Self {
+        Self { fast: Ema::new(fast_period), slow: Ema::new(slow_period), signal: Ema::new(signal_period) }
+    }
+}
+
+impl Indicator for Macd {
+    type Output = MacdOutput;
+
+    fn update(&mut self, candle: &Candle) -> Option<MacdOutput> {
+        let fast = self.fast.push(candle.close);
+        let slow = self.slow.push(candle.close);
+        let macd = fast? - slow?;
+        let signal = self.signal.push(macd)?;
+        Some(MacdOutput { macd, signal, histogram: macd - signal })
+    }
+}
+
+/// Average true range with Wilder's smoothing
+#[derive(Debug, Clone)]
+pub struct Atr {
+    true_range: TrueRange,
+    average: WilderAverage,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Self { true_range: TrueRange::default(), average: WilderAverage::new(period) }
+    }
+}
+
+impl Indicator for Atr {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let range = self.true_range.push(candle);
+        self.average.push(range)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StochasticOutput {
+    pub k: f64,
+    pub d: f64,
+}
+
+/// Stochastic oscillator: %K locates the close in the `k_period` high-low range, %D is its SMA
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    highest: RollingExtreme,
+    lowest: RollingExtreme,
+    d: Sma,
+}
+
+impl Stochastic {
+    pub fn new(k_period: usize, d_period: usize) -> Self {
+        Self { highest: RollingExtreme::max(k_period), lowest: RollingExtreme::min(k_period), d: Sma::new(d_period) }
+    }
+}
+
+impl Indicator for Stochastic {
+    type Output = StochasticOutput;
+
+    fn update(&mut self, candle: &Candle) -> Option<StochasticOutput> {
+        let highest = self.highest.push(candle.high);
+        let lowest = self.lowest.push(candle.low);
+        let (highest, lowest) = (highest?, lowest?);
+
+        // A flat range puts the close in the middle
+        let k = if highest > lowest { (candle.close - lowest) / (highest - lowest) * 100.0 } else { 50.0 };
+        let d = self.d.push(k)?;
+        Some(StochasticOutput { k, d })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AdxOutput {
+    pub adx: f64,
+    pub plus_di: f64,
+    pub minus_di: f64,
+}
+
+/// Average directional index with the +DI and -DI lines, all with Wilder's smoothing
+#[derive(Debug, Clone)]
+pub struct Adx {
+    true_range: WilderAverage,
+    plus_dm: WilderAverage,
+    minus_dm: WilderAverage,
+    adx: WilderAverage,
+    prev: Option<Candle>,
+}
+
+impl Adx {
+    pub fn new(period: usize) -> Self {
+        Self {
+            true_range: WilderAverage::new(period),
+            plus_dm: WilderAverage::new(period),
+            minus_dm: WilderAverage::new(period),
+            adx: WilderAverage::new(period),
+            prev: None,
+        }
+    }
+}
+
+impl Indicator for Adx {
+    type Output = AdxOutput;
+
+    fn update(&mut self, candle: &Candle) -> Option<AdxOutput> {
+        let prev = self.prev.replace(candle.clone())?;
+
+        let up = candle.high - prev.high;
+        let down = prev.low - candle.low;
+        let plus = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus = if down > up && down > 0.0 { down } else { 0.0 };
+        let range = (candle.high - candle.low).max((candle.high - prev.close).abs()).max((candle.low - prev.close).abs());
+
+        let range = self.true_range.push(range);
+        let plus = self.plus_dm.push(plus);
+        let minus = self.minus_dm.push(minus);
+        let (range, plus, minus) = (range?, plus?, minus?);
+
+        let (plus_di, minus_di) = if range > 0.0 { (100.0 * plus / range, 100.0 * minus / range) } else { (0.0, 0.0) };
+        let dx = if plus_di + minus_di > 0.0 { 100.0 * (plus_di - minus_di).abs() / (plus_di + minus_di) } else { 0.0 };
+        let adx = self.adx.push(dx)?;
+        Some(AdxOutput { adx, plus_di, minus_di })
+    }
+}
+
+/// On-balance volume, starting at zero
+#[derive(Debug, Clone, Default)]
+pub struct Obv {
+    value: f64,
+    prev_close: Option<f64>,
+}
+
+impl Obv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Indicator for Obv {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        if let Some(prev) = self.prev_close {
+            if candle.close > prev {
+                self.value += candle.volume;
+            } else if candle.close < prev {
+                self.value -= candle.volume;
+            }
+        }
+        self.prev_close = Some(candle.close);
+        Some(self.value)
+    }
+}
+
+/// Volume-weighted average of the typical price since the start of the UTC day
+#[derive(Debug, Clone, Default)]
+pub struct Vwap {
+    day: Option<NaiveDate>,
+    price_volume: f64,
+    volume: f64,
+}
+
+impl Vwap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Indicator for Vwap {
+    type Output = f64;
+
+    fn update(&mut self, candle: &Candle) -> Option<f64> {
+        let day = candle.timestamp.date_naive();
+        if self.day != Some(day) {
+            *self = Self { day: Some(day), ..Self::default() };
+        }
+
+        let price = typical_price(candle);
+        self.price_volume += price * candle.volume;
+        self.volume += candle.volume;
+        Some(if self.volume > 0.0 { self.price_volume / self.volume } else { price })
+    }
+}
+
+/// Keltner channel: EMA of the typical price plus and minus `multiplier` ATRs
+#[derive(Debug, Clone)]
+pub struct Keltner {
+    middle: Ema,
+    atr: Atr,
+    multiplier: f64,
+}
+
+impl Keltner {
+    pub fn new(period: usize, atr_period: usize, multiplier: f64) -> Self {
+        Self { middle: Ema::new(period), atr: Atr::new(atr_period), multiplier }
+    }
+}
+
+impl Indicator for Keltner {
+    type Output = Bands;
+
+    fn update(&mut self, candle: &Candle) -> Option<Bands> {
+        let middle = self.middle.push(typical_price(candle));
+        let atr = self.atr.update(candle);
+        let (middle, atr) = (middle?, atr?);
+        Some(Bands { middle, upper: middle + atr * self.multiplier, lower: middle - atr * self.multiplier })
+    }
+}
+
+/// Donchian channel: highest high and lowest low of the last `period` bars, including this one
+#[derive(Debug, Clone)]
+pub struct Donchian {
+    highest: RollingExtreme,
+    lowest: RollingExtreme,
+}
+
+impl Donchian {
+    pub fn new(period: usize) -> Self {
+        Self { highest: RollingExtreme::max(period), lowest: RollingExtreme::min(period) }
+    }
+}
+
+impl Indicator for Donchian {
+    type Output = Bands;
+
+    fn update(&mut self, candle: &Candle) -> Option<Bands> {
+        let upper = self.highest.push(candle.high);
+        let lower = self.lowest.push(candle.low);
+        let (upper, lower) = (upper?, lower?);
+        Some(Bands { middle: (upper + lower) / 2.0, upper, lower })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IchimokuOutput {
+    pub tenkan: f64,
+    pub kijun: f64,
+    pub senkou_a: f64, // Cloud edges at the current bar, i.e. computed `displacement` bars ago
+    pub senkou_b: f64,
+}
+
+/// Ichimoku cloud
+///
+/// The senkou spans are plotted `displacement` bars ahead of the bar they are computed on,
+/// so the output carries the spans that land on the current bar and never looks ahead.
+#[derive(Debug, Clone)]
+pub struct Ichimoku {
+    tenkan: Donchian,
+    kijun: Donchian,
+    senkou_b: Donchian,
+    displacement: usize,
+    spans: VecDeque<(f64, f64)>,
+}
+
+impl Ichimoku {
+    pub fn new(tenkan_period: usize, kijun_period: usize, senkou_b_period: usize, displacement: usize) -> Self {
+        Self {
+            tenkan: Donchian::new(tenkan_period),
+            kijun: Donchian::new(kijun_period),
+            senkou_b: Donchian::new(senkou_b_period),
+            displacement,
+            spans: VecDeque::with_capacity(displacement + 1),
+        }
+    }
+}
+
+impl Indicator for Ichimoku {
+    type Output = IchimokuOutput;
+
+    fn update(&mut self, candle: &Candle) -> Option<IchimokuOutput> {
+        let tenkan = self.tenkan.update(candle).map(|b| b.middle);
+        let kijun = self.kijun.update(candle).map(|b| b.middle);
+        let senkou_b = self.senkou_b.update(candle).map(|b| b.middle);
+        let (tenkan, kijun, senkou_b) = (tenkan?, kijun?, senkou_b?);
+
+        self.spans.push_back(((tenkan + kijun) / 2.0, senkou_b));
+        if self.spans.len() <= self.displacement {
+            return None;
+        }
+        let (senkou_a, senkou_b) = self.spans.pop_front()?;
+        Some(IchimokuOutput { tenkan, kijun, senkou_a, senkou_b })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SuperTrendOutput {
+    pub value: f64, // Trailing band: support in an uptrend, resistance in a downtrend
+    pub uptrend: bool,
+}
+
+/// SuperTrend: ATR bands around the bar midpoint that only ever tighten until price crosses them
+#[derive(Debug, Clone)]
+pub struct SuperTrend {
+    atr: Atr,
+    multiplier: f64,
+    upper: Option<f64>,
+    lower: Option<f64>,
+    uptrend: bool,
+    prev_close: Option<f64>,
+}
+
+impl SuperTrend {
+    pub fn new(period: usize, multiplier: f64) -> Self {
+        Self { atr: Atr::new(period), multiplier, upper: None, lower: None, uptrend: true, prev_close: None }
+    }
+}
+
+impl Indicator for SuperTrend {
+    type Output = SuperTrendOutput;
+
+    fn update(&mut self, candle: &Candle) -> Option<SuperTrendOutput> {
+        let atr = self.atr.update(candle);
+        let prev_close = self.prev_close.replace(candle.close);
+        let atr = atr?;
+
+        let midpoint = (candle.high + candle.low) / 2.0;
+        let basic_upper = midpoint + self.multiplier * atr;
+        let basic_lower = midpoint - self.multiplier * atr;
+
+        // A band only moves against the trend once the previous close has broken it
+        let upper = match (self.upper, prev_close) {
+            (Some(upper), Some(close)) if basic_upper > upper && close <= upper => upper,
+            _ => basic_upper,
+        };
+        let lower = match (self.lower, prev_close) {
+            (Some(lower), Some(close)) if basic_lower < lower && close >= lower => lower,
+            _ => basic_lower,
+        };
+
+        self.uptrend = if self.uptrend { candle.close >= lower } else { candle.close > upper };
+        self.upper = Some(upper);
+        self.lower = Some(lower);
+        Some(SuperTrendOutput { value: if self.uptrend { lower } else { upper }, uptrend: self.uptrend })
+    }
+}
+
+impl ta::Open for Candle {
+    fn open(&self) -> f64 {
+        self.open
+    }
+}
+
+impl ta::High for Candle {
+    fn high(&self) -> f64 {
+        self.high
+    }
+}
+
+impl ta::Low for Candle {
+    fn low(&self) -> f64 {
+        self.low
+    }
+}
+
+impl ta::Close for Candle {
+    fn close(&self) -> f64 {
+        self.close
+    }
+}
+
+impl ta::Volume for Candle {
+    fn volume(&self) -> f64 {
+        self.volume
+    }
+}
+
+/// Largest deviation of one of our indicators from the `ta` crate over a series
+#[derive(Debug, Clone, Serialize)]
+pub struct IndicatorCheck {
+    pub indicator: String,
+    pub reference: String,
+    pub compared: usize,
+    pub max_error: f64, // Relative to the reference value, or absolute below 1.0
+}
+
+// Compare two indicator outputs bar by bar, from `burn_in` on
+fn compare(
+    indicator: String,
+    reference: String,
+    candles: &[Candle],
+    burn_in: usize,
+    mut ours: impl FnMut(&Candle) -> Option<f64>,
+    mut theirs: impl FnMut(&Candle) -> f64,
+) -> IndicatorCheck {
+    let mut compared = 0;
+    let mut max_error: f64 = 0.0;
+
+    for (i, candle) in candles.iter().enumerate() {
+        let (ours, theirs) = (ours(candle), theirs(candle));
+        if let (Some(ours), true) = (ours, i >= burn_in) {
+            max_error = max_error.max((ours - theirs).abs() / theirs.abs().max(1.0));
+            compared += 1;
+        }
+    }
+
+    IndicatorCheck { indicator, reference, compared, max_error }
+}
+
+// Run the indicators `ta` also implements side by side with it. Both sides see every bar;
+// the first `burn_in` bars are not compared because `ta` seeds its exponential averages
+// with the first value instead of an SMA, a difference that decays geometrically.
+// Wilder's smoothing over `period` bars equals an EMA over `2 * period - 1`, so RSI and
+// ATR are checked against `ta` at that period.
+pub fn check_against_ta(candles: &[Candle], period: usize, burn_in: usize) -> Result<Vec<IndicatorCheck>, ta::errors::TaError> {
+    let period = period.max(2);
+    let wilder = 2 * period - 1;
+    let mut checks = Vec::new();
+
+    let (mut ours, mut theirs) = (Sma::new(period), ta::indicators::SimpleMovingAverage::new(period)?);
+    checks.push(compare(format!("SMA({})", period), format!("SMA({})", period), candles, burn_in,
+        |c| ours.update(c), |c| theirs.next(c)));
+
+    let (mut ours, mut theirs) = (Ema::new(period), ta::indicators::ExponentialMovingAverage::new(period)?);
+    checks.push(compare(format!("EMA({})", period), format!("EMA({})", period), candles, burn_in,
+        |c| ours.update(c), |c| theirs.next(c)));
+
+    let (mut ours, mut theirs) = (Rsi::new(period), ta::indicators::RelativeStrengthIndex::new(wilder)?);
+    checks.push(compare(format!("RSI({})", period), format!("RSI({})", wilder), candles, burn_in,
+        |c| ours.update(c), |c| theirs.next(c)));
+
+    let (mut ours, mut theirs) = (Atr::new(period), ta::indicators::AverageTrueRange::new(wilder)?);
+    checks.push(compare(format!("ATR({})", period), format!("ATR({})", wilder), candles, burn_in,
+        |c| ours.update(c), |c| theirs.next(c)));
+
+    for (line, pick) in [("upper", 0), ("lower", 1)] {
+        let (mut ours, mut theirs) = (Bollinger::new(period, 2.0), ta::indicators::BollingerBands::new(period, 2.0)?);
+        checks.push(compare(format!("Bollinger({}, 2) {}", period, line), format!("BB({}, 2)", period), candles, burn_in,
+            |c| ours.update(c).map(|b| if pick == 0 { b.upper } else { b.lower }),
+            |c| { let b = theirs.next(c); if pick == 0 { b.upper } else { b.lower } }));
+    }
+
+    for (line, pick) in [("macd", 0), ("signal", 1)] {
+        let (mut ours, mut theirs) = (Macd::new(12, 26, 9), ta::indicators::MovingAverageConvergenceDivergence::new(12, 26, 9)?);
+        checks.push(compare(format!("MACD(12, 26, 9) {}", line), "MACD(12, 26, 9)".to_string(), candles, burn_in,
+            |c| ours.update(c).map(|m| if pick == 0 { m.macd } else { m.signal }),
+            |c| { let m = theirs.next(c); if pick == 0 { m.macd } else { m.signal } }));
+    }
+
+    let (mut ours, mut theirs) = (Stochastic::new(period, 3), ta::indicators::FastStochastic::new(period)?);
+    checks.push(compare(format!("Stochastic({}, 3) %K", period), format!("FAST_STOCH({})", period), candles, burn_in,
+        |c| ours.update(c).map(|s| s.k), |c| theirs.next(c)));
+
+    // `ta` counts the first bar's volume as a rise from zero
+    let (mut ours, mut theirs) = (Obv::new(), ta::indicators::OnBalanceVolume::new());
+    let first_volume = candles.first().map_or(0.0, |c| c.volume);
+    checks.push(compare("OBV".to_string(), "OBV".to_string(), candles, burn_in,
+        |c| ours.update(c), |c| theirs.next(c) - first_volume));
+
+    let (mut ours, mut theirs) = (Keltner::new(period, period, 2.0), ta::indicators::KeltnerChannel::new(period, 2.0)?);
+    checks.push(compare(format!("Keltner({}, {}, 2) middle", period, period), format!("KC({}, 2)", period), candles, burn_in,
+        |c| ours.update(c).map(|b| b.middle), |c| theirs.next(c).average));
+
+    let (mut ours, mut highs, mut lows) = (Donchian::new(period), ta::indicators::Maximum::new(period)?, ta::indicators::Minimum::new(period)?);
+    checks.push(compare(format!("Donchian({}) middle", period), format!("Maximum({}) / Minimum({})", period, period), candles, burn_in,
+        |c| ours.update(c).map(|b| b.middle), |c| (highs.next(c) + lows.next(c)) / 2.0));
+
+    let (mut ours, mut theirs) = (Kama::new(period, 2, 30), ta::indicators::EfficiencyRatio::new(period)?);
+    checks.push(compare(format!("KAMA({}) efficiency ratio", period), format!("ER({})", period), candles, burn_in,
+        |c| { ours.update(c); ours.efficiency_ratio() }, |c| theirs.next(c)));
+
+    Ok(checks)
+}
+
+/// Value of one indicator line on the last bar of a series
+#[derive(Debug, Clone, Serialize)]
+pub struct IndicatorValue {
+    pub indicator: String,
+    pub value: Option<f64>, // None while the indicator is still warming up
+}
+
+// Feed the whole series and keep the output on its last bar
+fn last_output<I: Indicator>(mut indicator: I, candles: &[Candle]) -> Option<I::Output> {
+    candles.iter().map(|c| indicator.update(c)).last().flatten()
+}
+
+// Every indicator with `period` as its lookback (standard settings for the rest), on the last bar
+pub fn latest_values(candles: &[Candle], period: usize) -> Vec<IndicatorValue> {
+    let period = period.max(2);
+    let mut values = Vec::new();
+    let mut add = |indicator: String, value: Option<f64>| values.push(IndicatorValue { indicator, value });
+
+    add(format!("SMA({})", period), last_output(Sma::new(period), candles));
+    add(format!("EMA({})", period), last_output(Ema::new(period), candles));
+    add(format!("WMA({})", period), last_output(Wma::new(period), candles));
+    add(format!("KAMA({}, 2, 30)", period), last_output(Kama::new(period, 2, 30), candles));
+    add(format!("RSI({})", period), last_output(Rsi::new(period), candles));
+    add(format!("ATR({})", period), last_output(Atr::new(period), candles));
+    add("OBV".to_string(), last_output(Obv::new(), candles));
+    add("VWAP".to_string(), last_output(Vwap::new(), candles));
+
+    let bands = [
+        (format!("Bollinger({}, 2)", period), last_output(Bollinger::new(period, 2.0), candles)),
+        (format!("Keltner({}, {}, 2)", period, period), last_output(Keltner::new(period, period, 2.0), candles)),
+        (format!("Donchian({})", period), last_output(Donchian::new(period), candles)),
+    ];
+    for (name, bands) in bands {
+        add(format!("{} upper", name), bands.map(|b| b.upper));
+        add(format!("{} middle", name), bands.map(|b| b.middle));
+        add(format!("{} lower", name), bands.map(|b| b.lower));
+    }
+
+    let macd = last_output(Macd::new(12, 26, 9), candles);
+    add("MACD(12, 26, 9) macd".to_string(), macd.map(|m| m.macd));
+    add("MACD(12, 26, 9) signal".to_string(), macd.map(|m| m.signal));
+    add("MACD(12, 26, 9) histogram".to_string(), macd.map(|m| m.histogram));
+
+    let stochastic = last_output(Stochastic::new(period, 3), candles);
+    add(format!("Stochastic({}, 3) %K", period), stochastic.map(|s| s.k));
+    add(format!("Stochastic({}, 3) %D", period), stochastic.map(|s| s.d));
+
+    let adx = last_output(Adx::new(period), candles);
+    add(format!("ADX({})", period), adx.map(|a| a.adx));
+    add(format!("ADX({}) +DI", period), adx.map(|a| a.plus_di));
+    add(format!("ADX({}) -DI", period), adx.map(|a| a.minus_di));
+
+    let ichimoku = last_output(Ichimoku::new(9, 26, 52, 26), candles);
+    add("Ichimoku(9, 26, 52) tenkan".to_string(), ichimoku.map(|i| i.tenkan));
+    add("Ichimoku(9, 26, 52) kijun".to_string(), ichimoku.map(|i| i.kijun));
+    add("Ichimoku(9, 26, 52) senkou A".to_string(), ichimoku.map(|i| i.senkou_a));
+    add("Ichimoku(9, 26, 52) senkou B".to_string(), ichimoku.map(|i| i.senkou_b));
+
+    let supertrend = last_output(SuperTrend::new(period, 3.0), candles);
+    add(format!("SuperTrend({}, 3)", period), supertrend.map(|s| s.value));
+    add(format!("SuperTrend({}, 3) uptrend", period), supertrend.map(|s| if s.uptrend { 1.0 } else { 0.0 }));
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{bar, day};
+    use crate::synthetic::{self, SyntheticConfig};
+    use chrono::Duration;
+
+    // Errors are absolute for values below 1 and relative above, as `compare` scales them,
+    // so OBV's running totals in the millions get the same tolerance as RSI
+    const TOLERANCE: f64 = 1e-6;
+    const PERIOD: usize = 14;
+    const BURN_IN: usize = 260;
+
+    // Seeded synthetic bars, the same on every run
+    fn candles(seed: u64) -> Vec<Candle> {
+        let config = SyntheticConfig { seed, bar_interval: "1h".to_string(), ..SyntheticConfig::default() };
+        synthetic::generate("TEST", "2023-01-01", "2023-03-31", &config).unwrap().candles
+    }
+
+    // Every check whose name starts with `indicator` is compared and within the tolerance
+    fn assert_matches_ta(indicator: &str) {
+        for seed in [1, 42, 7919] {
+            let checks = check_against_ta(&candles(seed), PERIOD, BURN_IN).unwrap();
+            let matching: Vec<_> = checks.iter().filter(|c| c.indicator.starts_with(indicator)).collect();
+            assert!(!matching.is_empty(), "no check for {}", indicator);
+            for check in matching {
+                assert!(check.compared > 1000, "{} compared only {} bars", check.indicator, check.compared);
+                assert!(
+                    check.max_error < TOLERANCE,
+                    "{} differs from ta's {} by {:e} (seed {})",
+                    check.indicator, check.reference, check.max_error, seed,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sma_matches_ta() {
+        assert_matches_ta("SMA");
+    }
+
+    #[test]
+    fn ema_matches_ta() {
+        assert_matches_ta("EMA");
+    }
+
+    #[test]
+    fn rsi_matches_ta() {
+        assert_matches_ta("RSI");
+    }
+
+    #[test]
+    fn atr_matches_ta() {
+        assert_matches_ta("ATR");
+    }
+
+    #[test]
+    fn bollinger_matches_ta() {
+        assert_matches_ta("Bollinger");
+    }
+
+    #[test]
+    fn macd_matches_ta() {
+        assert_matches_ta("MACD");
+    }
+
+    #[test]
+    fn stochastic_matches_ta() {
+        assert_matches_ta("Stochastic");
+    }
+
+    #[test]
+    fn obv_matches_ta() {
+        assert_matches_ta("OBV");
+    }
+
+    #[test]
+    fn keltner_matches_ta() {
+        assert_matches_ta("Keltner");
+    }
+
+    #[test]
+    fn donchian_matches_ta() {
+        assert_matches_ta("Donchian");
+    }
+
+    #[test]
+    fn kama_efficiency_ratio_matches_ta() {
+        assert_matches_ta("KAMA");
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    // Daily bars from (high, low, close), opening at the low
+    fn bars(hlc: &[(f64, f64, f64)]) -> Vec<Candle> {
+        hlc.iter().enumerate().map(|(n, &(high, low, close))| bar(n as i64, low, high, low, close)).collect()
+    }
+
+    fn run<I: Indicator>(mut indicator: I, candles: &[Candle]) -> Vec<Option<I::Output>> {
+        candles.iter().map(|c| indicator.update(c)).collect()
+    }
+
+    #[test]
+    fn wma_weights_the_newest_close_most() {
+        let candles = bars(&[1.0, 2.0, 3.0, 4.0, 10.0].map(|c| (c, c, c)));
+        let values = run(Wma::new(3), &candles);
+
+        assert_eq!(&values[..2], &[None, None]);
+        assert_close(values[2].unwrap(), (1.0 + 2.0 * 2.0 + 3.0 * 3.0) / 6.0);
+        assert_close(values[3].unwrap(), (2.0 + 2.0 * 3.0 + 3.0 * 4.0) / 6.0);
+        assert_close(values[4].unwrap(), (3.0 + 2.0 * 4.0 + 3.0 * 10.0) / 6.0);
+    }
+
+    #[test]
+    fn adx_smooths_directional_movement() {
+        let candles = bars(&[(10.0, 8.0, 9.0), (12.0, 9.0, 11.0), (13.0, 10.0, 12.0), (12.0, 9.0, 10.0), (11.0, 7.0, 8.0)]);
+        let values = run(Adx::new(2), &candles);
+        assert!(values[..3].iter().all(Option::is_none));
+
+        // Bar 3: TR 3, +DM 0.75 and -DM 0.5 after smoothing; DX 100 then 20
+        let adx = values[3].unwrap();
+        assert_close(adx.plus_di, 25.0);
+        assert_close(adx.minus_di, 50.0 / 3.0);
+        assert_close(adx.adx, 60.0);
+
+        // Bar 4: TR 3.5, +DM 0.375, -DM 1.25
+        let adx = values[4].unwrap();
+        let (plus, minus) = (37.5 / 3.5, 125.0 / 3.5);
+        assert_close(adx.plus_di, plus);
+        assert_close(adx.minus_di, minus);
+        assert_close(adx.adx, 60.0 + (100.0 * (minus - plus) / (plus + minus) - 60.0) / 2.0);
+    }
+
+    #[test]
+    fn vwap_restarts_every_day() {
+        let at = |n: i64, hour: i64, high: f64, low: f64, close: f64, volume: f64| Candle {
+            timestamp: day(n) + Duration::hours(hour),
+            volume,
+            ..bar(n, low, high, low, close)
+        };
+        let candles = [
+            at(0, 0, 11.0, 9.0, 10.0, 100.0),
+            at(0, 1, 14.0, 10.0, 12.0, 300.0),
+            at(1, 0, 21.0, 19.0, 20.0, 50.0),
+            at(2, 0, 31.0, 29.0, 30.0, 0.0),
+        ];
+        let values: Vec<f64> = run(Vwap::new(), &candles).into_iter().flatten().collect();
+
+        assert_eq!(values, vec![10.0, (10.0 * 100.0 + 12.0 * 300.0) / 400.0, 20.0, 30.0]);
+    }
+
+    #[test]
+    fn keltner_bands_are_atr_multiples_around_the_ema() {
+        let candles = bars(&[(11.0, 9.0, 10.0), (12.0, 10.0, 11.0), (14.0, 11.0, 13.0)]);
+        let values = run(Keltner::new(2, 2, 1.5), &candles);
+        assert!(values[0].is_none());
+
+        // Typical prices 10, 11 and 38/3; true ranges 2, 2 and 3
+        let first = values[1].unwrap();
+        assert_eq!((first.middle, first.upper, first.lower), (10.5, 13.5, 7.5));
+        let middle = 38.0 / 3.0 * 2.0 / 3.0 + 10.5 / 3.0;
+        let second = values[2].unwrap();
+        assert_close(second.middle, middle);
+        assert_close(second.upper, middle + 1.5 * 2.5);
+        assert_close(second.lower, middle - 1.5 * 2.5);
+    }
+
+    #[test]
+    fn kama_moves_with_the_efficiency_ratio() {
+        let candles = bars(&[10.0, 11.0, 13.0, 12.0].map(|c| (c, c, c)));
+        let values = run(Kama::new(2, 2, 30), &candles);
+        let (fast, slow) = (2.0 / 3.0, 2.0 / 31.0);
+
+        assert_eq!(&values[..2], &[None, None]);
+        // A straight run has an efficiency ratio of 1: the fast constant, from the prior close
+        assert_close(values[2].unwrap(), 11.0 + fast * fast * 2.0);
+        // 11 -> 13 -> 12 nets 1 over a path of 3
+        let sc = (slow + (fast - slow) / 3.0).powi(2);
+        assert_close(values[3].unwrap(), values[2].unwrap() + sc * (12.0 - values[2].unwrap()));
+    }
+
+    #[test]
+    fn ichimoku_displaces_the_cloud_without_look_ahead() {
+        let candles = bars(&[(10.0, 6.0), (12.0, 9.0), (11.0, 7.0), (13.0, 8.0), (14.0, 11.0), (12.0, 10.0), (15.0, 12.0)].map(|(h, l)| (h, l, l)));
+        let values = run(Ichimoku::new(2, 3, 4, 2), &candles);
+        assert!(values[..5].iter().all(Option::is_none));
+
+        // The cloud on bar 5 was computed on bar 3: tenkan and kijun 10, senkou B 9.5
+        assert_eq!(values[5], Some(IchimokuOutput { tenkan: 12.0, kijun: 11.0, senkou_a: 10.0, senkou_b: 9.5 }));
+        // and on bar 6 on bar 4: tenkan 11 and kijun 10.5, senkou B 10.5
+        assert_eq!(values[6], Some(IchimokuOutput { tenkan: 12.5, kijun: 12.5, senkou_a: 10.75, senkou_b: 10.5 }));
+    }
+
+    #[test]
+    fn supertrend_trails_until_the_close_crosses() {
+        let candles = bars(&[(11.0, 9.0, 10.0), (12.0, 10.0, 11.0), (13.0, 11.0, 12.0), (12.0, 8.0, 9.0), (10.0, 8.0, 9.0), (14.0, 11.0, 13.5)]);
+        let values: Vec<Option<(f64, bool)>> = run(SuperTrend::new(2, 1.0), &candles).into_iter()
+            .map(|o| o.map(|s| (s.value, s.uptrend)))
+            .collect();
+
+        assert_eq!(values, vec![
+            None,
+            Some((9.0, true)),
+            Some((10.0, true)), // The lower band rises with the midpoint
+            Some((13.0, false)), // Close below the lower band of 10; the upper band held at 13
+            Some((11.5, false)), // The upper band tightens
+            Some((8.75, true)), // Close above 11.5
+        ]);
+    }
+}
@@ -0,0 +1,427 @@
+use crate::models::{Candle, Order, OrderType, TimeInForce, TradeDirection};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const MIN_FILL_SIZE: f64 = 1e-9;
+
+/// Liquidity assumptions of the bar-based matching engine
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchingConfig {
+    // Largest fraction of a bar's volume that orders may take; unlimited when unset.
+    // Orders larger than that are filled partially and keep working on later bars.
+    pub max_volume_participation: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum OrderStatus {
+    Working,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+}
+
+/// An order resting in the matching engine
+#[derive(Debug, Clone)]
+pub struct WorkingOrder {
+    pub id: u64,
+    pub order: Order,
+    pub filled: f64,
+    pub status: OrderStatus,
+    triggered: bool, // Stop-limit whose stop has been hit
+    extreme: Option<f64>, // Best price seen by a trailing stop
+    oco_group: Option<u64>, // Orders of a one-cancels-other group share the id of its first order
+}
+
+impl WorkingOrder {
+    pub fn remaining(&self) -> f64 {
+        (self.order.size - self.filled).max(0.0)
+    }
+
+    fn is_active(&self) -> bool {
+        matches!(self.status, OrderStatus::Working | OrderStatus::PartiallyFilled)
+    }
+}
+
+/// One execution against a bar
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub order_id: u64,
+    pub symbol: String,
+    pub direction: TradeDirection,
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+    pub size: f64,
+    pub taker: bool, // Crossed the spread (market or triggered stop) rather than resting as a limit
+    pub partial: bool, // The order still has size left after this fill
+}
+
+/// Bar-based order matching
+///
+/// Each bar only reveals its open, high, low and close, so fills follow these rules:
+/// market orders fill at the open; limit orders fill at the open when the bar gaps through
+/// the limit and otherwise at the limit once the range reaches it; stops trigger the same
+/// way and fill at the open on a gap or at the trigger price; a stop-limit becomes a limit
+/// order once triggered; a trailing stop checks the trigger from earlier bars before
+/// trailing the current bar's extreme, so it never triggers on a price it has not seen yet.
+pub struct MatchingEngine {
+    config: MatchingConfig,
+    orders: Vec<WorkingOrder>,
+    next_id: u64,
+    pub partial_fills: usize,
+    pub cancelled_orders: usize,
+}
+
+impl MatchingEngine {
+    pub fn new(config: MatchingConfig) -> Self {
+        Self {
+            config,
+            orders: Vec::new(),
+            next_id: 1,
+            partial_fills: 0,
+            cancelled_orders: 0,
+        }
+    }
+
+    pub fn submit(&mut self, order: Order) -> u64 {
+        self.submit_in_group(order, None)
+    }
+
+    // Submit orders as a one-cancels-other group: the first fill of any of them cancels the
+    // rest. When several would fill on the same bar, the one submitted first wins.
+    pub fn submit_oco(&mut self, orders: Vec<Order>) -> Vec<u64> {
+        let group = self.next_id;
+        orders.into_iter().map(|order| self.submit_in_group(order, Some(group))).collect()
+    }
+
+    fn submit_in_group(&mut self, order: Order, oco_group: Option<u64>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.orders.push(WorkingOrder {
+            id,
+            order,
+            filled: 0.0,
+            status: OrderStatus::Working,
+            triggered: false,
+            extreme: None,
+            oco_group,
+        });
+        id
+    }
+
+    // Cancel a working order; false if it is no longer working
+    pub fn cancel(&mut self, id: u64) -> bool {
+        let Some(index) = self.orders.iter().position(|w| w.id == id && w.is_active()) else {
+            return false;
+        };
+        self.orders.remove(index);
+        self.cancelled_orders += 1;
+        true
+    }
+
+    pub fn open_orders(&self) -> &[WorkingOrder] {
+        &self.orders
+    }
+
+    // Match the working orders for `symbol` against one bar, oldest order first
+    pub fn process_bar(&mut self, symbol: &str, candle: &Candle) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        let mut filled_groups = Vec::new();
+        let mut available = self.config.max_volume_participation
+            .map(|p| p * candle.volume)
+            .unwrap_or(f64::INFINITY);
+
+        for working in self.orders.iter_mut().filter(|w| w.order.symbol == symbol && w.is_active()) {
+            let immediate = working.order.time_in_force != TimeInForce::GoodTillCancel;
+            if working.oco_group.is_some_and(|g| filled_groups.contains(&g)) {
+                working.status = OrderStatus::Cancelled;
+                continue;
+            }
+
+            let Some((price, taker)) = match_price(working, candle) else {
+                if immediate {
+                    working.status = OrderStatus::Cancelled;
+                }
+                continue;
+            };
+
+            let remaining = working.remaining();
+            let size = remaining.min(available);
+            if working.order.time_in_force == TimeInForce::FillOrKill && size < remaining - MIN_FILL_SIZE {
+                working.status = OrderStatus::Cancelled;
+                continue;
+            }
+
+            if size > MIN_FILL_SIZE {
+                available -= size;
+                working.filled += size;
+                let partial = working.remaining() > MIN_FILL_SIZE;
+                working.status = if partial { OrderStatus::PartiallyFilled } else { OrderStatus::Filled };
+                filled_groups.extend(working.oco_group);
+
+                fills.push(Fill {
+                    order_id: working.id,
+                    symbol: symbol.to_string(),
+                    direction: working.order.direction,
+                    timestamp: candle.timestamp,
+                    price,
+                    size,
+                    taker,
+                    partial,
+                });
+            }
+
+            if immediate && working.is_active() {
+                working.status = OrderStatus::Cancelled;
+            }
+        }
+
+        // Siblings that were checked before their group filled
+        for working in self.orders.iter_mut().filter(|w| w.is_active()) {
+            let group_filled = working.oco_group.is_some_and(|g| filled_groups.contains(&g));
+            if group_filled && !fills.iter().any(|f| f.order_id == working.id) {
+                working.status = OrderStatus::Cancelled;
+            }
+        }
+
+        self.partial_fills += fills.iter().filter(|f| f.partial).count();
+        self.cancelled_orders += self.orders.iter().filter(|w| w.status == OrderStatus::Cancelled).count();
+        self.orders.retain(|w| w.is_active());
+        fills
+    }
+}
+
+// Where `working` fills on `candle`, if it does, and whether it takes liquidity
+fn match_price(working: &mut WorkingOrder, candle: &Candle) -> Option<(f64, bool)> {
+    let buy = working.order.direction == TradeDirection::Long;
+
+    match working.order.order_type {
+        OrderType::Market => Some((candle.open, true)),
+        OrderType::Limit { price } => limit_price(buy, price, candle).map(|p| (p, false)),
+        OrderType::Stop { trigger } => stop_price(buy, trigger, candle).map(|p| (p, true)),
+        OrderType::StopLimit { trigger, limit } => {
+            if working.triggered {
+                return limit_price(buy, limit, candle).map(|p| (p, false));
+            }
+
+            let hit = stop_price(buy, trigger, candle)?;
+            working.triggered = true;
+
+            // The bar's path after the trigger is unknown, so only fill at the trigger
+            // price when the limit allows it; otherwise rest as a limit from the next bar
+            let marketable = if buy { hit <= limit } else { hit >= limit };
+            marketable.then_some((hit, true))
+        }
+        OrderType::TrailingStop { distance } => {
+            let extreme = working.extreme.unwrap_or(candle.open);
+            let trigger = if buy { extreme + distance } else { extreme - distance };
+            let fill = stop_price(buy, trigger, candle);
+
+            working.extreme = Some(if buy { extreme.min(candle.low) } else { extreme.max(candle.high) });
+            fill.map(|p| (p, true))
+        }
+    }
+}
+
+// Buy limits fill at or below the limit, sell limits at or above it
+fn limit_price(buy: bool, limit: f64, candle: &Candle) -> Option<f64> {
+    if buy {
+        if candle.open <= limit {
+            Some(candle.open)
+        } else if candle.low <= limit {
+            Some(limit)
+        } else {
+            None
+        }
+    } else if candle.open >= limit {
+        Some(candle.open)
+    } else if candle.high >= limit {
+        Some(limit)
+    } else {
+        None
+    }
+}
+
+// Buy stops trigger when the price rises to the trigger, sell stops when it falls to it
+fn stop_price(buy: bool, trigger: f64, candle: &Candle) -> Option<f64> {
+    if buy {
+        if candle.open >= trigger {
+            Some(candle.open)
+        } else if candle.high >= trigger {
+            Some(trigger)
+        } else {
+            None
+        }
+    } else if candle.open <= trigger {
+        Some(candle.open)
+    } else if candle.low <= trigger {
+        Some(trigger)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::bar;
+
+    const SYMBOL: &str = "TEST";
+
+    fn order(direction: TradeDirection, size: f64, order_type: OrderType) -> Order {
+        Order::new(SYMBOL, direction, size, order_type)
+    }
+
+    fn engine() -> MatchingEngine {
+        MatchingEngine::new(MatchingConfig::default())
+    }
+
+    // (price, size, taker) of each fill
+    fn fills(engine: &mut MatchingEngine, candle: &Candle) -> Vec<(f64, f64, bool)> {
+        engine.process_bar(SYMBOL, candle).iter().map(|f| (f.price, f.size, f.taker)).collect()
+    }
+
+    #[test]
+    fn market_fills_at_the_open() {
+        let mut engine = engine();
+        engine.submit(Order::market(SYMBOL, TradeDirection::Long, 2.0));
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 105.0, 95.0, 102.0)), vec![(100.0, 2.0, true)]);
+        assert!(engine.open_orders().is_empty());
+    }
+
+    #[test]
+    fn limit_fills_at_its_price_once_reached() {
+        let mut engine = engine();
+        engine.submit(order(TradeDirection::Long, 1.0, OrderType::Limit { price: 95.0 }));
+        engine.submit(order(TradeDirection::Short, 1.0, OrderType::Limit { price: 104.0 }));
+
+        assert!(fills(&mut engine, &bar(0, 100.0, 103.0, 97.0, 100.0)).is_empty());
+        assert_eq!(engine.open_orders().len(), 2);
+        assert_eq!(
+            fills(&mut engine, &bar(1, 100.0, 106.0, 94.0, 100.0)),
+            vec![(95.0, 1.0, false), (104.0, 1.0, false)],
+        );
+    }
+
+    #[test]
+    fn limit_fills_at_the_open_on_a_gap_through() {
+        let mut engine = engine();
+        engine.submit(order(TradeDirection::Long, 1.0, OrderType::Limit { price: 95.0 }));
+
+        assert_eq!(fills(&mut engine, &bar(0, 93.0, 96.0, 92.0, 94.0)), vec![(93.0, 1.0, false)]);
+    }
+
+    #[test]
+    fn stop_fills_at_the_trigger_or_the_gap_open() {
+        let mut engine = engine();
+        engine.submit(order(TradeDirection::Short, 1.0, OrderType::Stop { trigger: 95.0 }));
+        engine.submit(order(TradeDirection::Long, 1.0, OrderType::Stop { trigger: 105.0 }));
+
+        assert!(fills(&mut engine, &bar(0, 100.0, 104.0, 96.0, 100.0)).is_empty());
+        assert_eq!(fills(&mut engine, &bar(1, 100.0, 104.0, 94.0, 96.0)), vec![(95.0, 1.0, true)]);
+        assert_eq!(fills(&mut engine, &bar(2, 108.0, 110.0, 107.0, 109.0)), vec![(108.0, 1.0, true)]);
+    }
+
+    #[test]
+    fn stop_limit_fills_at_the_trigger_when_the_limit_allows() {
+        let mut engine = engine();
+        engine.submit(order(TradeDirection::Long, 1.0, OrderType::StopLimit { trigger: 105.0, limit: 106.0 }));
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 107.0, 99.0, 106.0)), vec![(105.0, 1.0, true)]);
+    }
+
+    #[test]
+    fn stop_limit_rests_as_a_limit_after_triggering() {
+        let mut engine = engine();
+        engine.submit(order(TradeDirection::Long, 1.0, OrderType::StopLimit { trigger: 105.0, limit: 104.0 }));
+
+        assert!(fills(&mut engine, &bar(0, 100.0, 107.0, 99.0, 106.0)).is_empty());
+        assert_eq!(engine.open_orders().len(), 1);
+        assert_eq!(fills(&mut engine, &bar(1, 106.0, 107.0, 103.0, 104.0)), vec![(104.0, 1.0, false)]);
+    }
+
+    #[test]
+    fn trailing_stop_trails_only_prices_already_seen() {
+        let mut engine = engine();
+        engine.submit(order(TradeDirection::Short, 1.0, OrderType::TrailingStop { distance: 5.0 }));
+
+        // Trailing the first bar's high of 110 first would trigger at 105 on its low of 98
+        assert!(fills(&mut engine, &bar(0, 100.0, 110.0, 98.0, 108.0)).is_empty());
+        assert_eq!(fills(&mut engine, &bar(1, 108.0, 109.0, 104.0, 106.0)), vec![(105.0, 1.0, true)]);
+    }
+
+    #[test]
+    fn immediate_or_cancel_fills_what_it_can_on_the_first_bar() {
+        let mut engine = MatchingEngine::new(MatchingConfig { max_volume_participation: Some(0.1) });
+        engine.submit(Order::market(SYMBOL, TradeDirection::Long, 25.0).with_time_in_force(TimeInForce::ImmediateOrCancel));
+        engine.submit(order(TradeDirection::Long, 1.0, OrderType::Limit { price: 90.0 }).with_time_in_force(TimeInForce::ImmediateOrCancel));
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 101.0, 99.0, 100.0)), vec![(100.0, 10.0, true)]);
+        assert!(engine.open_orders().is_empty());
+        assert_eq!(engine.cancelled_orders, 2);
+    }
+
+    #[test]
+    fn fill_or_kill_fills_completely_or_not_at_all() {
+        let mut engine = MatchingEngine::new(MatchingConfig { max_volume_participation: Some(0.1) });
+        engine.submit(Order::market(SYMBOL, TradeDirection::Long, 25.0).with_time_in_force(TimeInForce::FillOrKill));
+        engine.submit(Order::market(SYMBOL, TradeDirection::Long, 5.0).with_time_in_force(TimeInForce::FillOrKill));
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 101.0, 99.0, 100.0)), vec![(100.0, 5.0, true)]);
+        assert!(engine.open_orders().is_empty());
+        assert_eq!(engine.cancelled_orders, 1);
+    }
+
+    #[test]
+    fn one_cancels_other_keeps_the_first_fill() {
+        let mut engine = engine();
+        engine.submit_oco(vec![
+            order(TradeDirection::Short, 1.0, OrderType::Limit { price: 110.0 }),
+            order(TradeDirection::Short, 1.0, OrderType::Stop { trigger: 95.0 }),
+        ]);
+
+        // Both could fill on this bar; the order submitted first wins
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 112.0, 94.0, 100.0)), vec![(110.0, 1.0, false)]);
+        assert!(engine.open_orders().is_empty());
+        assert!(fills(&mut engine, &bar(1, 100.0, 100.0, 90.0, 92.0)).is_empty());
+    }
+
+    #[test]
+    fn one_cancels_other_cancels_a_sibling_checked_earlier() {
+        let mut engine = engine();
+        engine.submit_oco(vec![
+            order(TradeDirection::Short, 1.0, OrderType::Limit { price: 110.0 }),
+            order(TradeDirection::Short, 1.0, OrderType::Stop { trigger: 95.0 }),
+        ]);
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 101.0, 94.0, 96.0)), vec![(95.0, 1.0, true)]);
+        assert!(engine.open_orders().is_empty());
+    }
+
+    #[test]
+    fn volume_cap_carries_the_rest_to_later_bars() {
+        let mut engine = MatchingEngine::new(MatchingConfig { max_volume_participation: Some(0.1) });
+        let id = engine.submit(Order::market(SYMBOL, TradeDirection::Long, 25.0));
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 101.0, 99.0, 100.0)), vec![(100.0, 10.0, true)]);
+        assert_eq!(engine.open_orders()[0].status, OrderStatus::PartiallyFilled);
+        assert_eq!(engine.open_orders()[0].remaining(), 15.0);
+        assert_eq!(fills(&mut engine, &bar(1, 101.0, 102.0, 100.0, 101.0)), vec![(101.0, 10.0, true)]);
+
+        let last = engine.process_bar(SYMBOL, &bar(2, 102.0, 103.0, 101.0, 102.0));
+        assert_eq!((last[0].order_id, last[0].size, last[0].partial), (id, 5.0, false));
+        assert!(engine.open_orders().is_empty());
+        assert_eq!(engine.partial_fills, 2);
+    }
+
+    #[test]
+    fn volume_cap_is_shared_oldest_order_first() {
+        let mut engine = MatchingEngine::new(MatchingConfig { max_volume_participation: Some(0.1) });
+        engine.submit(Order::market(SYMBOL, TradeDirection::Long, 8.0));
+        engine.submit(Order::market(SYMBOL, TradeDirection::Long, 8.0));
+
+        assert_eq!(fills(&mut engine, &bar(0, 100.0, 101.0, 99.0, 100.0)), vec![(100.0, 8.0, true), (100.0, 2.0, true)]);
+        assert_eq!(fills(&mut engine, &bar(1, 100.0, 101.0, 99.0, 100.0)), vec![(100.0, 6.0, true)]);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::models::Candle;
+use crate::synthetic::{self, SyntheticConfig};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Local stand-in for an exchange's OHLCV endpoint
+///
+/// Serves synthetic history in the format `RestProvider` expects, so the adapter can be
+/// exercised offline. Pages are capped at `max_page_size` bars, requests beyond
+/// `requests_per_second` get `429 Too Many Requests`, and a `failure_rate` fraction of
+/// requests fail with `503` to exercise retries.
+#[derive(Debug, Clone)]
+pub struct MockServerConfig {
+    pub start_date: String,
+    pub end_date: String,
+    pub generator: SyntheticConfig,
+    pub max_page_size: usize,
+    pub requests_per_second: Option<usize>,
+    pub failure_rate: f64,
+}
+
+struct ServerState {
+    config: MockServerConfig,
+    histories: HashMap<(String, String), Arc<Vec<Candle>>>, // Full history per (symbol, interval)
+    recent_requests: VecDeque<Instant>,
+    rng: StdRng,
+}
+
+// Accept connections until the process is stopped
+pub async fn serve(listener: TcpListener, config: MockServerConfig) -> Result<(), Box<dyn Error>> {
+    let state = Arc::new(Mutex::new(ServerState {
+        rng: StdRng::seed_from_u64(config.generator.seed),
+        config,
+        histories: HashMap::new(),
+        recent_requests: VecDeque::new(),
+    }));
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &state).await {
+                eprintln!("Mock server: {}", e);
+            }
+        });
+    }
+}
+
+// Answer a single request and close the connection
+async fn handle_connection(mut stream: TcpStream, state: &Mutex<ServerState>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 || buffer.len() > 16 * 1024 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&buffer);
+    let target = request.lines().next().and_then(|line| line.split_whitespace().nth(1)).unwrap_or("/");
+    let (status, extra_headers, body) = respond(target, state);
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status,
+        body.len(),
+        extra_headers,
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+// Status line, extra headers and JSON body for a request target
+fn respond(target: &str, state: &Mutex<ServerState>) -> (&'static str, &'static str, String) {
+    let mut state = state.lock().unwrap();
+
+    // Rate limit over a sliding one-second window
+    if let Some(limit) = state.config.requests_per_second {
+        let now = Instant::now();
+        while state.recent_requests.front().is_some_and(|t| now.duration_since(*t) > Duration::from_secs(1)) {
+            state.recent_requests.pop_front();
+        }
+        if state.recent_requests.len() >= limit {
+            return ("429 Too Many Requests", "Retry-After: 1\r\n", error_body("rate limit exceeded"));
+        }
+        state.recent_requests.push_back(now);
+    }
+
+    let failure_rate = state.config.failure_rate.clamp(0.0, 1.0);
+    if state.rng.gen_bool(failure_rate) {
+        return ("503 Service Unavailable", "", error_body("temporarily unavailable"));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/ohlcv" {
+        return ("404 Not Found", "", error_body("unknown endpoint"));
+    }
+
+    let params: HashMap<String, String> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect();
+    let (Some(symbol), Some(interval)) = (params.get("symbol"), params.get("interval")) else {
+        return ("400 Bad Request", "", error_body("symbol and interval are required"));
+    };
+    let number = |key: &str, default: i64| params.get(key).and_then(|v| v.parse::<i64>().ok()).unwrap_or(default);
+    let start = number("start", i64::MIN);
+    let end = number("end", i64::MAX);
+    let limit = (number("limit", state.config.max_page_size as i64).max(0) as usize).min(state.config.max_page_size);
+
+    let history = match history(&mut state, symbol, interval) {
+        Ok(history) => history,
+        Err(e) => return ("400 Bad Request", "", error_body(&e)),
+    };
+
+    let page: Vec<_> = history
+        .iter()
+        .filter(|c| (start..=end).contains(&c.timestamp.timestamp_millis()))
+        .take(limit)
+        .map(|c| json!({
+            "timestamp": c.timestamp.timestamp_millis(),
+            "open": c.open,
+            "high": c.high,
+            "low": c.low,
+            "close": c.close,
+            "volume": c.volume,
+        }))
+        .collect();
+
+    ("200 OK", "", serde_json::Value::Array(page).to_string())
+}
+
+// Generated once per symbol and interval so every page comes from the same price path
+fn history(state: &mut ServerState, symbol: &str, interval: &str) -> Result<Arc<Vec<Candle>>, String> {
+    let key = (symbol.to_string(), interval.to_string());
+    if let Some(history) = state.histories.get(&key) {
+        return Ok(Arc::clone(history));
+    }
+
+    let generator = SyntheticConfig { bar_interval: interval.to_string(), ..state.config.generator.clone() };
+    let data = synthetic::generate(symbol, &state.config.start_date, &state.config.end_date, &generator)
+        .map_err(|e| e.to_string())?;
+    let history = Arc::new(data.candles);
+    state.histories.insert(key, Arc::clone(&history));
+    Ok(history)
+}
+
+fn error_body(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
+
+// Decode `%XX` escapes and `+` in a query string component
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
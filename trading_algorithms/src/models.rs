@@ -1,6 +1,8 @@
+use crate::analytics::PerformanceReport;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::Range;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Candle {
@@ -28,6 +30,73 @@ pub enum TradeDirection {
     Short,
 }
 
+impl TradeDirection {
+    pub fn opposite(self) -> Self {
+        match self {
+            TradeDirection::Long => TradeDirection::Short,
+            TradeDirection::Short => TradeDirection::Long,
+        }
+    }
+
+    // +1.0 for long, -1.0 for short
+    pub fn sign(self) -> f64 {
+        match self {
+            TradeDirection::Long => 1.0,
+            TradeDirection::Short => -1.0,
+        }
+    }
+}
+
+/// How the matching engine decides whether and where an order fills
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderType {
+    Market,
+    Limit { price: f64 },
+    Stop { trigger: f64 },
+    StopLimit { trigger: f64, limit: f64 },
+    TrailingStop { distance: f64 }, // Trigger trails the best price since submission by this amount
+}
+
+/// How long an order stays working
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeInForce {
+    GoodTillCancel,
+    ImmediateOrCancel, // Fill what the first bar allows, cancel the rest
+    FillOrKill, // Fill completely on the first bar or not at all
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub symbol: String,
+    pub direction: TradeDirection,
+    pub size: f64,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+}
+
+impl Order {
+    pub fn new(symbol: &str, direction: TradeDirection, size: f64, order_type: OrderType) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            direction,
+            size,
+            order_type,
+            time_in_force: TimeInForce::GoodTillCancel,
+        }
+    }
+
+    pub fn market(symbol: &str, direction: TradeDirection, size: f64) -> Self {
+        Self::new(symbol, direction, size, OrderType::Market)
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub symbol: String,
@@ -37,6 +106,8 @@ pub struct Position {
     pub size: f64,
     pub entry_time: DateTime<Utc>,
     pub exit_time: Option<DateTime<Utc>>,
+    pub exit_price: Option<f64>,
+    pub costs: f64,
     pub profit_loss: f64,
 }
 
@@ -50,8 +121,19 @@ pub struct BacktestResult {
     pub sharpe_ratio: f64,
     pub max_drawdown: f64,
     pub trades: Vec<Trade>,
+    pub positions: Vec<Position>,
     pub equity_curve: Vec<(DateTime<Utc>, f64)>,
     pub metrics: HashMap<String, f64>,
+    pub analytics: PerformanceReport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioBacktestResult {
+    pub result: BacktestResult,
+    pub symbols: Vec<String>,
+    pub contributions: HashMap<String, f64>, // Net P&L per symbol
+    pub return_correlation: Vec<Vec<f64>>, // Bar-return correlation between symbols
+    pub pnl_correlation: Vec<Vec<f64>>, // Correlation of per-symbol P&L changes
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,4 +141,58 @@ pub struct MarketData {
     pub symbol: String,
     pub timeframe: String,
     pub candles: Vec<Candle>,
-}
\ No newline at end of file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pair: Option<Box<MarketData>>, // Second leg for pairs strategies, aligned bar for bar with `candles`
+}
+
+impl MarketData {
+    pub fn new(symbol: &str, timeframe: &str, candles: Vec<Candle>) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            timeframe: timeframe.to_string(),
+            candles,
+            pair: None,
+        }
+    }
+
+    // Bars `range` of the series and of its pair leg
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        Self {
+            symbol: self.symbol.clone(),
+            timeframe: self.timeframe.clone(),
+            candles: self.candles[range.clone()].to_vec(),
+            pair: self.pair.as_ref().map(|pair| Box::new(pair.slice(range))),
+        }
+    }
+}
+
+// Dated bars and scratch directories for the test modules
+#[cfg(test)]
+pub mod fixtures {
+    use super::Candle;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use std::path::PathBuf;
+
+    // Midnight `n` days after 2023-01-01
+    pub fn day(n: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap() + Duration::days(n)
+    }
+
+    // Bar on `day(n)` trading 100 units
+    pub fn bar(n: i64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle { timestamp: day(n), open, high, low, close, volume: 100.0 }
+    }
+
+    // Bar on `day(n)` that never moved from `close`
+    pub fn flat(n: i64, close: f64) -> Candle {
+        bar(n, close, close, close, close)
+    }
+
+    // Empty directory under the system temp dir, private to this process and `name`
+    pub fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("trading_algorithms-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+}
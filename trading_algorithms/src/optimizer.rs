@@ -0,0 +1,380 @@
+use crate::backtest;
+use crate::config::BacktestConfig;
+use crate::models::{BacktestResult, MarketData};
+use crate::strategies::{self, StrategyConfig};
+use plotters::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Metric the optimizer ranks parameter sets by (higher is better)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Objective {
+    Sharpe,
+    TotalPnl,
+    Calmar,
+}
+
+impl Objective {
+    pub fn score(&self, result: &BacktestResult) -> f64 {
+        match self {
+            Objective::Sharpe => result.sharpe_ratio,
+            Objective::TotalPnl => result.total_profit_loss,
+            Objective::Calmar => result.analytics.calmar_ratio,
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sharpe" => Ok(Objective::Sharpe),
+            "total_pnl" | "pnl" => Ok(Objective::TotalPnl),
+            "calmar" => Ok(Objective::Calmar),
+            _ => Err(format!("unknown objective '{}' (expected sharpe, total_pnl or calmar)", s)),
+        }
+    }
+}
+
+/// Inclusive range of values to sweep for one strategy parameter
+///
+/// Parsed from `name=min:max:step`, e.g. `fast_period=5:20:5`.
+#[derive(Debug, Clone)]
+pub struct ParamRange {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+}
+
+impl ParamRange {
+    // Every grid point from min to max
+    pub fn values(&self) -> Vec<f64> {
+        if self.step <= 0.0 {
+            return vec![self.min];
+        }
+
+        let steps = ((self.max - self.min) / self.step + 1e-9).floor() as usize;
+        (0..=steps).map(|i| self.min + i as f64 * self.step).collect()
+    }
+
+    // Uniform sample in [min, max]: one of the grid points of `values` when there is a step,
+    // so a step that does not divide the range never lands past max
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        if self.max <= self.min {
+            return self.min;
+        }
+
+        if self.step > 0.0 {
+            let values = self.values();
+            values[rng.gen_range(0..values.len())]
+        } else {
+            rng.gen_range(self.min..=self.max)
+        }
+    }
+}
+
+impl FromStr for ParamRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, range) = s.split_once('=').ok_or_else(|| format!("expected name=min:max:step, got '{}'", s))?;
+        let parts: Vec<f64> = range
+            .split(':')
+            .map(|p| p.parse::<f64>().map_err(|e| format!("invalid range for '{}': {}", name, e)))
+            .collect::<Result<_, _>>()?;
+
+        let (min, max, step) = match parts.as_slice() {
+            [value] => (*value, *value, 0.0),
+            [min, max] => (*min, *max, 1.0),
+            [min, max, step] => (*min, *max, *step),
+            _ => return Err(format!("expected name=min:max:step, got '{}'", s)),
+        };
+
+        if max < min {
+            return Err(format!("range for '{}' has max < min", name));
+        }
+
+        Ok(Self { name: name.to_string(), min, max, step })
+    }
+}
+
+/// One evaluated parameter set
+#[derive(Debug, Clone, Serialize)]
+pub struct OptimizationResult {
+    pub params: BTreeMap<String, f64>,
+    pub score: f64,
+    pub total_profit_loss: f64,
+    pub sharpe_ratio: f64,
+    pub calmar_ratio: f64,
+    pub max_drawdown: f64,
+    pub total_trades: usize,
+}
+
+// Backtest every combination of the given ranges, holding the strategy's own params constant
+pub fn grid_search(
+    strategy: &StrategyConfig,
+    ranges: &[ParamRange],
+    data: &MarketData,
+    config: &BacktestConfig,
+    objective: Objective,
+) -> Result<Vec<OptimizationResult>, Box<dyn Error>> {
+    let mut combinations: Vec<BTreeMap<String, f64>> = vec![BTreeMap::new()];
+    for range in ranges {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|params| {
+                range.values().into_iter().map(move |value| {
+                    let mut params = params.clone();
+                    params.insert(range.name.clone(), value);
+                    params
+                })
+            })
+            .collect();
+    }
+
+    evaluate(strategy, combinations, data, config, objective)
+}
+
+// Backtest `samples` random parameter sets drawn from the given ranges
+pub fn random_search(
+    strategy: &StrategyConfig,
+    ranges: &[ParamRange],
+    samples: usize,
+    seed: u64,
+    data: &MarketData,
+    config: &BacktestConfig,
+    objective: Objective,
+) -> Result<Vec<OptimizationResult>, Box<dyn Error>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let combinations = (0..samples)
+        .map(|_| ranges.iter().map(|r| (r.name.clone(), r.sample(&mut rng))).collect())
+        .collect();
+
+    evaluate(strategy, combinations, data, config, objective)
+}
+
+// Check every parameter set against the strategy's schema, skip the sets that break a
+// constraint between parameters (a grid of fast and slow periods crosses over), then run
+// the backtests in parallel and rank them best first
+fn evaluate(
+    strategy: &StrategyConfig,
+    combinations: Vec<BTreeMap<String, f64>>,
+    data: &MarketData,
+    config: &BacktestConfig,
+    objective: Objective,
+) -> Result<Vec<OptimizationResult>, Box<dyn Error>> {
+    let descriptor = strategies::describe_strategy(&strategy.name)?;
+    let mut skipped = None;
+    let mut valid = Vec::new();
+    for params in combinations {
+        let mut overrides = strategy.params.clone();
+        overrides.extend(params.iter().map(|(k, v)| (k.clone(), *v)));
+        match descriptor.check(&descriptor.validate_each(&overrides)?) {
+            Ok(()) => valid.push((params, overrides)),
+            Err(e) => skipped = Some(e),
+        }
+    }
+    if valid.is_empty() && let Some(e) = skipped {
+        return Err(format!("no parameter set is valid ({})", e).into());
+    }
+
+    let results: Result<Vec<OptimizationResult>, String> = valid
+        .into_par_iter()
+        .map(|(params, overrides)| {
+            let mut run_strategy = strategies::create_strategy_with_params(&strategy.name, &overrides).map_err(|e| e.to_string())?;
+            let result = backtest::run_strategy(run_strategy.as_mut(), data, config);
+
+            Ok(OptimizationResult {
+                score: objective.score(&result),
+                total_profit_loss: result.total_profit_loss,
+                sharpe_ratio: result.sharpe_ratio,
+                calmar_ratio: result.analytics.calmar_ratio,
+                max_drawdown: result.max_drawdown,
+                total_trades: result.total_trades,
+                params,
+            })
+        })
+        .collect();
+
+    let mut results = results?;
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(results)
+}
+
+pub fn print_results_table(results: &[OptimizationResult], top_n: usize) {
+    let Some(first) = results.first() else {
+        println!("No parameter sets evaluated");
+        return;
+    };
+
+    let param_names: Vec<&String> = first.params.keys().collect();
+    let header: Vec<String> = param_names.iter().map(|n| format!("{:<12}", n)).collect();
+    println!("Rank | {} | Score      | Total P&L    | Sharpe | Calmar | Max DD  | # Trades", header.join(" | "));
+    println!("---- | {} | ---------- | ------------ | ------ | ------ | ------- | --------", vec!["------------"; param_names.len()].join(" | "));
+
+    for (rank, r) in results.iter().take(top_n).enumerate() {
+        let values: Vec<String> = param_names.iter().map(|n| format!("{:<12}", r.params[*n])).collect();
+        println!(
+            "{:<4} | {} | {:<10.4} | ${:<11.2} | {:<6.2} | {:<6.2} | {:<7} | {}",
+            rank + 1,
+            values.join(" | "),
+            r.score,
+            r.total_profit_loss,
+            r.sharpe_ratio,
+            r.calmar_ratio,
+            format!("{:.2}%", r.max_drawdown * 100.0),
+            r.total_trades,
+        );
+    }
+}
+
+/// Heatmap of the objective over two parameters
+///
+/// When more than two parameters were swept, each cell shows the best score over the others.
+pub fn plot_heatmap<P: AsRef<Path>>(
+    results: &[OptimizationResult],
+    x_param: &str,
+    y_param: &str,
+    output_path: P,
+) -> Result<(), Box<dyn Error>> {
+    let distinct = |name: &str| -> Vec<f64> {
+        let mut values: Vec<f64> = results.iter().filter_map(|r| r.params.get(name).copied()).collect();
+        values.sort_by(f64::total_cmp);
+        values.dedup();
+        values
+    };
+    let xs = distinct(x_param);
+    let ys = distinct(y_param);
+
+    if xs.is_empty() || ys.is_empty() {
+        return Err(format!("No results for parameters '{}' and '{}'", x_param, y_param).into());
+    }
+
+    // Best score per cell
+    let mut cells = vec![vec![None::<f64>; ys.len()]; xs.len()];
+    for r in results {
+        let (Some(x), Some(y)) = (r.params.get(x_param), r.params.get(y_param)) else {
+            continue;
+        };
+        let i = xs.iter().position(|v| v == x).unwrap();
+        let j = ys.iter().position(|v| v == y).unwrap();
+        let cell = &mut cells[i][j];
+        *cell = Some(cell.map_or(r.score, |s: f64| s.max(r.score)));
+    }
+
+    let scores = cells.iter().flatten().flatten();
+    let min_score = scores.clone().copied().fold(f64::INFINITY, f64::min);
+    let max_score = scores.copied().fold(f64::NEG_INFINITY, f64::max);
+    let spread = (max_score - min_score).max(f64::EPSILON);
+
+    let root = BitMapBackend::new(output_path.as_ref(), (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(format!("Parameter surface ({} vs {})", x_param, y_param), ("sans-serif", 24).into_font())
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d((0..xs.len() as i32 - 1).into_segmented(), (0..ys.len() as i32 - 1).into_segmented())?;
+
+    chart.configure_mesh()
+        .disable_mesh()
+        .x_desc(x_param)
+        .y_desc(y_param)
+        .x_label_formatter(&|v| segment_label(v, &xs))
+        .y_label_formatter(&|v| segment_label(v, &ys))
+        .draw()?;
+
+    chart.draw_series(cells.iter().enumerate().flat_map(|(i, column)| {
+        column.iter().enumerate().filter_map(move |(j, score)| {
+            score.map(|s| {
+                // Red (worst) through yellow to green (best)
+                let t = (s - min_score) / spread;
+                let color = HSLColor(t * 0.33, 0.8, 0.5);
+                Rectangle::new(
+                    [
+                        (SegmentValue::Exact(i as i32), SegmentValue::Exact(j as i32)),
+                        (SegmentValue::Exact(i as i32 + 1), SegmentValue::Exact(j as i32 + 1)),
+                    ],
+                    color.filled(),
+                )
+            })
+        })
+    }))?;
+
+    Ok(())
+}
+
+// Axis label for the parameter value at the centre of a heatmap column/row
+fn segment_label(value: &SegmentValue<i32>, values: &[f64]) -> String {
+    match value {
+        SegmentValue::CenterOf(i) => values.get(*i as usize).map(|v| format!("{}", v)).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::{self, SyntheticConfig};
+    use std::collections::HashMap;
+
+    #[test]
+    fn values_stop_at_the_last_grid_point_within_the_range() {
+        let range: ParamRange = "fast=5:20:4".parse().unwrap();
+        assert_eq!(range.values(), vec![5.0, 9.0, 13.0, 17.0]);
+
+        let range: ParamRange = "rate=0.1:0.3:0.1".parse().unwrap();
+        assert_eq!(range.values().len(), 3);
+    }
+
+    #[test]
+    fn samples_are_grid_points_within_the_range() {
+        let range: ParamRange = "fast=5:20:4".parse().unwrap();
+        let grid = range.values();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let samples: Vec<f64> = (0..1000).map(|_| range.sample(&mut rng)).collect();
+        assert!(samples.iter().all(|v| grid.contains(v)), "sample outside {:?}", grid);
+        assert!(grid.iter().all(|v| samples.contains(v)), "grid point never sampled");
+    }
+
+    #[test]
+    fn samples_without_a_step_are_continuous() {
+        let range = ParamRange { name: "rate".to_string(), min: 0.5, max: 0.75, step: 0.0 };
+        let mut rng = StdRng::seed_from_u64(1);
+        assert!((0..1000).map(|_| range.sample(&mut rng)).all(|v| (0.5..=0.75).contains(&v)));
+    }
+
+    #[test]
+    fn rejects_malformed_ranges() {
+        assert!("fast=20:5".parse::<ParamRange>().is_err());
+        assert!("fast=a:5".parse::<ParamRange>().is_err());
+        assert!("fast".parse::<ParamRange>().is_err());
+        assert_eq!("fast=7".parse::<ParamRange>().unwrap().values(), vec![7.0]);
+    }
+
+    #[test]
+    fn grid_skips_sets_that_break_a_parameter_ordering() {
+        let data = synthetic::generate("BTC/USD", "2023-01-01", "2023-06-30", &SyntheticConfig::default()).unwrap();
+        let strategy = StrategyConfig { name: "moving_average_crossover".to_string(), params: HashMap::new() };
+        let ranges: Vec<ParamRange> = ["fast_period=5:15:5", "slow_period=10:20:5"].iter().map(|r| r.parse().unwrap()).collect();
+
+        let results = grid_search(&strategy, &ranges, &data, &BacktestConfig::default(), Objective::Sharpe).unwrap();
+        let mut tried: Vec<(f64, f64)> = results.iter().map(|r| (r.params["fast_period"], r.params["slow_period"])).collect();
+        tried.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(tried, vec![(5.0, 10.0), (5.0, 15.0), (5.0, 20.0), (10.0, 15.0), (10.0, 20.0), (15.0, 20.0)]);
+
+        let crossed: Vec<ParamRange> = ["fast_period=20:30:5", "slow_period=10:20:5"].iter().map(|r| r.parse().unwrap()).collect();
+        let error = grid_search(&strategy, &crossed, &data, &BacktestConfig::default(), Objective::Sharpe).err().unwrap();
+        assert!(error.to_string().contains("no parameter set is valid"), "{}", error);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::models::{Position, Trade};
+use std::collections::HashMap;
+
+/// Portfolio ledger tracking cash, open positions and realized/unrealized P&L
+///
+/// Fills are netted per symbol: a fill in the direction of the open position adds to it
+/// (averaging the entry price), a fill against it reduces or reverses it. Every closed
+/// slice of a position is recorded as a round trip in `closed_positions`.
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    pub initial_capital: f64,
+    pub cash: f64,
+    pub positions: HashMap<String, Position>,
+    pub closed_positions: Vec<Position>,
+    pub trades: Vec<Trade>,
+    pub realized_pnl: f64,
+    pub total_costs: f64,
+    pub cash_flows: HashMap<String, f64>, // Net cash paid/received per symbol
+}
+
+impl Portfolio {
+    pub fn new(initial_capital: f64) -> Self {
+        Self {
+            initial_capital,
+            cash: initial_capital,
+            positions: HashMap::new(),
+            closed_positions: Vec::new(),
+            trades: Vec::new(),
+            realized_pnl: 0.0,
+            total_costs: 0.0,
+            cash_flows: HashMap::new(),
+        }
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&Position> {
+        self.positions.get(symbol)
+    }
+
+    // Book a fill against the ledger
+    pub fn apply_fill(&mut self, trade: &Trade) {
+        let sign = trade.direction.sign();
+        self.cash -= sign * trade.price * trade.size + trade.costs;
+        *self.cash_flows.entry(trade.symbol.clone()).or_insert(0.0) -= sign * trade.price * trade.size + trade.costs;
+        self.total_costs += trade.costs;
+        self.trades.push(trade.clone());
+
+        let mut remaining = trade.size;
+        let mut remaining_costs = trade.costs;
+
+        if let Some(position) = self.positions.get_mut(&trade.symbol) {
+            if position.direction == trade.direction {
+                // Adding to the position: average the entry price
+                let new_size = position.size + trade.size;
+                position.entry_price = (position.entry_price * position.size + trade.price * trade.size) / new_size;
+                position.size = new_size;
+                position.costs += trade.costs;
+                position.current_price = trade.price;
+                position.profit_loss = unrealized(position);
+                return;
+            }
+
+            // Reducing or reversing the position
+            let closed_size = remaining.min(position.size);
+            let exit_costs = trade.costs * closed_size / trade.size;
+            let entry_costs = position.costs * closed_size / position.size;
+            let gross = (trade.price - position.entry_price) * closed_size * position.direction.sign();
+
+            self.realized_pnl += gross;
+            self.closed_positions.push(Position {
+                symbol: position.symbol.clone(),
+                direction: position.direction,
+                entry_price: position.entry_price,
+                current_price: trade.price,
+                size: closed_size,
+                entry_time: position.entry_time,
+                exit_time: Some(trade.timestamp),
+                exit_price: Some(trade.price),
+                costs: entry_costs + exit_costs,
+                profit_loss: gross - entry_costs - exit_costs,
+            });
+
+            position.size -= closed_size;
+            position.costs -= entry_costs;
+            position.current_price = trade.price;
+            position.profit_loss = unrealized(position);
+
+            remaining -= closed_size;
+            remaining_costs -= exit_costs;
+
+            if position.size <= f64::EPSILON {
+                self.positions.remove(&trade.symbol);
+            }
+        }
+
+        // Whatever is left opens a new position
+        if remaining > f64::EPSILON {
+            self.positions.insert(trade.symbol.clone(), Position {
+                symbol: trade.symbol.clone(),
+                direction: trade.direction,
+                entry_price: trade.price,
+                current_price: trade.price,
+                size: remaining,
+                entry_time: trade.timestamp,
+                exit_time: None,
+                exit_price: None,
+                costs: remaining_costs,
+                profit_loss: 0.0,
+            });
+        }
+    }
+
+    // Revalue the open position in `symbol` at the latest price
+    pub fn mark_to_market(&mut self, symbol: &str, price: f64) {
+        if let Some(position) = self.positions.get_mut(symbol) {
+            position.current_price = price;
+            position.profit_loss = unrealized(position);
+        }
+    }
+
+    // Net P&L contributed by `symbol` so far, realized and unrealized, after costs
+    pub fn symbol_pnl(&self, symbol: &str) -> f64 {
+        let cash_flow = self.cash_flows.get(symbol).copied().unwrap_or(0.0);
+        let market_value = self.position(symbol)
+            .map(|p| p.direction.sign() * p.size * p.current_price)
+            .unwrap_or(0.0);
+        cash_flow + market_value
+    }
+
+    // Cash plus the marked-to-market value of all open positions
+    pub fn equity(&self) -> f64 {
+        self.cash
+            + self.positions.values()
+                .map(|p| p.direction.sign() * p.size * p.current_price)
+                .sum::<f64>()
+    }
+}
+
+// Gross unrealized P&L of an open position
+fn unrealized(position: &Position) -> f64 {
+    (position.current_price - position.entry_price) * position.size * position.direction.sign()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TradeDirection;
+    use crate::models::fixtures::day;
+
+    fn fill(n: i64, direction: TradeDirection, price: f64, size: f64, costs: f64) -> Trade {
+        Trade {
+            timestamp: day(n),
+            symbol: "TEST".to_string(),
+            direction,
+            price,
+            size,
+            costs,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn adding_averages_the_entry_price() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        portfolio.apply_fill(&fill(0, TradeDirection::Long, 100.0, 10.0, 1.0));
+        portfolio.apply_fill(&fill(1, TradeDirection::Long, 110.0, 30.0, 3.0));
+
+        let position = portfolio.position("TEST").unwrap();
+        assert_close(position.size, 40.0);
+        assert_close(position.entry_price, 107.5);
+        assert_close(position.costs, 4.0);
+        assert_close(portfolio.cash, 10_000.0 - 1_000.0 - 3_300.0 - 4.0);
+    }
+
+    #[test]
+    fn partial_close_realizes_the_closed_slice() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        portfolio.apply_fill(&fill(0, TradeDirection::Long, 100.0, 10.0, 2.0));
+        portfolio.apply_fill(&fill(1, TradeDirection::Short, 120.0, 4.0, 1.0));
+
+        assert_close(portfolio.realized_pnl, 80.0);
+        let closed = &portfolio.closed_positions[0];
+        assert_close(closed.size, 4.0);
+        assert_close(closed.costs, 0.8 + 1.0);
+        assert_close(closed.profit_loss, 80.0 - 1.8);
+
+        let open = portfolio.position("TEST").unwrap();
+        assert_eq!(open.direction, TradeDirection::Long);
+        assert_close(open.size, 6.0);
+        assert_close(open.entry_price, 100.0);
+        assert_close(open.costs, 1.2);
+    }
+
+    #[test]
+    fn reversing_closes_through_flat_and_opens_the_rest() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        portfolio.apply_fill(&fill(0, TradeDirection::Long, 100.0, 10.0, 0.0));
+        portfolio.apply_fill(&fill(1, TradeDirection::Short, 90.0, 15.0, 3.0));
+
+        assert_close(portfolio.realized_pnl, -100.0);
+        assert_eq!(portfolio.closed_positions.len(), 1);
+        assert_close(portfolio.closed_positions[0].size, 10.0);
+        assert_close(portfolio.closed_positions[0].costs, 2.0);
+
+        // The short keeps the exit costs of the slice it opened
+        let short = portfolio.position("TEST").unwrap();
+        assert_eq!(short.direction, TradeDirection::Short);
+        assert_close(short.size, 5.0);
+        assert_close(short.entry_price, 90.0);
+        assert_close(short.costs, 1.0);
+        assert_eq!(short.entry_time, day(1));
+    }
+
+    #[test]
+    fn closing_exactly_leaves_no_position() {
+        let mut portfolio = Portfolio::new(1_000.0);
+        portfolio.apply_fill(&fill(0, TradeDirection::Short, 50.0, 2.0, 0.5));
+        portfolio.apply_fill(&fill(1, TradeDirection::Long, 40.0, 2.0, 0.5));
+
+        assert!(portfolio.position("TEST").is_none());
+        assert_close(portfolio.realized_pnl, 20.0);
+        assert_close(portfolio.closed_positions[0].profit_loss, 19.0);
+        assert_close(portfolio.equity(), 1_000.0 + 19.0);
+    }
+
+    #[test]
+    fn unrealized_pnl_follows_the_mark() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        portfolio.apply_fill(&fill(0, TradeDirection::Short, 100.0, 10.0, 5.0));
+        portfolio.mark_to_market("TEST", 95.0);
+
+        assert_close(portfolio.position("TEST").unwrap().profit_loss, 50.0);
+        assert_close(portfolio.realized_pnl, 0.0);
+        assert_close(portfolio.equity(), 10_000.0 + 50.0 - 5.0);
+        assert_close(portfolio.symbol_pnl("TEST"), 45.0);
+    }
+
+    #[test]
+    fn costs_reduce_cash_and_symbol_pnl() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        portfolio.apply_fill(&fill(0, TradeDirection::Long, 100.0, 10.0, 1.5));
+        portfolio.apply_fill(&fill(1, TradeDirection::Short, 100.0, 10.0, 1.5));
+
+        assert_close(portfolio.total_costs, 3.0);
+        assert_close(portfolio.realized_pnl, 0.0);
+        assert_close(portfolio.cash, 10_000.0 - 3.0);
+        assert_close(portfolio.symbol_pnl("TEST"), -3.0);
+        let costs: f64 = portfolio.closed_positions.iter().map(|p| p.costs).sum();
+        assert_close(costs, 3.0);
+    }
+}
@@ -0,0 +1,291 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Value type of a strategy or execution algorithm parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamKind {
+    Integer,
+    Float,
+}
+
+impl fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamKind::Integer => write!(f, "integer"),
+            ParamKind::Float => write!(f, "float"),
+        }
+    }
+}
+
+/// One tunable parameter with its default and the values it accepts
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamSpec {
+    pub name: String,
+    pub kind: ParamKind,
+    pub default: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub description: String,
+}
+
+impl ParamSpec {
+    pub fn integer(name: &str, default: usize, min: usize, max: usize, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: ParamKind::Integer,
+            default: default as f64,
+            min: Some(min as f64),
+            max: Some(max as f64),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn float(name: &str, default: f64, min: f64, max: f64, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: ParamKind::Float,
+            default,
+            min: Some(min),
+            max: Some(max),
+            description: description.to_string(),
+        }
+    }
+
+    // Check `value` against the kind and bounds
+    fn check(&self, value: f64) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err(format!("{} must be a finite number, got {}", self.name, value));
+        }
+        if self.kind == ParamKind::Integer && value.fract() != 0.0 {
+            return Err(format!("{} must be a whole number, got {}", self.name, value));
+        }
+        if self.min.is_some_and(|min| value < min) || self.max.is_some_and(|max| value > max) {
+            return Err(format!("{} must be in {}, got {}", self.name, self.range(), value));
+        }
+        Ok(())
+    }
+
+    // Accepted range, e.g. "[2, 500]" or "any"
+    pub fn range(&self) -> String {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => format!("[{}, {}]", min, max),
+            (Some(min), None) => format!(">= {}", min),
+            (None, Some(max)) => format!("<= {}", max),
+            (None, None) => "any".to_string(),
+        }
+    }
+}
+
+/// Constraint across parameters, e.g. a fast period below the slow one
+pub type Check = fn(&Params) -> Result<(), String>;
+
+/// Name, description and parameter schema of a strategy or execution algorithm
+#[derive(Debug, Clone, Serialize)]
+pub struct Descriptor {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    pub description: String,
+    pub params: Vec<ParamSpec>,
+    #[serde(skip)]
+    pub checks: Vec<Check>,
+}
+
+impl Descriptor {
+    pub fn new(name: &str, description: &str, params: Vec<ParamSpec>) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            description: description.to_string(),
+            params,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn with_alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    pub fn with_params(mut self, params: Vec<ParamSpec>) -> Self {
+        self.params.extend(params);
+        self
+    }
+
+    pub fn with_check(mut self, check: Check) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|a| a == name)
+    }
+
+    // Fill in defaults for `overrides`, rejecting unknown names, out-of-range values and
+    // combinations that fail a cross-parameter check
+    pub fn validate(&self, overrides: &HashMap<String, f64>) -> Result<Params, String> {
+        let params = self.validate_each(overrides)?;
+        self.check(&params)?;
+        Ok(params)
+    }
+
+    // Fill in defaults for `overrides`, checking each parameter on its own
+    pub fn validate_each(&self, overrides: &HashMap<String, f64>) -> Result<Params, String> {
+        let mut values = BTreeMap::new();
+        for spec in &self.params {
+            let value = overrides.get(&spec.name).copied().unwrap_or(spec.default);
+            spec.check(value).map_err(|e| format!("{}: {}", self.name, e))?;
+            values.insert(spec.name.clone(), value);
+        }
+
+        let mut unknown: Vec<&String> = overrides.keys().filter(|k| !values.contains_key(*k)).collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            let expected: Vec<&str> = self.params.iter().map(|p| p.name.as_str()).collect();
+            return Err(format!(
+                "{} has no parameter {} (expected {})",
+                self.name,
+                unknown.iter().map(|k| format!("'{}'", k)).collect::<Vec<_>>().join(", "),
+                if expected.is_empty() { "none".to_string() } else { expected.join(", ") },
+            ));
+        }
+
+        Ok(Params { values })
+    }
+
+    // Run the cross-parameter checks on values that passed `validate_each`
+    pub fn check(&self, params: &Params) -> Result<(), String> {
+        self.checks.iter().try_for_each(|check| check(params).map_err(|e| format!("{}: {}", self.name, e)))
+    }
+}
+
+// Check for `Descriptor::with_check` that `low` is below `high`
+pub fn less_than(params: &Params, low: &str, high: &str) -> Result<(), String> {
+    let (a, b) = (params.float(low), params.float(high));
+    if a < b { Ok(()) } else { Err(format!("{} must be less than {}, got {} and {}", low, high, a, b)) }
+}
+
+// Check for `Descriptor::with_check` that `low` is at most `high`
+pub fn at_most(params: &Params, low: &str, high: &str) -> Result<(), String> {
+    let (a, b) = (params.float(low), params.float(high));
+    if a <= b { Ok(()) } else { Err(format!("{} must be at most {}, got {} and {}", low, high, a, b)) }
+}
+
+/// Parameter values that passed `Descriptor::validate`
+#[derive(Debug, Clone)]
+pub struct Params {
+    values: BTreeMap<String, f64>,
+}
+
+impl Params {
+    // Parameters are validated against the schema that declares them, so a missing one is a
+    // bug in the type's `from_params`
+    pub fn float(&self, name: &str) -> f64 {
+        self.values[name]
+    }
+
+    pub fn integer(&self, name: &str) -> usize {
+        self.values[name] as usize
+    }
+
+    pub fn values(&self) -> &BTreeMap<String, f64> {
+        &self.values
+    }
+}
+
+/// Implemented by every strategy and execution algorithm the factories can build by name
+pub trait Configurable: Sized {
+    fn descriptor() -> Descriptor;
+    fn from_params(params: &Params) -> Self;
+}
+
+/// Descriptor and constructor of one buildable type, boxed as the trait object `T`
+pub struct Registration<T: ?Sized> {
+    pub descriptor: Descriptor,
+    pub build: fn(&Params) -> Box<T>,
+}
+
+impl<T: ?Sized> Registration<T> {
+    // Validate `overrides` against the schema and build
+    pub fn create(&self, overrides: &HashMap<String, f64>) -> Result<Box<T>, String> {
+        let params = self.descriptor.validate(overrides)?;
+        Ok((self.build)(&params))
+    }
+}
+
+// Look `name` up in `registry`, listing what exists when it is unknown
+pub fn find<'a, T: ?Sized>(registry: &'a [Registration<T>], kind: &str, name: &str) -> Result<&'a Registration<T>, String> {
+    registry.iter().find(|r| r.descriptor.matches(name)).ok_or_else(|| {
+        let names: Vec<&str> = registry.iter().map(|r| r.descriptor.name.as_str()).collect();
+        format!("unknown {} '{}' (available: {})", kind, name, names.join(", "))
+    })
+}
+
+// Parse a `name:key=value,key=value` spec as used on the command line
+pub fn parse_spec(spec: &str) -> Result<(String, HashMap<String, f64>), String> {
+    let Some((name, params)) = spec.split_once(':') else {
+        return Ok((spec.to_string(), HashMap::new()));
+    };
+
+    let params = params
+        .split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| {
+            let (key, value) = p.split_once('=').ok_or_else(|| format!("expected key=value in '{}', got '{}'", spec, p))?;
+            let value = value.trim().parse::<f64>().map_err(|e| format!("invalid value for '{}' in '{}': {}", key, spec, e))?;
+            Ok((key.trim().to_string(), value))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok((name.to_string(), params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{execution, strategies};
+
+    fn overrides(pairs: &[(&str, f64)]) -> HashMap<String, f64> {
+        pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn checks_run_after_each_parameter_passes() {
+        let descriptor = Descriptor::new("demo", "", vec![
+            ParamSpec::integer("fast", 5, 1, 100, ""),
+            ParamSpec::integer("slow", 20, 1, 100, ""),
+        ])
+        .with_check(|p| less_than(p, "fast", "slow"));
+
+        assert!(descriptor.validate(&overrides(&[])).is_ok());
+        assert_eq!(
+            descriptor.validate(&overrides(&[("fast", 20.0)])).unwrap_err(),
+            "demo: fast must be less than slow, got 20 and 20",
+        );
+        // A bad value on its own is reported before any combination
+        assert!(descriptor.validate(&overrides(&[("fast", 0.0)])).unwrap_err().contains("fast must be in [1, 100]"));
+        assert!(descriptor.validate_each(&overrides(&[("fast", 50.0)])).is_ok());
+    }
+
+    #[test]
+    fn crossovers_need_a_fast_period_below_the_slow_one() {
+        for name in ["moving_average_crossover", "multi_timeframe_trend", "macd"] {
+            let descriptor = strategies::describe_strategy(name).unwrap();
+            assert!(descriptor.validate(&overrides(&[("fast_period", 10.0), ("slow_period", 11.0)])).is_ok(), "{}", name);
+            for fast in [11.0, 12.0] {
+                let error = descriptor.validate(&overrides(&[("fast_period", fast), ("slow_period", 11.0)])).unwrap_err();
+                assert!(error.contains("fast_period must be less than slow_period"), "{}: {}", name, error);
+            }
+        }
+    }
+
+    #[test]
+    fn participation_bounds_must_be_ordered() {
+        for name in ["adaptive", "pov"] {
+            assert!(execution::create_execution_algorithm(name, &overrides(&[("min_rate", 0.2), ("max_rate", 0.2)])).is_ok(), "{}", name);
+            let error = execution::create_execution_algorithm(name, &overrides(&[("min_rate", 0.3), ("max_rate", 0.2)])).err().unwrap();
+            assert!(error.to_string().contains("min_rate must be at most max_rate"), "{}: {}", name, error);
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use crate::models::{BacktestResult, MarketData, Position, TradeDirection};
+use crate::rules;
+use chrono::{DateTime, Datelike, Utc};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const WIDTH: u32 = 1200;
+const TITLE_HEIGHT: u32 = 50;
+const PRICE_HEIGHT: u32 = 480;
+const EQUITY_HEIGHT: u32 = 240;
+const DRAWDOWN_HEIGHT: u32 = 200;
+const HISTOGRAM_HEIGHT: u32 = 280;
+const ROW_HEIGHT: u32 = 28;
+const METRIC_COLUMNS: usize = 3;
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+const GAIN: RGBColor = RGBColor(46, 160, 67);
+const LOSS: RGBColor = RGBColor(214, 39, 40);
+
+// Indicators on the price axis; oscillators such as RSI or MACD have scales of their own
+const PRICE_INDICATORS: [&str; 10] = ["sma", "ema", "wma", "kama", "vwap", "bollinger", "keltner", "donchian", "ichimoku", "supertrend"];
+
+type Area<'a> = DrawingArea<SVGBackend<'a>, Shift>;
+type Panel<'r> = fn(&Report<'r>, &Area) -> Result<(), Box<dyn Error>>;
+
+/// How a report is written, chosen by the output file's extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html, // One page with the metrics as a table and each chart as inline SVG
+    Svg,  // One image with the charts stacked above the metrics
+}
+
+impl ReportFormat {
+    pub fn from_path(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("html") | Some("htm") => Ok(ReportFormat::Html),
+            Some("svg") => Ok(ReportFormat::Svg),
+            _ => Err(format!("Unsupported report format: {} (expected .html or .svg)", path.display()).into()),
+        }
+    }
+}
+
+/// Indicator line drawn over the price chart, one value per candle
+pub struct Overlay {
+    pub name: String,
+    pub values: Vec<Option<f64>>,
+}
+
+// Overlays for indicator calls written as in rule files, e.g. `ema(50)` or `bollinger(20, 2)`
+pub fn overlays(calls: &[String], data: &MarketData) -> Result<Vec<Overlay>, Box<dyn Error>> {
+    let mut overlays = Vec::new();
+    for call in calls {
+        let lines = rules::indicator_lines(call, &data.candles)?;
+        let name = call.split('(').next().unwrap_or_default().trim().to_lowercase();
+        if !PRICE_INDICATORS.contains(&name.as_str()) {
+            return Err(format!("{} is not on the price scale, overlays can be {}", call, PRICE_INDICATORS.join(", ")).into());
+        }
+        overlays.extend(lines.into_iter()
+            .filter(|(line, _)| !line.ends_with(".uptrend")) // SuperTrend's direction flag
+            .map(|(name, values)| Overlay { name, values }));
+    }
+    Ok(overlays)
+}
+
+/// Self-contained report of one backtest
+///
+/// Charts the traded symbol's closes with the overlays and every round trip's entry and exit,
+/// the equity curve, the underwater drawdown, monthly returns and the distribution of round
+/// trip P&L, next to a table of the performance report. Everything is drawn with plotters'
+/// SVG backend, so the file has no external dependencies and opens offline.
+pub fn write_report(
+    result: &BacktestResult,
+    data: &MarketData,
+    overlays: &[Overlay],
+    format: ReportFormat,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let report = Report { result, data, overlays, monthly: monthly_returns(&result.equity_curve) };
+    let contents = match format {
+        ReportFormat::Html => report.html()?,
+        ReportFormat::Svg => report.svg()?,
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+struct Report<'a> {
+    result: &'a BacktestResult,
+    data: &'a MarketData,
+    overlays: &'a [Overlay],
+    monthly: BTreeMap<i32, [Option<f64>; 12]>,
+}
+
+impl<'a> Report<'a> {
+    fn title(&self) -> String {
+        match (self.data.candles.first(), self.data.candles.last()) {
+            (Some(first), Some(last)) => format!(
+                "{} on {}, {} to {} ({} bars)",
+                self.result.strategy_name,
+                self.data.symbol,
+                first.timestamp.format("%Y-%m-%d"),
+                last.timestamp.format("%Y-%m-%d"),
+                self.data.timeframe,
+            ),
+            _ => format!("{} on {}", self.result.strategy_name, self.data.symbol),
+        }
+    }
+
+    fn panels(&self) -> Vec<(u32, Panel<'a>)> {
+        vec![
+            (PRICE_HEIGHT, Self::price_chart),
+            (EQUITY_HEIGHT, Self::equity_chart),
+            (DRAWDOWN_HEIGHT, Self::drawdown_chart),
+            (ROW_HEIGHT * (self.monthly.len().max(1) as u32 + 2) + 40, Self::monthly_heatmap),
+            (HISTOGRAM_HEIGHT, Self::pnl_histogram),
+        ]
+    }
+
+    fn html(&self) -> Result<String, Box<dyn Error>> {
+        let mut rows = String::new();
+        for chunk in self.metrics().chunks(METRIC_COLUMNS) {
+            let cells: String = chunk.iter().map(|(label, value)| format!("<th>{}</th><td>{}</td>", escape(label), escape(value))).collect();
+            rows.push_str(&format!("<tr>{}</tr>\n", cells));
+        }
+
+        let mut figures = String::new();
+        for (height, panel) in self.panels() {
+            figures.push_str(&format!("<figure>{}</figure>\n", render(height, |area| panel(self, area))?));
+        }
+
+        Ok(format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body {{ font-family: sans-serif; color: #222; max-width: {width}px; margin: 24px auto; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 24px; }}\n\
+             th, td {{ padding: 4px 12px; border-bottom: 1px solid #ddd; }}\n\
+             th {{ text-align: left; font-weight: normal; color: #555; }}\n\
+             td {{ text-align: right; padding-right: 32px; }}\n\
+             figure {{ margin: 0 0 16px; }}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<table>\n{rows}</table>\n{figures}</body>\n</html>\n",
+            title = escape(&self.title()),
+            width = WIDTH,
+            rows = rows,
+            figures = figures,
+        ))
+    }
+
+    fn svg(&self) -> Result<String, Box<dyn Error>> {
+        let panels = self.panels();
+        let metrics = self.metrics();
+        let metrics_height = ROW_HEIGHT * metrics.len().div_ceil(METRIC_COLUMNS) as u32 + 20;
+        let height = TITLE_HEIGHT + panels.iter().map(|(h, _)| h).sum::<u32>() + metrics_height;
+
+        render(height, |root| {
+            let (title, mut rest) = root.split_vertically(TITLE_HEIGHT);
+            title.draw(&Text::new(self.title(), (20, 15), ("sans-serif", 24)))?;
+            for (height, panel) in panels {
+                let (top, bottom) = rest.split_vertically(height);
+                panel(self, &top)?;
+                rest = bottom;
+            }
+            draw_metrics(&rest, &metrics)
+        })
+    }
+
+    // Label and formatted value of every metric in the table
+    fn metrics(&self) -> Vec<(String, String)> {
+        let result = self.result;
+        let a = &result.analytics;
+        let percent = |value: f64| format!("{:.2}%", value * 100.0);
+        let optional = |value: Option<f64>| value.map_or("n/a".to_string(), |v| format!("{:.2}", v));
+
+        let mut metrics = vec![
+            ("Total P&L", format!("${:.2}", result.total_profit_loss)),
+            ("Total return", percent(a.total_return)),
+            ("CAGR", percent(a.cagr)),
+            ("Annual volatility", percent(a.annual_volatility)),
+            ("Sharpe ratio", format!("{:.2}", a.sharpe_ratio)),
+            ("Sortino ratio", format!("{:.2}", a.sortino_ratio)),
+            ("Calmar ratio", format!("{:.2}", a.calmar_ratio)),
+            ("Omega ratio", optional(a.omega_ratio)),
+            ("Max drawdown", percent(a.max_drawdown)),
+            ("Longest drawdown", format!("{:.1} days", a.max_drawdown_days)),
+            ("Round trips", result.positions.len().to_string()),
+            ("Win rate", percent(a.win_rate)),
+            ("Profit factor", optional(a.profit_factor)),
+            ("Expectancy", format!("${:.2}", a.expectancy)),
+            ("Average win", format!("${:.2}", a.avg_win)),
+            ("Average loss", format!("${:.2}", a.avg_loss)),
+            ("Max consecutive losses", a.max_consecutive_losses.to_string()),
+            ("Exposure time", percent(a.exposure_time)),
+            ("Turnover", format!("{:.2}x per year", a.turnover)),
+            ("Bars per year", format!("{:.0}", a.periods_per_year)),
+        ];
+        if let Some(b) = &a.benchmark {
+            metrics.extend([
+                ("Benchmark", b.symbol.clone()),
+                ("Benchmark return", percent(b.benchmark_return)),
+                ("Beta", format!("{:.2}", b.beta)),
+                ("Alpha", percent(b.alpha)),
+                ("Correlation", format!("{:.2}", b.correlation)),
+            ]);
+        }
+        metrics.into_iter().map(|(label, value)| (label.to_string(), value)).collect()
+    }
+
+    // Closes with the overlays, entry triangles (green long, red short) and exit crosses
+    fn price_chart(&self, area: &Area) -> Result<(), Box<dyn Error>> {
+        let candles = &self.data.candles;
+        let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+            return empty(area, "No bars");
+        };
+        if first.timestamp >= last.timestamp {
+            return empty(area, "Not enough bars to chart");
+        }
+
+        let overlay_values = self.overlays.iter().flat_map(|o| o.values.iter().flatten().copied());
+        let (low, high) = bounds(candles.iter().flat_map(|c| [c.low, c.high]).chain(overlay_values));
+        let mut chart = ChartBuilder::on(area)
+            .caption(format!("{} close", self.data.symbol), ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(first.timestamp..last.timestamp, low..high)?;
+        chart.configure_mesh()
+            .x_label_formatter(&|t| t.format(date_format(first.timestamp, last.timestamp)).to_string())
+            .y_desc("Price")
+            .draw()?;
+
+        chart.draw_series(LineSeries::new(candles.iter().map(|c| (c.timestamp, c.close)), &BLACK))?
+            .label("close")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+        for (i, overlay) in self.overlays.iter().enumerate() {
+            let color = Palette99::pick(i).to_rgba();
+            let points = candles.iter().zip(&overlay.values).filter_map(|(c, v)| v.map(|v| (c.timestamp, v)));
+            chart.draw_series(LineSeries::new(points, color))?
+                .label(&overlay.name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+
+        let positions: Vec<&Position> = self.result.positions.iter().filter(|p| p.symbol == self.data.symbol).collect();
+        for (direction, color, label) in [(TradeDirection::Long, GAIN, "long entry"), (TradeDirection::Short, LOSS, "short entry")] {
+            let entries: Vec<&&Position> = positions.iter().filter(|p| p.direction == direction).collect();
+            if !entries.is_empty() {
+                chart.draw_series(entries.iter().map(|p| TriangleMarker::new((p.entry_time, p.entry_price), 6, color.filled())))?
+                    .label(label)
+                    .legend(move |(x, y)| TriangleMarker::new((x + 10, y), 6, color.filled()));
+            }
+        }
+        let exits: Vec<(DateTime<Utc>, f64)> = positions.iter().filter_map(|p| Some((p.exit_time?, p.exit_price?))).collect();
+        if !exits.is_empty() {
+            chart.draw_series(exits.iter().map(|exit| Cross::new(*exit, 4, BLUE.stroke_width(2))))?
+                .label("exit")
+                .legend(|(x, y)| Cross::new((x + 10, y), 4, BLUE.stroke_width(2)));
+        }
+
+        chart.configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        Ok(())
+    }
+
+    fn equity_chart(&self, area: &Area) -> Result<(), Box<dyn Error>> {
+        let curve = &self.result.equity_curve;
+        let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+            return empty(area, "No equity curve");
+        };
+        if first.0 >= last.0 {
+            return empty(area, "Not enough bars to chart");
+        }
+
+        let (low, high) = bounds(curve.iter().map(|p| p.1));
+        let mut chart = ChartBuilder::on(area)
+            .caption("Equity", ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(first.0..last.0, low..high)?;
+        chart.configure_mesh()
+            .x_label_formatter(&|t| t.format(date_format(first.0, last.0)).to_string())
+            .y_desc("Equity")
+            .draw()?;
+        chart.draw_series(LineSeries::new(curve.iter().copied(), &BLUE))?;
+        Ok(())
+    }
+
+    // Percentage below the running equity peak
+    fn drawdown_chart(&self, area: &Area) -> Result<(), Box<dyn Error>> {
+        let curve = &self.result.equity_curve;
+        let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+            return empty(area, "No equity curve");
+        };
+        if first.0 >= last.0 {
+            return empty(area, "Not enough bars to chart");
+        }
+
+        let mut peak = f64::NEG_INFINITY;
+        let underwater: Vec<(DateTime<Utc>, f64)> = curve.iter()
+            .map(|&(timestamp, equity)| {
+                peak = peak.max(equity);
+                (timestamp, if peak > 0.0 { (equity / peak - 1.0) * 100.0 } else { 0.0 })
+            })
+            .collect();
+        let deepest = underwater.iter().map(|p| p.1).fold(0.0, f64::min);
+
+        let mut chart = ChartBuilder::on(area)
+            .caption("Drawdown", ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(first.0..last.0, (deepest * 1.05).min(-1.0)..0.0)?;
+        chart.configure_mesh()
+            .x_label_formatter(&|t| t.format(date_format(first.0, last.0)).to_string())
+            .y_desc("% below peak")
+            .draw()?;
+        chart.draw_series(AreaSeries::new(underwater, 0.0, LOSS.mix(0.3)).border_style(LOSS))?;
+        Ok(())
+    }
+
+    // Calendar of monthly returns, one row per year with the year's compounded return last
+    fn monthly_heatmap(&self, area: &Area) -> Result<(), Box<dyn Error>> {
+        let area = area.titled("Monthly returns", ("sans-serif", 20))?;
+        if self.monthly.is_empty() {
+            return empty(&area, "No equity curve");
+        }
+
+        let (label_width, height) = (70, ROW_HEIGHT as i32);
+        let cell_width = (WIDTH as i32 - label_width - 20) / 13;
+        let scale = self.monthly.values().flatten().flatten().fold(0.0_f64, |m, r| m.max(r.abs()));
+        let centered = TextStyle::from(("sans-serif", 13)).pos(Pos::new(HPos::Center, VPos::Center));
+        let cell = |column: i32, row: i32| {
+            let (x, y) = (label_width + column * cell_width, row * height);
+            ([(x, y), (x + cell_width - 2, y + height - 2)], (x + cell_width / 2, y + height / 2))
+        };
+
+        for (column, month) in MONTHS.iter().chain(["Year"].iter()).enumerate() {
+            area.draw(&Text::new(month.to_string(), cell(column as i32, 0).1, centered.clone()))?;
+        }
+        for (row, (year, months)) in self.monthly.iter().enumerate() {
+            let row = row as i32 + 1;
+            area.draw(&Text::new(year.to_string(), (label_width / 2, cell(0, row).1.1), centered.clone()))?;
+
+            let compounded = months.iter().flatten().fold(1.0, |total, r| total * (1.0 + r)) - 1.0;
+            let values = months.iter().copied().chain([Some(compounded)]);
+            for (column, value) in values.enumerate() {
+                let Some(value) = value else { continue };
+                let (corners, center) = cell(column as i32, row);
+                area.draw(&Rectangle::new(corners, shade(value, scale).filled()))?;
+                area.draw(&Text::new(format!("{:.1}%", value * 100.0), center, centered.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Histogram of round trip P&L, losing bins in red
+    fn pnl_histogram(&self, area: &Area) -> Result<(), Box<dyn Error>> {
+        let pnl: Vec<f64> = self.result.positions.iter().map(|p| p.profit_loss).collect();
+        if pnl.is_empty() {
+            let area = area.titled("Round trip P&L", ("sans-serif", 20))?;
+            return empty(&area, "No closed round trips");
+        }
+
+        let bins = ((pnl.len() as f64).sqrt().ceil() as usize).clamp(5, 40);
+        let (mut low, mut high) = pnl.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+        if high - low < 1e-9 {
+            (low, high) = (low - 1.0, high + 1.0);
+        }
+        let width = (high - low) / bins as f64;
+        let mut counts = vec![0usize; bins];
+        for value in &pnl {
+            counts[(((value - low) / width) as usize).min(bins - 1)] += 1;
+        }
+        let tallest = counts.iter().copied().max().unwrap_or(0);
+
+        let mut chart = ChartBuilder::on(area)
+            .caption("Round trip P&L", ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(70)
+            .build_cartesian_2d(low..high, 0.0..(tallest as f64 * 1.1).max(1.0))?;
+        chart.configure_mesh()
+            .y_label_formatter(&|count| format!("{:.0}", count))
+            .x_desc("P&L")
+            .y_desc("Round trips")
+            .draw()?;
+        chart.draw_series(counts.iter().enumerate().map(|(i, count)| {
+            let left = low + i as f64 * width;
+            let color = if left + width / 2.0 >= 0.0 { GAIN } else { LOSS };
+            Rectangle::new([(left, 0.0), (left + width, *count as f64)], color.mix(0.8).filled())
+        }))?;
+        Ok(())
+    }
+}
+
+// Draw into a new SVG document `height` pixels tall and return its markup
+fn render(height: u32, draw: impl FnOnce(&Area) -> Result<(), Box<dyn Error>>) -> Result<String, Box<dyn Error>> {
+    let mut svg = String::new();
+    {
+        let area = SVGBackend::with_string(&mut svg, (WIDTH, height)).into_drawing_area();
+        area.fill(&WHITE)?;
+        draw(&area)?;
+        area.present()?;
+    }
+    Ok(svg)
+}
+
+// Metrics table of the SVG report, filled row by row
+fn draw_metrics(area: &Area, metrics: &[(String, String)]) -> Result<(), Box<dyn Error>> {
+    let column_width = (WIDTH as i32 - 40) / METRIC_COLUMNS as i32;
+    let label = TextStyle::from(("sans-serif", 14)).color(&RGBColor(85, 85, 85));
+    let value = TextStyle::from(("sans-serif", 14)).pos(Pos::new(HPos::Right, VPos::Top));
+
+    for (i, (name, text)) in metrics.iter().enumerate() {
+        let x = 20 + (i % METRIC_COLUMNS) as i32 * column_width;
+        let y = 10 + (i / METRIC_COLUMNS) as i32 * ROW_HEIGHT as i32;
+        area.draw(&Text::new(name.clone(), (x, y), label.clone()))?;
+        area.draw(&Text::new(text.clone(), (x + column_width - 40, y), value.clone()))?;
+    }
+    Ok(())
+}
+
+fn empty(area: &Area, message: &str) -> Result<(), Box<dyn Error>> {
+    let (width, height) = area.dim_in_pixel();
+    let style = TextStyle::from(("sans-serif", 16)).pos(Pos::new(HPos::Center, VPos::Center));
+    area.draw(&Text::new(message.to_string(), (width as i32 / 2, height as i32 / 2), style))?;
+    Ok(())
+}
+
+// Dates on the time axis, with the time of day when the chart spans less than a month
+fn date_format(start: DateTime<Utc>, end: DateTime<Utc>) -> &'static str {
+    if end - start < chrono::Duration::days(30) { "%m-%d %H:%M" } else { "%Y-%m-%d" }
+}
+
+// Value range with a margin so lines do not run along the chart's edges
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    if !min.is_finite() || !max.is_finite() {
+        return (0.0, 1.0);
+    }
+    let margin = ((max - min) * 0.05).max(max.abs() * 1e-3).max(1e-9);
+    (min - margin, max + margin)
+}
+
+// Month-end to month-end returns of the equity curve by year, the first month measured from
+// the curve's first point
+fn monthly_returns(equity_curve: &[(DateTime<Utc>, f64)]) -> BTreeMap<i32, [Option<f64>; 12]> {
+    let mut month_ends: Vec<((i32, u32), f64)> = Vec::new();
+    for (timestamp, equity) in equity_curve {
+        let month = (timestamp.year(), timestamp.month0());
+        match month_ends.last_mut() {
+            Some((last, value)) if *last == month => *value = *equity,
+            _ => month_ends.push((month, *equity)),
+        }
+    }
+
+    let mut returns = BTreeMap::new();
+    let mut previous = equity_curve.first().map(|p| p.1);
+    for ((year, month), equity) in month_ends {
+        if let Some(start) = previous.filter(|start| *start > 0.0) {
+            returns.entry(year).or_insert([None; 12])[month as usize] = Some(equity / start - 1.0);
+        }
+        previous = Some(equity);
+    }
+    returns
+}
+
+// White for a flat month, deepening to green for gains and red for losses as large as `scale`
+fn shade(value: f64, scale: f64) -> RGBColor {
+    let t = if scale > 0.0 { (value.abs() / scale).min(1.0) * 0.8 } else { 0.0 };
+    let target = if value >= 0.0 { GAIN } else { LOSS };
+    let mix = |channel: u8| (255.0 + (channel as f64 - 255.0) * t).round() as u8;
+    RGBColor(mix(target.0), mix(target.1), mix(target.2))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
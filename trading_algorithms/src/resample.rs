@@ -0,0 +1,237 @@
+use crate::data;
+use crate::models::{Candle, MarketData};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+const WEEK_SECONDS: i64 = 7 * DAY_SECONDS;
+const FIRST_MONDAY: i64 = 4 * DAY_SECONDS; // 1970-01-05, the first Monday after the epoch
+
+/// Trading session used to align resampled bars
+///
+/// Daily and weekly bars start at `offset_minutes` past midnight UTC (weekly bars on Monday),
+/// and intraday bars never straddle the session boundary.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub offset_minutes: i64,
+}
+
+/// A higher timeframe of some base series, with a no-look-ahead index into it
+///
+/// `completed_index(i)` is the last higher-timeframe bar that had fully closed once base bar
+/// `i` closed, so a strategy called on bar `i` never sees a partially formed bar.
+#[derive(Debug, Clone)]
+pub struct MultiTimeframe {
+    pub data: MarketData,
+    completed: Vec<Option<usize>>,
+}
+
+impl MultiTimeframe {
+    pub fn new(base: &MarketData, timeframe: &str, session: Session) -> Result<Self, Box<dyn Error>> {
+        let base_seconds = bar_seconds(base)?;
+        let target_seconds = target_seconds(timeframe, base_seconds)?;
+        let data = resample_with_session(base, timeframe, session)?;
+
+        let mut completed = Vec::with_capacity(base.candles.len());
+        let mut bucket: Option<usize> = None;
+        let mut current_start = None;
+
+        for candle in &base.candles {
+            let (start, end) = bucket_bounds(candle.timestamp, target_seconds, session);
+            if current_start != Some(start) {
+                bucket = Some(bucket.map_or(0, |b| b + 1));
+                current_start = Some(start);
+            }
+
+            // The bucket is complete once a base bar reaching its end has closed
+            let bucket = bucket.unwrap_or(0);
+            if candle.timestamp.timestamp() + base_seconds >= end {
+                completed.push(Some(bucket));
+            } else {
+                completed.push(bucket.checked_sub(1));
+            }
+        }
+
+        Ok(Self { data, completed })
+    }
+
+    pub fn completed_index(&self, base_index: usize) -> Option<usize> {
+        self.completed.get(base_index).copied().flatten()
+    }
+
+    // Higher-timeframe bars known at base bar `base_index`, oldest first
+    pub fn completed_candles(&self, base_index: usize) -> &[Candle] {
+        match self.completed_index(base_index) {
+            Some(index) => &self.data.candles[..=index],
+            None => &[],
+        }
+    }
+}
+
+// Aggregate candles into a coarser timeframe ("5m", "1h", "1D", "1W", ...) with UTC sessions
+pub fn resample(data: &MarketData, timeframe: &str) -> Result<MarketData, Box<dyn Error>> {
+    resample_with_session(data, timeframe, Session::default())
+}
+
+pub fn resample_with_session(data: &MarketData, timeframe: &str, session: Session) -> Result<MarketData, Box<dyn Error>> {
+    let target_seconds = target_seconds(timeframe, bar_seconds(data)?)?;
+
+    let mut candles: Vec<Candle> = Vec::new();
+    let mut current_start = None;
+
+    for candle in &data.candles {
+        let (start, _) = bucket_bounds(candle.timestamp, target_seconds, session);
+
+        match candles.last_mut() {
+            Some(bar) if current_start == Some(start) => {
+                bar.high = bar.high.max(candle.high);
+                bar.low = bar.low.min(candle.low);
+                bar.close = candle.close;
+                bar.volume += candle.volume;
+            }
+            _ => {
+                candles.push(Candle {
+                    timestamp: Utc.timestamp_opt(start, 0).single().ok_or("Timestamp out of range")?,
+                    ..candle.clone()
+                });
+                current_start = Some(start);
+            }
+        }
+    }
+
+    Ok(MarketData::new(&data.symbol, timeframe, candles))
+}
+
+// Bar length of a series, from its label or else from the spacing of its bars
+fn bar_seconds(data: &MarketData) -> Result<i64, Box<dyn Error>> {
+    data::timeframe_seconds(&data.timeframe)
+        .or_else(|| data::infer_bar_seconds(&data.candles))
+        .ok_or_else(|| format!("Cannot determine the timeframe of {} data", data.symbol).into())
+}
+
+// Target bar length, which must be a whole multiple of the source bar length
+fn target_seconds(timeframe: &str, source_seconds: i64) -> Result<i64, Box<dyn Error>> {
+    let target = data::timeframe_seconds(timeframe).ok_or_else(|| format!("Invalid timeframe '{}'", timeframe))?;
+
+    if target < source_seconds || target % source_seconds != 0 {
+        return Err(format!(
+            "Cannot resample {} bars into {}: the target must be a multiple of the source timeframe",
+            data::timeframe_label(source_seconds),
+            timeframe
+        )
+        .into());
+    }
+
+    Ok(target)
+}
+
+// Start and end (epoch seconds) of the target bar containing `timestamp`
+fn bucket_bounds(timestamp: DateTime<Utc>, bar_seconds: i64, session: Session) -> (i64, i64) {
+    let offset = session.offset_minutes * 60;
+    let t = timestamp.timestamp() - offset;
+
+    let (start, end) = if bar_seconds % WEEK_SECONDS == 0 {
+        let start = FIRST_MONDAY + (t - FIRST_MONDAY).div_euclid(bar_seconds) * bar_seconds;
+        (start, start + bar_seconds)
+    } else if bar_seconds % DAY_SECONDS == 0 {
+        let start = t.div_euclid(bar_seconds) * bar_seconds;
+        (start, start + bar_seconds)
+    } else {
+        // Intraday bars restart at every session open
+        let day = t.div_euclid(DAY_SECONDS) * DAY_SECONDS;
+        let start = day + (t - day).div_euclid(bar_seconds) * bar_seconds;
+        (start, (start + bar_seconds).min(day + DAY_SECONDS))
+    };
+
+    (start + offset, end + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{day, flat};
+    use chrono::Duration;
+
+    // Hourly bars from midnight of `day(0)`, each closing one above its open
+    fn hourly(hours: usize) -> MarketData {
+        let candles = (0..hours)
+            .map(|h| Candle {
+                timestamp: day(0) + Duration::hours(h as i64),
+                open: 100.0 + h as f64,
+                high: 102.0 + h as f64,
+                low: 99.0 + h as f64,
+                close: 101.0 + h as f64,
+                volume: 1.0 + h as f64,
+            })
+            .collect();
+        MarketData::new("TEST", "1h", candles)
+    }
+
+    fn starts(data: &MarketData) -> Vec<DateTime<Utc>> {
+        data.candles.iter().map(|c| c.timestamp).collect()
+    }
+
+    #[test]
+    fn bars_aggregate_open_high_low_close_and_volume() {
+        let resampled = resample(&hourly(8), "4h").unwrap();
+
+        assert_eq!(resampled.timeframe, "4h");
+        let bars: Vec<_> = resampled.candles.iter().map(|c| (c.timestamp, c.open, c.high, c.low, c.close, c.volume)).collect();
+        assert_eq!(bars, vec![
+            (day(0), 100.0, 105.0, 99.0, 104.0, 1.0 + 2.0 + 3.0 + 4.0),
+            (day(0) + Duration::hours(4), 104.0, 109.0, 103.0, 108.0, 5.0 + 6.0 + 7.0 + 8.0),
+        ]);
+
+        assert!(resample(&hourly(8), "90m").is_err());
+        assert!(resample(&hourly(8), "30m").is_err());
+    }
+
+    #[test]
+    fn weekly_bars_start_on_monday() {
+        // 2023-01-01 is a Sunday, so it closes the week that began on 2022-12-26
+        let daily = MarketData::new("TEST", "1D", (0..9).map(|n| flat(n, 100.0 + n as f64)).collect());
+        let weekly = resample(&daily, "1W").unwrap();
+
+        assert_eq!(starts(&weekly), vec![day(-6), day(1), day(8)]);
+        let closes: Vec<f64> = weekly.candles.iter().map(|c| c.close).collect();
+        assert_eq!(closes, vec![100.0, 107.0, 108.0]);
+    }
+
+    #[test]
+    fn sessions_clip_intraday_bars_and_shift_daily_bars() {
+        // 5h bars cannot tile a day: the last one is cut short at midnight
+        let five_hours = resample(&hourly(26), "5h").unwrap();
+        assert_eq!(starts(&five_hours), [0, 5, 10, 15, 20, 24].map(|h| day(0) + Duration::hours(h)));
+        assert_eq!(five_hours.candles[4].volume, 21.0 + 22.0 + 23.0 + 24.0);
+
+        // Days that open at 01:00 put midnight in the previous session
+        let session = Session { offset_minutes: 60 };
+        let daily = resample_with_session(&hourly(26), "1D", session).unwrap();
+        let open = Duration::hours(1);
+        assert_eq!(starts(&daily), vec![day(-1) + open, day(0) + open, day(1) + open]);
+        assert_eq!(daily.candles[1].open, 101.0);
+        assert_eq!(daily.candles[1].close, 125.0);
+    }
+
+    #[test]
+    fn only_completed_higher_bars_are_visible() {
+        let base = hourly(10);
+        let four_hours = MultiTimeframe::new(&base, "4h", Session::default()).unwrap();
+
+        let completed: Vec<Option<usize>> = (0..10).map(|i| four_hours.completed_index(i)).collect();
+        assert_eq!(completed, vec![None, None, None, Some(0), Some(0), Some(0), Some(0), Some(1), Some(1), Some(1)]);
+        assert!(four_hours.completed_candles(2).is_empty());
+        assert_eq!(four_hours.completed_candles(6).len(), 1);
+        // The partly formed third bar exists but is not exposed yet
+        assert_eq!(four_hours.data.candles.len(), 3);
+        assert_eq!(four_hours.completed_candles(9).last().map(|c| c.close), Some(108.0));
+
+        // A bar clipped at the session end completes with the last base bar of the day
+        let five_hours = MultiTimeframe::new(&hourly(26), "5h", Session::default()).unwrap();
+        assert_eq!(five_hours.completed_index(22), Some(3));
+        assert_eq!(five_hours.completed_index(23), Some(4));
+        assert_eq!(five_hours.completed_index(25), Some(4));
+    }
+}
@@ -0,0 +1,514 @@
+use crate::analytics;
+use crate::config::BacktestConfig;
+use crate::indicators::{Atr, Indicator};
+use crate::matching::{Fill, MatchingEngine};
+use crate::models::{BacktestResult, MarketData, Order, OrderType, TradeDirection};
+use crate::portfolio::Portfolio;
+use crate::utils::risk;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How large a new position is when the strategy opens one from flat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PositionSizing {
+    Fixed { size: f64 },
+    PercentOfEquity { percent: f64 },
+    // Lose `risk_percent` of equity if the ATR stop is hit (the whole notional without a stop)
+    FixedFractional { risk_percent: f64 },
+    // Scale the position so its annualized volatility is `annual_volatility` of equity
+    VolatilityTarget { annual_volatility: f64, lookback: usize },
+    // Bet `fraction` of the Kelly fraction estimated from the symbol's closed trades,
+    // using `default_percent` of equity until there are `min_trades` of them
+    Kelly { fraction: f64, min_trades: usize, default_percent: f64 },
+}
+
+/// Protective stop `atr_multiplier` ATRs away from the entry price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopLossConfig {
+    pub atr_period: usize,
+    pub atr_multiplier: f64,
+}
+
+/// Stops, take-profits and exposure limits applied around whatever strategy is running
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub stop_loss: Option<StopLossConfig>,
+    pub take_profit_ratio: Option<f64>, // Target at this multiple of the stop distance
+    pub max_position: Option<f64>, // Largest position per symbol, in units
+    pub max_leverage: Option<f64>, // Largest gross exposure as a multiple of equity
+    pub daily_loss_limit: Option<f64>, // Percent of the day's starting equity
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExitKind {
+    StopLoss,
+    TakeProfit,
+}
+
+// Protective orders working for one position
+struct Bracket {
+    direction: TradeDirection,
+    size: f64,
+    order_ids: Vec<u64>,
+}
+
+/// Risk layer between a strategy and the matching engine
+///
+/// Orders opening a position from flat are sized by `PositionSizing`, then every order is
+/// cut down to the position, leverage and daily loss limits before it reaches the engine.
+/// Once a position is open, a one-cancels-other bracket of a stop and a take-profit limit
+/// order protects it from the next bar on, so exits are matched intrabar against the bars'
+/// highs and lows; when both levels fall inside one bar the stop is assumed to hit first.
+/// Breaching the daily loss limit flattens every position and blocks new exposure until
+/// the next UTC day.
+pub struct RiskManager {
+    sizing: PositionSizing,
+    config: RiskConfig,
+    brackets: HashMap<String, Bracket>,
+    atr: HashMap<String, (Atr, Option<f64>)>, // Streaming ATR per symbol and its latest value
+    exit_orders: HashMap<u64, ExitKind>,
+    pending_exits: Vec<Order>,
+    day: Option<NaiveDate>,
+    day_start_equity: f64,
+    last_equity: f64,
+    halted: bool,
+    stop_loss_exits: usize,
+    take_profit_exits: usize,
+    limited_orders: usize,
+}
+
+impl RiskManager {
+    pub fn new(config: &BacktestConfig) -> Self {
+        Self {
+            sizing: config.position_sizing.clone(),
+            config: config.risk.clone(),
+            brackets: HashMap::new(),
+            atr: HashMap::new(),
+            exit_orders: HashMap::new(),
+            pending_exits: Vec::new(),
+            day: None,
+            day_start_equity: config.initial_capital,
+            last_equity: config.initial_capital,
+            halted: false,
+            stop_loss_exits: 0,
+            take_profit_exits: 0,
+            limited_orders: 0,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        let c = &self.config;
+        c.stop_loss.is_some() || c.max_position.is_some() || c.max_leverage.is_some() || c.daily_loss_limit.is_some()
+    }
+
+    // Exits the risk layer decided on for `symbol`, to go out ahead of the strategy's orders
+    pub fn take_exits(&mut self, symbol: &str) -> Vec<Order> {
+        let (exits, rest) = self.pending_exits.drain(..).partition(|o| o.symbol == symbol);
+        self.pending_exits = rest;
+        exits
+    }
+
+    // Feed closed bar `index` of `data` to the symbol's ATR; bars the strategy only warms up
+    // on go through here too, so stops are available from the first traded bar
+    pub fn observe(&mut self, data: &MarketData, index: usize) {
+        let Some(stop) = &self.config.stop_loss else {
+            return;
+        };
+        let (atr, value) = self.atr.entry(data.symbol.clone()).or_insert_with(|| (Atr::new(stop.atr_period), None));
+        *value = atr.update(&data.candles[index]);
+    }
+
+    // ATR of the bars closed so far
+    fn atr(&self, symbol: &str) -> Option<f64> {
+        self.atr.get(symbol).and_then(|(_, value)| *value)
+    }
+
+    // Size of a position in `direction` opened at `price` on bar `index`
+    pub fn entry_size(&self, direction: TradeDirection, portfolio: &Portfolio, data: &MarketData, index: usize, price: f64) -> f64 {
+        if price <= 0.0 {
+            return 0.0;
+        }
+        let equity = portfolio.equity();
+
+        match &self.sizing {
+            PositionSizing::Fixed { size } => *size,
+            PositionSizing::PercentOfEquity { percent } => equity * percent / 100.0 / price,
+            PositionSizing::FixedFractional { risk_percent } => {
+                match (self.atr(&data.symbol), &self.config.stop_loss) {
+                    (Some(atr), Some(stop)) => {
+                        let stop_price = risk::atr_stop_loss(price, atr, stop.atr_multiplier, direction);
+                        risk::position_size(equity, *risk_percent, price, stop_price)
+                    }
+                    _ => equity * risk_percent / 100.0 / price,
+                }
+            }
+            PositionSizing::VolatilityTarget { annual_volatility, lookback } => {
+                let history = &data.candles[..index.min(data.candles.len())];
+                if *lookback < 2 || history.len() <= *lookback {
+                    return 0.0;
+                }
+
+                let returns: Vec<f64> = history[history.len() - lookback - 1..]
+                    .windows(2)
+                    .map(|w| w[1].close / w[0].close - 1.0)
+                    .collect();
+                let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+                let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+                let volatility = (variance * analytics::periods_per_year(history.iter().map(|c| c.timestamp))).sqrt();
+
+                if volatility <= 0.0 {
+                    return 0.0;
+                }
+                equity * annual_volatility / volatility / price
+            }
+            PositionSizing::Kelly { fraction, min_trades, default_percent } => {
+                let closed: Vec<f64> = portfolio.closed_positions.iter()
+                    .filter(|p| p.symbol == data.symbol)
+                    .map(|p| p.profit_loss)
+                    .collect();
+                if closed.len() < *min_trades || closed.is_empty() {
+                    return equity * default_percent / 100.0 / price;
+                }
+
+                let wins: Vec<f64> = closed.iter().copied().filter(|pnl| *pnl > 0.0).collect();
+                let losses: Vec<f64> = closed.iter().copied().filter(|pnl| *pnl <= 0.0).map(f64::abs).collect();
+                let win_rate = wins.len() as f64 / closed.len() as f64;
+
+                // f* = W - (1 - W) / R, with R the ratio of the average win to the average loss
+                let kelly = if losses.is_empty() || losses.iter().sum::<f64>() == 0.0 {
+                    1.0
+                } else if wins.is_empty() {
+                    0.0
+                } else {
+                    let payoff = (wins.iter().sum::<f64>() / wins.len() as f64) / (losses.iter().sum::<f64>() / losses.len() as f64);
+                    win_rate - (1.0 - win_rate) / payoff
+                };
+                (kelly * fraction).max(0.0) * equity / price
+            }
+        }
+    }
+
+    // Cut `order` down to the position and leverage limits, given the symbol's projected
+    // signed position. Orders that only reduce the position always pass. Returns false when
+    // nothing of the order is left.
+    pub fn limit_order(&mut self, order: &mut Order, position: f64, portfolio: &Portfolio, price: f64) -> bool {
+        let target = position + order.direction.sign() * order.size;
+        let same_side = target * position >= 0.0;
+        if same_side && target.abs() <= position.abs() {
+            return order.size > 0.0;
+        }
+
+        let mut cap = f64::INFINITY;
+        if let Some(max_position) = self.config.max_position {
+            cap = cap.min(max_position);
+        }
+        if let (Some(max_leverage), true) = (self.config.max_leverage, price > 0.0) {
+            let other_exposure: f64 = portfolio.positions.values()
+                .filter(|p| p.symbol != order.symbol)
+                .map(|p| p.size * p.current_price)
+                .sum();
+            cap = cap.min(((max_leverage * portfolio.equity() - other_exposure) / price).max(0.0));
+        }
+        if self.halted {
+            cap = 0.0;
+        }
+
+        // A position may always keep its current size on its own side
+        let floor = if same_side { position.abs() } else { 0.0 };
+        let allowed = target.abs().min(cap.max(floor));
+        let size = (target.signum() * allowed - position).abs();
+
+        if size < order.size - 1e-9 {
+            self.limited_orders += 1;
+            order.size = size;
+        }
+        order.size > 1e-9
+    }
+
+    // Pull the bracket of `symbol` before another order changes its position
+    pub fn cancel_bracket(&mut self, engine: &mut MatchingEngine, symbol: &str) {
+        if let Some(bracket) = self.brackets.remove(symbol) {
+            for id in bracket.order_ids {
+                engine.cancel(id);
+                self.exit_orders.remove(&id);
+            }
+        }
+    }
+
+    // Count the fills of bracket orders as stop-loss or take-profit exits
+    pub fn record_fills(&mut self, fills: &[Fill]) {
+        for fill in fills {
+            match self.exit_orders.remove(&fill.order_id) {
+                Some(ExitKind::StopLoss) => self.stop_loss_exits += 1,
+                Some(ExitKind::TakeProfit) => self.take_profit_exits += 1,
+                None => {}
+            }
+        }
+    }
+
+    // After bar `index` of `data` has closed: enforce the daily loss limit and make sure the
+    // symbol's position is protected by a bracket matching its current size
+    pub fn on_bar_close(&mut self, engine: &mut MatchingEngine, portfolio: &Portfolio, data: &MarketData, index: usize) {
+        self.observe(data, index);
+        let equity = portfolio.equity();
+
+        if let Some(limit) = self.config.daily_loss_limit {
+            let day = data.candles[index].timestamp.date_naive();
+            if self.day != Some(day) {
+                self.day = Some(day);
+                self.day_start_equity = self.last_equity;
+                self.halted = false;
+            }
+
+            if !self.halted && equity < self.day_start_equity * (1.0 - limit / 100.0) {
+                self.halted = true;
+                for position in portfolio.positions.values() {
+                    self.cancel_bracket(engine, &position.symbol);
+                    self.pending_exits.push(Order::market(&position.symbol, position.direction.opposite(), position.size));
+                }
+            }
+        }
+        self.last_equity = equity;
+
+        let position = portfolio.position(&data.symbol);
+        let current = position.map(|p| (p.direction, p.size));
+        let protected = self.brackets.get(&data.symbol).is_some_and(|bracket| {
+            current.is_some_and(|(direction, size)| direction == bracket.direction && (size - bracket.size).abs() <= 1e-9)
+        });
+        if protected {
+            return;
+        }
+        self.cancel_bracket(engine, &data.symbol);
+
+        let (Some(position), Some(stop), false) = (position, &self.config.stop_loss, self.halted) else {
+            return;
+        };
+        let Some(atr) = self.atr(&data.symbol) else {
+            return;
+        };
+
+        let exit = position.direction.opposite();
+        let stop_price = risk::atr_stop_loss(position.entry_price, atr, stop.atr_multiplier, position.direction);
+        let mut orders = vec![(ExitKind::StopLoss, Order::new(&data.symbol, exit, position.size, OrderType::Stop { trigger: stop_price }))];
+        if let Some(ratio) = self.config.take_profit_ratio {
+            let target = risk::take_profit(position.entry_price, stop_price, ratio, position.direction);
+            orders.push((ExitKind::TakeProfit, Order::new(&data.symbol, exit, position.size, OrderType::Limit { price: target })));
+        }
+
+        let kinds: Vec<ExitKind> = orders.iter().map(|(kind, _)| *kind).collect();
+        let ids = engine.submit_oco(orders.into_iter().map(|(_, order)| order).collect());
+        for (id, kind) in ids.iter().zip(kinds) {
+            self.exit_orders.insert(*id, kind);
+        }
+        self.brackets.insert(data.symbol.clone(), Bracket {
+            direction: position.direction,
+            size: position.size,
+            order_ids: ids,
+        });
+    }
+
+    pub fn add_metrics(&self, result: &mut BacktestResult) {
+        if !self.is_active() {
+            return;
+        }
+        result.metrics.insert("stop_loss_exits".to_string(), self.stop_loss_exits as f64);
+        result.metrics.insert("take_profit_exits".to_string(), self.take_profit_exits as f64);
+        result.metrics.insert("limited_orders".to_string(), self.limited_orders as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::MatchingConfig;
+    use crate::models::fixtures::{bar, day, flat};
+    use crate::models::{Candle, Position, Trade};
+    use chrono::Duration;
+
+    const SYMBOL: &str = "TEST";
+
+    fn manager(position_sizing: PositionSizing, risk: RiskConfig) -> RiskManager {
+        RiskManager::new(&BacktestConfig { initial_capital: 10_000.0, position_sizing, risk, ..BacktestConfig::default() })
+    }
+
+    fn atr_stop() -> RiskConfig {
+        RiskConfig { stop_loss: Some(StopLossConfig { atr_period: 2, atr_multiplier: 2.0 }), ..RiskConfig::default() }
+    }
+
+    // Two bars with a true range of 4 each, so a 2-bar ATR of 4 once both have closed
+    fn ranging() -> MarketData {
+        MarketData::new(SYMBOL, "1D", vec![bar(0, 100.0, 102.0, 98.0, 100.0), bar(1, 100.0, 104.0, 100.0, 102.0)])
+    }
+
+    fn buy(portfolio: &mut Portfolio, price: f64, size: f64) {
+        portfolio.apply_fill(&Trade {
+            timestamp: day(0),
+            symbol: SYMBOL.to_string(),
+            direction: TradeDirection::Long,
+            price,
+            size,
+            costs: 0.0,
+        });
+    }
+
+    fn closed(symbol: &str, profit_loss: f64) -> Position {
+        Position {
+            symbol: symbol.to_string(),
+            direction: TradeDirection::Long,
+            entry_price: 100.0,
+            current_price: 100.0,
+            size: 1.0,
+            entry_time: day(0),
+            exit_time: Some(day(1)),
+            exit_price: Some(100.0),
+            costs: 0.0,
+            profit_loss,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn fixed_and_fixed_fractional_sizing() {
+        let portfolio = Portfolio::new(10_000.0);
+        let data = ranging();
+
+        let fixed = manager(PositionSizing::Fixed { size: 3.0 }, RiskConfig::default());
+        assert_close(fixed.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 3.0);
+        let percent = manager(PositionSizing::PercentOfEquity { percent: 10.0 }, RiskConfig::default());
+        assert_close(percent.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 10.0);
+
+        // Without an ATR yet the whole notional is at risk: 1% of equity
+        let mut fractional = manager(PositionSizing::FixedFractional { risk_percent: 1.0 }, atr_stop());
+        assert_close(fractional.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 1.0);
+
+        // 1% of 10,000 lost over a stop 2 ATRs = 8 away
+        fractional.observe(&data, 0);
+        fractional.observe(&data, 1);
+        assert_close(fractional.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 12.5);
+        assert_close(fractional.entry_size(TradeDirection::Short, &portfolio, &data, 2, 100.0), 12.5);
+    }
+
+    #[test]
+    fn volatility_target_scales_by_annualized_volatility() {
+        let portfolio = Portfolio::new(10_000.0);
+        let data = MarketData::new(SYMBOL, "1D", vec![flat(0, 100.0), flat(1, 110.0), flat(2, 99.0), flat(3, 99.0)]);
+        let sizing = manager(PositionSizing::VolatilityTarget { annual_volatility: 0.2, lookback: 2 }, RiskConfig::default());
+
+        // Returns +10% and -10% on daily bars of a 24/7 market
+        let volatility = (0.02_f64 * 365.25).sqrt();
+        assert_close(sizing.entry_size(TradeDirection::Long, &portfolio, &data, 3, 99.0), 10_000.0 * 0.2 / volatility / 99.0);
+        // Not enough history before bar 2
+        assert_close(sizing.entry_size(TradeDirection::Long, &portfolio, &data, 2, 99.0), 0.0);
+    }
+
+    #[test]
+    fn kelly_bets_a_fraction_of_the_estimated_edge() {
+        let mut portfolio = Portfolio::new(10_000.0);
+        let data = ranging();
+        let sizing = manager(PositionSizing::Kelly { fraction: 0.5, min_trades: 3, default_percent: 2.0 }, RiskConfig::default());
+
+        portfolio.closed_positions = vec![closed(SYMBOL, 20.0), closed(SYMBOL, 20.0), closed("OTHER", -50.0)];
+        assert_close(sizing.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 2.0);
+
+        // W = 2/3 and R = 20 / 10 give f* = 2/3 - 1/3 / 2 = 0.5, half of which is bet
+        portfolio.closed_positions.push(closed(SYMBOL, -10.0));
+        assert_close(sizing.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 25.0);
+
+        // No edge left: f* = 1/2 - 1/2 / 1 < 0
+        portfolio.closed_positions = vec![closed(SYMBOL, 10.0), closed(SYMBOL, -10.0), closed(SYMBOL, 10.0), closed(SYMBOL, -10.0)];
+        assert_close(sizing.entry_size(TradeDirection::Long, &portfolio, &data, 2, 100.0), 0.0);
+    }
+
+    // A long of 10 from 100 protected by a 2-ATR stop and a 1.5R target
+    fn bracketed() -> (RiskManager, MatchingEngine) {
+        let mut risk = manager(PositionSizing::Fixed { size: 10.0 }, RiskConfig { take_profit_ratio: Some(1.5), ..atr_stop() });
+        let mut engine = MatchingEngine::new(MatchingConfig::default());
+        let mut portfolio = Portfolio::new(10_000.0);
+        buy(&mut portfolio, 100.0, 10.0);
+
+        let data = ranging();
+        risk.on_bar_close(&mut engine, &portfolio, &data, 0);
+        assert!(engine.open_orders().is_empty(), "no stop before the ATR has warmed up");
+        risk.on_bar_close(&mut engine, &portfolio, &data, 1);
+        (risk, engine)
+    }
+
+    #[test]
+    fn bracket_sits_atr_multiples_from_the_entry() {
+        let (_, engine) = bracketed();
+
+        let orders: Vec<(TradeDirection, f64, OrderType)> = engine.open_orders().iter()
+            .map(|w| (w.order.direction, w.order.size, w.order.order_type))
+            .collect();
+        assert_eq!(orders, vec![
+            (TradeDirection::Short, 10.0, OrderType::Stop { trigger: 92.0 }),
+            (TradeDirection::Short, 10.0, OrderType::Limit { price: 112.0 }),
+        ]);
+    }
+
+    #[test]
+    fn bracket_exits_intrabar_with_the_stop_first() {
+        let (mut risk, mut engine) = bracketed();
+        let fills = engine.process_bar(SYMBOL, &bar(2, 101.0, 113.0, 91.0, 100.0));
+        risk.record_fills(&fills);
+
+        assert_eq!(fills.len(), 1);
+        assert_close(fills[0].price, 92.0);
+        assert!(engine.open_orders().is_empty());
+        assert_eq!((risk.stop_loss_exits, risk.take_profit_exits), (1, 0));
+
+        let (mut risk, mut engine) = bracketed();
+        let fills = engine.process_bar(SYMBOL, &bar(2, 101.0, 113.0, 95.0, 110.0));
+        risk.record_fills(&fills);
+
+        assert_eq!(fills.len(), 1);
+        assert_close(fills[0].price, 112.0);
+        assert_eq!((risk.stop_loss_exits, risk.take_profit_exits), (0, 1));
+    }
+
+    #[test]
+    fn daily_loss_halt_resets_on_a_new_day() {
+        let mut risk = manager(PositionSizing::Fixed { size: 1.0 }, RiskConfig { daily_loss_limit: Some(5.0), ..RiskConfig::default() });
+        let mut engine = MatchingEngine::new(MatchingConfig::default());
+        let mut portfolio = Portfolio::new(10_000.0);
+        buy(&mut portfolio, 100.0, 100.0);
+
+        let at = |n: i64, hours: i64, close: f64| Candle { timestamp: day(n) + Duration::hours(hours), ..flat(n, close) };
+        let data = MarketData::new(SYMBOL, "1h", vec![at(0, 0, 100.0), at(0, 1, 90.0), at(0, 2, 90.0), at(1, 0, 90.0)]);
+        let buy_one = || Order::market(SYMBOL, TradeDirection::Long, 1.0);
+
+        risk.on_bar_close(&mut engine, &portfolio, &data, 0);
+        assert!(!risk.halted);
+
+        // 10% down on the day flattens the position and blocks new exposure
+        portfolio.mark_to_market(SYMBOL, 90.0);
+        risk.on_bar_close(&mut engine, &portfolio, &data, 1);
+        assert!(risk.halted);
+        let exits = risk.take_exits(SYMBOL);
+        assert_eq!(exits.len(), 1);
+        assert_eq!((exits[0].direction, exits[0].size), (TradeDirection::Short, 100.0));
+
+        portfolio.apply_fill(&Trade {
+            timestamp: data.candles[2].timestamp,
+            symbol: SYMBOL.to_string(),
+            direction: TradeDirection::Short,
+            price: 90.0,
+            size: 100.0,
+            costs: 0.0,
+        });
+        risk.on_bar_close(&mut engine, &portfolio, &data, 2);
+        assert!(!risk.limit_order(&mut buy_one(), 0.0, &portfolio, 90.0));
+
+        // The next UTC day starts from the equity left at the close
+        risk.on_bar_close(&mut engine, &portfolio, &data, 3);
+        assert!(!risk.halted);
+        assert_close(risk.day_start_equity, 9_000.0);
+        assert!(risk.limit_order(&mut buy_one(), 0.0, &portfolio, 90.0));
+    }
+}
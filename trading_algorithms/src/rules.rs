@@ -0,0 +1,814 @@
+use crate::config::BacktestConfig;
+use crate::indicators::{
+    Adx, Atr, Bollinger, Donchian, Ema, Ichimoku, Indicator, Kama, Keltner, Macd, Obv, Rsi, Sma, Stochastic, SuperTrend, Vwap, Wma,
+};
+use crate::models::{Candle, MarketData, Order, TradeDirection};
+use crate::portfolio::Portfolio;
+use crate::registry::{Descriptor, ParamKind, ParamSpec};
+use crate::risk::{PositionSizing, RiskConfig};
+use crate::strategies::{self, Strategy};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Strategy written as entry and exit conditions over indicators
+///
+/// Loaded from a TOML or JSON file, e.g.
+///
+/// ```toml
+/// name = "ema_rsi"
+/// entry.long = "ema($fast) crosses_above ema($slow) and rsi(14) < 70"
+/// exit.long = "ema($fast) crosses_below ema($slow) or rsi(14) > 80"
+///
+/// [params]
+/// fast = 12
+/// slow = 26
+/// ```
+///
+/// `$name` refers to `params`, which `--param` and the optimizer can override. Sizing and
+/// risk sections replace the backtest config's when the strategy runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleStrategyConfig {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub entry: Sides,
+    #[serde(default)]
+    pub exit: Sides,
+    #[serde(default)]
+    pub params: HashMap<String, f64>,
+    pub size: Option<f64>, // Units per order, each sized by the position sizing rule (1 by default)
+    pub position_sizing: Option<PositionSizing>,
+    pub risk: Option<RiskConfig>,
+}
+
+/// A condition per side of the book; a missing one never fires
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sides {
+    pub long: Option<String>,
+    pub short: Option<String>,
+}
+
+impl RuleStrategyConfig {
+    // Load a strategy definition from a `.toml` or `.json` file
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+
+        let config: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&contents)?,
+            Some("json") => serde_json::from_str(&contents)?,
+            _ => return Err(format!("Unsupported strategy format: {}", path.display()).into()),
+        };
+        config.compile(&HashMap::new())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    // Let the file's sizing and risk sections take over from the backtest config
+    pub fn apply_to(&self, config: &mut BacktestConfig) {
+        if let Some(sizing) = &self.position_sizing {
+            config.position_sizing = sizing.clone();
+        }
+        if let Some(risk) = &self.risk {
+            config.risk = risk.clone();
+        }
+    }
+
+    // The file's params as a schema; rule params are untyped numbers without bounds
+    pub fn descriptor(&self) -> Descriptor {
+        let mut params: Vec<ParamSpec> = self.params.iter()
+            .map(|(name, default)| ParamSpec {
+                name: name.clone(),
+                kind: ParamKind::Float,
+                default: *default,
+                min: None,
+                max: None,
+                description: format!("${} in the rules", name),
+            })
+            .collect();
+        params.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let description = if self.description.is_empty() { "Rule strategy" } else { &self.description };
+        Descriptor::new(&self.name, description, params)
+    }
+
+    // Parse every condition, with `overrides` taking precedence over the file's params
+    fn compile(&self, overrides: &HashMap<String, f64>) -> Result<RuleSet, String> {
+        let params = self.descriptor().validate(overrides)?.values().clone().into_iter().collect();
+
+        let mut parser_state = Registry { params, series: Vec::new() };
+        let mut parse = |label: &str, rule: &Option<String>| {
+            rule.as_deref()
+                .map(|text| Parser::new(text, &mut parser_state).and_then(Parser::parse).map_err(|e| format!("{}: {}", label, e)))
+                .transpose()
+        };
+
+        let entry_long = parse("entry.long", &self.entry.long)?;
+        let entry_short = parse("entry.short", &self.entry.short)?;
+        let exit_long = parse("exit.long", &self.exit.long)?;
+        let exit_short = parse("exit.short", &self.exit.short)?;
+        if entry_long.is_none() && entry_short.is_none() {
+            return Err("no entry.long or entry.short condition".to_string());
+        }
+
+        Ok(RuleSet { series: parser_state.series, entry_long, entry_short, exit_long, exit_short })
+    }
+}
+
+// Whether a strategy name refers to a rule file rather than a built-in strategy
+pub fn is_rule_file(name: &str) -> bool {
+    name.ends_with(".toml") || name.ends_with(".json")
+}
+
+/// Strategy running the conditions of a `RuleStrategyConfig`
+///
+/// Every bar, all conditions are evaluated (so crossings see each bar). Flat, it enters long
+/// or short on the matching entry; in a position, the opposite entry reverses it and the
+/// side's exit closes it.
+pub struct RuleStrategy {
+    config: RuleStrategyConfig,
+    params: HashMap<String, f64>,
+    rules: RuleSet,
+}
+
+impl RuleStrategy {
+    pub fn new(config: RuleStrategyConfig, params: &HashMap<String, f64>) -> Result<Self, String> {
+        let rules = config.compile(params)?;
+        Ok(Self { config, params: params.clone(), rules })
+    }
+}
+
+impl Strategy for RuleStrategy {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn prepare(&mut self, _data: &MarketData) {
+        // Already compiled once, so this cannot fail
+        if let Ok(rules) = self.config.compile(&self.params) {
+            self.rules = rules;
+        }
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let candle = &data.candles[index];
+        let rules = &mut self.rules;
+        for series in rules.series.iter_mut() {
+            series.values = (series.indicator)(candle);
+        }
+
+        let bar = Bar { candle, series: &rules.series };
+        let check = |rule: &mut Option<Condition>| rule.as_mut().is_some_and(|c| c.eval(&bar));
+        let (entry_long, entry_short) = (check(&mut rules.entry_long), check(&mut rules.entry_short));
+        let (exit_long, exit_short) = (check(&mut rules.exit_long), check(&mut rules.exit_short));
+
+        let target = match strategies::current_direction(portfolio, &data.symbol) {
+            Some(TradeDirection::Long) if entry_short => Some(TradeDirection::Short),
+            Some(TradeDirection::Long) if exit_long => None,
+            Some(TradeDirection::Short) if entry_long => Some(TradeDirection::Long),
+            Some(TradeDirection::Short) if exit_short => None,
+            Some(direction) => Some(direction),
+            None if entry_long => Some(TradeDirection::Long),
+            None if entry_short => Some(TradeDirection::Short),
+            None => None,
+        };
+
+        let size = self.config.size.unwrap_or(strategies::DEFAULT_ORDER_SIZE);
+        strategies::rebalance_to(portfolio, &data.symbol, target, size)
+    }
+}
+
+// Load the rule file at `path` as a strategy, overriding its params with `params`
+pub fn load_rule_strategy(path: &Path, params: &HashMap<String, f64>) -> Result<RuleStrategy, Box<dyn Error>> {
+    let config = RuleStrategyConfig::from_file(path)?;
+    Ok(RuleStrategy::new(config, params).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+// Every line of an indicator written as in a rule, e.g. `ema(50)` or `bollinger(20, 2)`,
+// evaluated on each candle and named like `bollinger(20, 2).upper`
+pub fn indicator_lines(call: &str, candles: &[Candle]) -> Result<Vec<NamedLine>, String> {
+    let tokens = tokenize(call)?;
+    let Some((Token::Ident(name), rest)) = tokens.split_first() else {
+        return Err(format!("expected an indicator call such as ema(50), got '{}'", call));
+    };
+
+    let mut args = Vec::new();
+    match rest {
+        [] => {}
+        [Token::Symbol("("), inner @ .., Token::Symbol(")")] => {
+            for (i, token) in inner.iter().enumerate() {
+                match (i % 2, token) {
+                    (0, Token::Number(value)) => args.push(*value),
+                    (1, Token::Symbol(",")) if i + 1 < inner.len() => {}
+                    _ => return Err(format!("expected numbers separated by commas in '{}'", call)),
+                }
+            }
+        }
+        _ => return Err(format!("expected an indicator call such as ema(50), got '{}'", call)),
+    }
+
+    let (indicator, lines) = build_indicator(name, &args)?;
+    let key = if args.is_empty() {
+        name.clone()
+    } else {
+        format!("{}({})", name, args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "))
+    };
+    let values: Vec<Option<Vec<f64>>> = candles.iter().map(indicator).collect();
+
+    Ok(lines.iter().enumerate()
+        .map(|(line, line_name)| {
+            let label = if lines.len() == 1 { key.clone() } else { format!("{}.{}", key, line_name) };
+            (label, values.iter().map(|v| v.as_ref().map(|v| v[line])).collect())
+        })
+        .collect())
+}
+
+// Compiled conditions sharing one set of indicators
+struct RuleSet {
+    series: Vec<Series>,
+    entry_long: Option<Condition>,
+    entry_short: Option<Condition>,
+    exit_long: Option<Condition>,
+    exit_short: Option<Condition>,
+}
+
+type LineFn = Box<dyn FnMut(&Candle) -> Option<Vec<f64>>>;
+type NamedLine = (String, Vec<Option<f64>>);
+
+// One indicator referenced by the rules, with its output flattened into named lines
+struct Series {
+    key: String, // Canonical call, e.g. "ema(12)", so repeated references share the indicator
+    lines: &'static [&'static str],
+    indicator: LineFn,
+    values: Option<Vec<f64>>,
+}
+
+struct Bar<'a> {
+    candle: &'a Candle,
+    series: &'a [Series],
+}
+
+fn lines<I: Indicator + 'static>(mut indicator: I, flatten: fn(I::Output) -> Vec<f64>) -> LineFn {
+    Box::new(move |candle| indicator.update(candle).map(flatten))
+}
+
+fn single<I: Indicator<Output = f64> + 'static>(indicator: I) -> LineFn {
+    lines(indicator, |v| vec![v])
+}
+
+// Indicator called `name` with `args`, missing trailing arguments taking their defaults
+fn build_indicator(name: &str, args: &[f64]) -> Result<(LineFn, &'static [&'static str]), String> {
+    const VALUE: &[&str] = &["value"];
+    const BANDS: &[&str] = &["middle", "upper", "lower"];
+
+    let defaults: &[f64] = match name {
+        "sma" | "ema" | "wma" | "rsi" | "atr" | "donchian" | "adx" => &[14.0],
+        "kama" => &[10.0, 2.0, 30.0],
+        "bollinger" | "keltner" => &[20.0, 2.0],
+        "macd" => &[12.0, 26.0, 9.0],
+        "stochastic" => &[14.0, 3.0],
+        "ichimoku" => &[9.0, 26.0, 52.0, 26.0],
+        "supertrend" => &[10.0, 3.0],
+        "obv" | "vwap" => &[],
+        _ => return Err(format!("unknown indicator '{}'", name)),
+    };
+    if args.len() > defaults.len() {
+        return Err(format!("{} takes at most {} argument(s), got {}", name, defaults.len(), args.len()));
+    }
+    let arg = |i: usize| args.get(i).copied().unwrap_or(defaults[i]);
+    let period = |i: usize| {
+        let value = arg(i);
+        if value >= 1.0 && value.fract() == 0.0 {
+            Ok(value as usize)
+        } else {
+            Err(format!("{} needs whole periods of at least 1, got {}", name, value))
+        }
+    };
+
+    Ok(match name {
+        "sma" => (single(Sma::new(period(0)?)), VALUE),
+        "ema" => (single(Ema::new(period(0)?)), VALUE),
+        "wma" => (single(Wma::new(period(0)?)), VALUE),
+        "rsi" => (single(Rsi::new(period(0)?)), VALUE),
+        "atr" => (single(Atr::new(period(0)?)), VALUE),
+        "kama" => (single(Kama::new(period(0)?, period(1)?, period(2)?)), VALUE),
+        "obv" => (single(Obv::new()), VALUE),
+        "vwap" => (single(Vwap::new()), VALUE),
+        "bollinger" => (lines(Bollinger::new(period(0)?, arg(1)), |b| vec![b.middle, b.upper, b.lower]), BANDS),
+        "keltner" => (lines(Keltner::new(period(0)?, period(0)?, arg(1)), |b| vec![b.middle, b.upper, b.lower]), BANDS),
+        "donchian" => (lines(Donchian::new(period(0)?), |b| vec![b.middle, b.upper, b.lower]), BANDS),
+        "macd" => (
+            lines(Macd::new(period(0)?, period(1)?, period(2)?), |m| vec![m.macd, m.signal, m.histogram]),
+            &["macd", "signal", "histogram"],
+        ),
+        "stochastic" => (lines(Stochastic::new(period(0)?, period(1)?), |s| vec![s.k, s.d]), &["k", "d"]),
+        "adx" => (lines(Adx::new(period(0)?), |a| vec![a.adx, a.plus_di, a.minus_di]), &["adx", "plus_di", "minus_di"]),
+        "ichimoku" => (
+            lines(Ichimoku::new(period(0)?, period(1)?, period(2)?, period(3)?), |i| vec![i.tenkan, i.kijun, i.senkou_a, i.senkou_b]),
+            &["tenkan", "kijun", "senkou_a", "senkou_b"],
+        ),
+        "supertrend" => (
+            lines(SuperTrend::new(period(0)?, arg(1)), |s| vec![s.value, if s.uptrend { 1.0 } else { 0.0 }]),
+            &["value", "uptrend"],
+        ),
+        _ => unreachable!(),
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Price {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+// Numeric expression evaluated on the current bar; None while an indicator warms up
+enum Value {
+    Number(f64),
+    Price(Price),
+    Line { series: usize, line: usize },
+    Negate(Box<Value>),
+    Arithmetic { op: char, left: Box<Value>, right: Box<Value> },
+}
+
+impl Value {
+    fn eval(&self, bar: &Bar) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            Value::Price(price) => Some(match price {
+                Price::Open => bar.candle.open,
+                Price::High => bar.candle.high,
+                Price::Low => bar.candle.low,
+                Price::Close => bar.candle.close,
+                Price::Volume => bar.candle.volume,
+            }),
+            Value::Line { series, line } => bar.series[*series].values.as_ref().map(|v| v[*line]),
+            Value::Negate(value) => value.eval(bar).map(|v| -v),
+            Value::Arithmetic { op, left, right } => {
+                let (left, right) = (left.eval(bar)?, right.eval(bar)?);
+                match op {
+                    '+' => Some(left + right),
+                    '-' => Some(left - right),
+                    '*' => Some(left * right),
+                    _ => (right != 0.0).then(|| left / right),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+enum Condition {
+    Compare { op: Comparison, left: Value, right: Value },
+    Cross { above: bool, left: Value, right: Value, prev: Option<(f64, f64)> },
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    // Both sides of `and` and `or` are always evaluated so every crossing tracks every bar
+    fn eval(&mut self, bar: &Bar) -> bool {
+        match self {
+            Condition::Compare { op, left, right } => {
+                let (Some(left), Some(right)) = (left.eval(bar), right.eval(bar)) else {
+                    return false;
+                };
+                match op {
+                    Comparison::Less => left < right,
+                    Comparison::LessEqual => left <= right,
+                    Comparison::Greater => left > right,
+                    Comparison::GreaterEqual => left >= right,
+                    Comparison::Equal => left == right,
+                    Comparison::NotEqual => left != right,
+                }
+            }
+            Condition::Cross { above, left, right, prev } => {
+                let current = left.eval(bar).zip(right.eval(bar));
+                let crossed = match (*prev, current) {
+                    (Some((prev_left, prev_right)), Some((left, right))) if *above => prev_left <= prev_right && left > right,
+                    (Some((prev_left, prev_right)), Some((left, right))) => prev_left >= prev_right && left < right,
+                    _ => false,
+                };
+                *prev = current;
+                crossed
+            }
+            Condition::And(left, right) => {
+                let (left, right) = (left.eval(bar), right.eval(bar));
+                left && right
+            }
+            Condition::Or(left, right) => {
+                let (left, right) = (left.eval(bar), right.eval(bar));
+                left || right
+            }
+            Condition::Not(condition) => !condition.eval(bar),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Param(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    const SYMBOLS: [&str; 14] = ["<=", ">=", "==", "!=", "<", ">", "(", ")", ",", ".", "+", "-", "*", "/"];
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        let word_len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+        let starts_number = rest.starts_with(|c: char| c.is_ascii_digit())
+            || (rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()));
+
+        let len = if starts_number {
+            let number = &rest[..word_len];
+            tokens.push(Token::Number(number.parse().map_err(|_| format!("invalid number '{}'", number))?));
+            word_len
+        } else if let Some(name) = rest.strip_prefix('$') {
+            let len = name.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(name.len());
+            if len == 0 {
+                return Err("expected a parameter name after '$'".to_string());
+            }
+            tokens.push(Token::Param(name[..len].to_string()));
+            len + 1
+        } else if rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_lowercase()));
+            len
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        } else {
+            return Err(format!("unexpected character '{}'", rest.chars().next().unwrap_or_default()));
+        };
+        rest = rest[len..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+// Params and indicators shared by all conditions of one strategy
+struct Registry {
+    params: HashMap<String, f64>,
+    series: Vec<Series>,
+}
+
+impl Registry {
+    fn series(&mut self, name: &str, args: &[f64]) -> Result<usize, String> {
+        let key = format!("{}({})", name, args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "));
+        if let Some(index) = self.series.iter().position(|s| s.key == key) {
+            return Ok(index);
+        }
+
+        let (indicator, lines) = build_indicator(name, args)?;
+        self.series.push(Series { key, lines, indicator, values: None });
+        Ok(self.series.len() - 1)
+    }
+}
+
+// Recursive-descent parser for conditions such as
+// `ema(12) crosses_above ema(26) and (rsi(14) < 70 or close > bollinger(20, 2).upper)`
+//
+//   condition  := conjunction ("or" conjunction)*
+//   conjunction := negation ("and" negation)*
+//   negation   := "not" negation | "(" condition ")" | comparison
+//   comparison := sum (("<" | "<=" | ">" | ">=" | "==" | "!=" | "crosses_above" | "crosses_below") sum)
+//   sum        := product (("+" | "-") product)*
+//   product    := unary (("*" | "/") unary)*
+//   unary      := "-" unary | number | $param | price | indicator ["(" args ")"] ["." line] | "(" sum ")"
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    registry: &'a mut Registry,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &str, registry: &'a mut Registry) -> Result<Self, String> {
+        Ok(Self { tokens: tokenize(text)?, pos: 0, registry })
+    }
+
+    fn parse(mut self) -> Result<Condition, String> {
+        let condition = self.condition()?;
+        match self.peek() {
+            None => Ok(condition),
+            Some(token) => Err(format!("unexpected {} after the condition", describe(token))),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Symbol(s)) if s == symbol => Ok(()),
+            Some(token) => Err(format!("expected '{}', found {}", symbol, describe(&token))),
+            None => Err(format!("expected '{}' at the end", symbol)),
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        self.eat(&Token::Ident(word.to_string()))
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let mut condition = self.conjunction()?;
+        while self.keyword("or") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.conjunction()?));
+        }
+        Ok(condition)
+    }
+
+    fn conjunction(&mut self) -> Result<Condition, String> {
+        let mut condition = self.negation()?;
+        while self.keyword("and") {
+            condition = Condition::And(Box::new(condition), Box::new(self.negation()?));
+        }
+        Ok(condition)
+    }
+
+    fn negation(&mut self) -> Result<Condition, String> {
+        if self.keyword("not") {
+            return Ok(Condition::Not(Box::new(self.negation()?)));
+        }
+
+        // A parenthesis opens either a nested condition or an arithmetic term like `(high - low)`
+        if self.peek() == Some(&Token::Symbol("(")) {
+            let start = self.pos;
+            self.pos += 1;
+            let series_count = self.registry.series.len();
+            if let Ok(condition) = self.condition()
+                && self.eat(&Token::Symbol(")")) {
+                return Ok(condition);
+            }
+            self.pos = start;
+            self.registry.series.truncate(series_count);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Condition, String> {
+        let left = self.sum()?;
+        let op = match self.next() {
+            Some(Token::Symbol("<")) => Comparison::Less,
+            Some(Token::Symbol("<=")) => Comparison::LessEqual,
+            Some(Token::Symbol(">")) => Comparison::Greater,
+            Some(Token::Symbol(">=")) => Comparison::GreaterEqual,
+            Some(Token::Symbol("==")) => Comparison::Equal,
+            Some(Token::Symbol("!=")) => Comparison::NotEqual,
+            Some(Token::Ident(word)) if word == "crosses_above" || word == "crosses_below" => {
+                let above = word == "crosses_above";
+                return Ok(Condition::Cross { above, left, right: self.sum()?, prev: None });
+            }
+            Some(token) => return Err(format!("expected a comparison or crossing, found {}", describe(&token))),
+            None => return Err("expected a comparison or crossing at the end".to_string()),
+        };
+        Ok(Condition::Compare { op, left, right: self.sum()? })
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut value = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => '+',
+                Some(Token::Symbol("-")) => '-',
+                _ => return Ok(value),
+            };
+            self.pos += 1;
+            value = Value::Arithmetic { op, left: Box::new(value), right: Box::new(self.product()?) };
+        }
+    }
+
+    fn product(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => '*',
+                Some(Token::Symbol("/")) => '/',
+                _ => return Ok(value),
+            };
+            self.pos += 1;
+            value = Value::Arithmetic { op, left: Box::new(value), right: Box::new(self.unary()?) };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Symbol("-")) => Ok(Value::Negate(Box::new(self.unary()?))),
+            Some(Token::Symbol("(")) => {
+                let value = self.sum()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some(Token::Number(value)) => Ok(Value::Number(value)),
+            Some(Token::Param(name)) => self.param(&name).map(Value::Number),
+            Some(Token::Ident(name)) => self.operand(&name),
+            Some(token) => Err(format!("expected a value, found {}", describe(&token))),
+            None => Err("expected a value at the end".to_string()),
+        }
+    }
+
+    fn param(&self, name: &str) -> Result<f64, String> {
+        self.registry.params.get(name).copied().ok_or_else(|| format!("unknown parameter '${}'", name))
+    }
+
+    // Price field or indicator call with an optional `.line`
+    fn operand(&mut self, name: &str) -> Result<Value, String> {
+        let price = match name {
+            "open" => Some(Price::Open),
+            "high" => Some(Price::High),
+            "low" => Some(Price::Low),
+            "close" => Some(Price::Close),
+            "volume" => Some(Price::Volume),
+            _ => None,
+        };
+        if let Some(price) = price {
+            return Ok(Value::Price(price));
+        }
+
+        let mut args = Vec::new();
+        if self.eat(&Token::Symbol("(")) && !self.eat(&Token::Symbol(")")) {
+            loop {
+                args.push(match self.next() {
+                    Some(Token::Number(value)) => value,
+                    Some(Token::Param(param)) => self.param(&param)?,
+                    Some(token) => return Err(format!("expected a number argument to {}, found {}", name, describe(&token))),
+                    None => return Err(format!("unclosed argument list of {}", name)),
+                });
+                if self.eat(&Token::Symbol(")")) {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+
+        let series = self.registry.series(name, &args)?;
+        let lines = self.registry.series[series].lines;
+        let line = if self.eat(&Token::Symbol(".")) {
+            match self.next() {
+                Some(Token::Ident(line)) => lines.iter().position(|l| *l == line).ok_or_else(|| {
+                    format!("{} has no line '{}', expected one of {}", name, line, lines.join(", "))
+                })?,
+                _ => return Err(format!("expected a line name after '{}.'", name)),
+            }
+        } else {
+            0 // The main line: the value, middle band, MACD line, %K, ADX or tenkan
+        };
+
+        Ok(Value::Line { series, line })
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {}", value),
+        Token::Ident(name) => format!("'{}'", name),
+        Token::Param(name) => format!("'${}'", name),
+        Token::Symbol(symbol) => format!("'{}'", symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::flat;
+
+    fn registry() -> Registry {
+        Registry { params: HashMap::from([("fast".to_string(), 2.0)]), series: Vec::new() }
+    }
+
+    fn parse(text: &str) -> Result<Condition, String> {
+        Parser::new(text, &mut registry()).and_then(Parser::parse)
+    }
+
+    fn parse_error(text: &str) -> String {
+        match parse(text) {
+            Ok(_) => panic!("'{}' should not parse", text),
+            Err(e) => e,
+        }
+    }
+
+    // Evaluate `text` on flat bars closing at each of `closes`
+    fn eval(text: &str, closes: &[f64]) -> Vec<bool> {
+        let mut registry = registry();
+        let mut condition = Parser::new(text, &mut registry).and_then(Parser::parse).unwrap();
+
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| {
+                let candle = flat(i as i64, close);
+                for series in registry.series.iter_mut() {
+                    series.values = (series.indicator)(&candle);
+                }
+                condition.eval(&Bar { candle: &candle, series: &registry.series })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(eval("close > 1 or close > 5 and close < 0", &[2.0]), vec![true]);
+        assert_eq!(eval("(close > 1 or close > 5) and close < 0", &[2.0]), vec![false]);
+        assert_eq!(eval("not close > 5 and close > 1", &[2.0, 6.0]), vec![true, false]);
+    }
+
+    #[test]
+    fn arithmetic_follows_the_usual_precedence() {
+        assert_eq!(eval("close == 1 + 2 * 3", &[7.0, 9.0]), vec![true, false]);
+        assert_eq!(eval("close == (1 + 2) * 3", &[7.0, 9.0]), vec![false, true]);
+        assert_eq!(eval("close == 8 - 4 - 2", &[2.0]), vec![true]);
+        assert_eq!(eval("close == 8 / 4 / 2", &[1.0]), vec![true]);
+        assert_eq!(eval("-close < -1", &[2.0, 0.5]), vec![true, false]);
+        assert_eq!(eval("(high - low) * 2 >= 0 and (close > 1)", &[2.0]), vec![true]);
+    }
+
+    #[test]
+    fn division_by_zero_is_false() {
+        assert_eq!(eval("close / 0 > 1", &[2.0]), vec![false]);
+    }
+
+    #[test]
+    fn crossings_fire_on_the_bar_that_crosses() {
+        let closes = [4.0, 6.0, 7.0, 5.0, 4.0, 6.0];
+        assert_eq!(eval("close crosses_above 5", &closes), vec![false, true, false, false, false, true]);
+        assert_eq!(eval("close crosses_below 5", &closes), vec![false, false, false, false, true, false]);
+    }
+
+    #[test]
+    fn indicators_are_false_while_warming_up() {
+        assert_eq!(eval("sma($fast) > 3", &[1.0, 5.0, 2.0]), vec![false, false, true]);
+        assert_eq!(eval("close crosses_above sma(2)", &[3.0, 1.0, 4.0]), vec![false, false, true]);
+    }
+
+    #[test]
+    fn repeated_indicator_calls_share_one_series() {
+        let mut registry = registry();
+        Parser::new("sma(2) > 1 and sma($fast) < 5 or sma(3) > 0", &mut registry).and_then(Parser::parse).unwrap();
+        assert_eq!(registry.series.len(), 2);
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert!(parse_error("foo(3) > 1").contains("unknown indicator 'foo'"));
+        assert!(parse_error("bollinger(20, 2).top > 1").contains("has no line 'top'"));
+        assert!(parse_error("sma($slow) > 1").contains("unknown parameter '$slow'"));
+        assert!(parse_error("sma(2, 3) > 1").contains("at most 1 argument"));
+        assert!(parse_error("sma(2.5) > 1").contains("whole periods"));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse_error("").contains("expected a value at the end"));
+        assert!(parse_error("close >").contains("expected a value at the end"));
+        assert!(parse_error("close 5").contains("expected a comparison or crossing"));
+        assert!(parse_error("close > 1)").contains("unexpected ')'"));
+        assert!(parse_error("close > 1 and").contains("at the end"));
+        assert!(parse_error("sma(2 > 1").contains("expected ','"));
+        assert!(parse_error("(close + 1 > 2").contains("expected ')'"));
+        assert!(parse_error("close # 1").contains("unexpected character '#'"));
+        assert!(parse_error("close > $").contains("parameter name"));
+    }
+
+    #[test]
+    fn example_strategy_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("configs/strategies/ema_rsi.toml");
+        let config = RuleStrategyConfig::from_file(&path).unwrap();
+
+        assert_eq!(config.name, "EMA_RSI_Rules");
+        assert!(config.compile(&HashMap::from([("fast".to_string(), 8.0)])).is_ok());
+        assert!(config.compile(&HashMap::from([("unknown".to_string(), 1.0)])).is_err());
+    }
+}
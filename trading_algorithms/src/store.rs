@@ -0,0 +1,507 @@
+use crate::models::{Candle, MarketData};
+use chrono::{DateTime, TimeZone, Utc};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"TABARS01";
+const EXTENSION: &str = "bars";
+
+/// On-disk store of bars, one file per symbol and timeframe
+///
+/// Each file records which source the bars came from (e.g. a CSV path plus its modification
+/// time) and which time ranges have already been fetched from it, so a later request only
+/// fetches the ranges that are missing. Bars are stored as fixed-width little-endian records:
+/// an i64 epoch-second timestamp followed by open, high, low, close and volume as f64.
+/// Each symbol gets its own directory, named by percent-escaping it (`BTC%2FUSD` for "BTC/USD").
+pub struct DataStore {
+    root: PathBuf,
+}
+
+/// Inclusive range of bar timestamps
+pub type DateRange = (DateTime<Utc>, DateTime<Utc>);
+
+/// Header and bars of one stored series
+#[derive(Debug, Clone)]
+pub struct StoredSeries {
+    pub source: String,
+    pub coverage: Vec<(i64, i64)>, // Inclusive epoch-second ranges already fetched, sorted and disjoint
+    pub candles: Vec<Candle>,
+}
+
+/// Summary of one stored series, for listings
+#[derive(Debug, Clone)]
+pub struct SeriesInfo {
+    pub symbol: String,
+    pub timeframe: String,
+    pub source: String,
+    pub coverage: Vec<DateRange>,
+    pub bars: u64,
+}
+
+// Everything in a series file before the bars
+struct SeriesHeader {
+    source: String,
+    coverage: Vec<(i64, i64)>,
+    bars: u64,
+}
+
+impl DataStore {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self { root: root.as_ref().to_path_buf() }
+    }
+
+    // Timeframe of the series for `symbol` that was filled from `source`, if any
+    pub fn find_timeframe(&self, symbol: &str, source: &str) -> Result<Option<String>, Box<dyn Error>> {
+        for (timeframe, path) in self.series_files(symbol)? {
+            let mut reader = BufReader::new(File::open(&path)?);
+            if read_header(&mut reader)?.source == source {
+                return Ok(Some(timeframe));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn read(&self, symbol: &str, timeframe: &str) -> Result<Option<StoredSeries>, Box<dyn Error>> {
+        let path = self.series_path(symbol, timeframe);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut reader = BufReader::new(File::open(&path)?);
+        let header = read_header(&mut reader)?;
+        let mut candles = Vec::with_capacity(header.bars as usize);
+        let mut record = [0u8; 48];
+        for _ in 0..header.bars {
+            reader.read_exact(&mut record)?;
+            candles.push(decode_candle(&record)?);
+        }
+
+        Ok(Some(StoredSeries { source: header.source, coverage: header.coverage, candles }))
+    }
+
+    pub fn write(&self, symbol: &str, timeframe: &str, series: &StoredSeries) -> Result<(), Box<dyn Error>> {
+        let path = self.series_path(symbol, timeframe);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write to a temporary file first so an interrupted write never corrupts the store
+        let tmp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(MAGIC)?;
+        write_bytes(&mut writer, series.source.as_bytes())?;
+        writer.write_all(&(series.coverage.len() as u32).to_le_bytes())?;
+        for (start, end) in &series.coverage {
+            writer.write_all(&start.to_le_bytes())?;
+            writer.write_all(&end.to_le_bytes())?;
+        }
+        writer.write_all(&(series.candles.len() as u64).to_le_bytes())?;
+        for candle in &series.candles {
+            writer.write_all(&encode_candle(candle))?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    // Merge bars fetched from `source` for start..=end into the stored series
+    //
+    // A series filled from a different source is replaced rather than merged.
+    pub fn insert(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        source: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        candles: Vec<Candle>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut series = match self.read(symbol, timeframe)? {
+            Some(series) if series.source == source => series,
+            _ => StoredSeries { source: source.to_string(), coverage: Vec::new(), candles: Vec::new() },
+        };
+
+        // New bars win over stored bars with the same timestamp
+        let mut merged = candles;
+        merged.extend(series.candles);
+        merged.sort_by_key(|c| c.timestamp);
+        merged.dedup_by_key(|c| c.timestamp);
+        series.candles = merged;
+
+        series.coverage.push((start.timestamp(), end.timestamp()));
+        series.coverage = merge_ranges(series.coverage);
+
+        self.write(symbol, timeframe, &series)
+    }
+
+    // Parts of start..=end not yet fetched from `source`
+    pub fn missing_ranges(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        source: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DateRange>, Box<dyn Error>> {
+        let coverage = match self.read_header(symbol, timeframe)? {
+            Some(header) if header.source == source => header.coverage,
+            _ => Vec::new(),
+        };
+
+        subtract_ranges((start.timestamp(), end.timestamp()), &coverage)
+            .into_iter()
+            .map(|(s, e)| Ok((to_datetime(s)?, to_datetime(e)?)))
+            .collect()
+    }
+
+    // Stored bars between start and end (inclusive)
+    pub fn query(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<MarketData, Box<dyn Error>> {
+        let candles = self.read(symbol, timeframe)?
+            .map(|series| series.candles)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|c| c.timestamp >= start && c.timestamp <= end)
+            .collect();
+
+        Ok(MarketData::new(symbol, timeframe, candles))
+    }
+
+    // Answer a range query, fetching only the ranges the store does not have yet
+    pub fn backfill<F>(
+        &self,
+        symbol: &str,
+        timeframe: &str,
+        source: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        mut fetch: F,
+    ) -> Result<MarketData, Box<dyn Error>>
+    where
+        F: FnMut(DateTime<Utc>, DateTime<Utc>) -> Result<Vec<Candle>, Box<dyn Error>>,
+    {
+        for (missing_start, missing_end) in self.missing_ranges(symbol, timeframe, source, start, end)? {
+            eprintln!("Backfilling {} {} from {} to {}", symbol, timeframe, missing_start, missing_end);
+            let candles = fetch(missing_start, missing_end)?;
+            self.insert(symbol, timeframe, source, missing_start, missing_end, candles)?;
+        }
+
+        self.query(symbol, timeframe, start, end)
+    }
+
+    // Every stored series
+    pub fn list(&self) -> Result<Vec<SeriesInfo>, Box<dyn Error>> {
+        let mut infos = Vec::new();
+        if !self.root.exists() {
+            return Ok(infos);
+        }
+
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let Some(symbol) = entry.file_name().to_str().and_then(unescape_symbol) else {
+                continue;
+            };
+
+            for (timeframe, path) in self.series_files(&symbol)? {
+                let mut reader = BufReader::new(File::open(&path)?);
+                let header = read_header(&mut reader)?;
+                infos.push(SeriesInfo {
+                    symbol: symbol.clone(),
+                    timeframe,
+                    source: header.source,
+                    coverage: header.coverage.iter()
+                        .map(|(s, e)| Ok((to_datetime(*s)?, to_datetime(*e)?)))
+                        .collect::<Result<_, Box<dyn Error>>>()?,
+                    bars: header.bars,
+                });
+            }
+        }
+
+        infos.sort_by(|a, b| (&a.symbol, &a.timeframe).cmp(&(&b.symbol, &b.timeframe)));
+        Ok(infos)
+    }
+
+    fn read_header(&self, symbol: &str, timeframe: &str) -> Result<Option<SeriesHeader>, Box<dyn Error>> {
+        let path = self.series_path(symbol, timeframe);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut reader = BufReader::new(File::open(&path)?);
+        Ok(Some(read_header(&mut reader)?))
+    }
+
+    fn symbol_dir(&self, symbol: &str) -> PathBuf {
+        self.root.join(escape_symbol(symbol))
+    }
+
+    fn series_path(&self, symbol: &str, timeframe: &str) -> PathBuf {
+        self.symbol_dir(symbol).join(format!("{}.{}", timeframe, EXTENSION))
+    }
+
+    // (timeframe, path) of every series stored for `symbol`
+    fn series_files(&self, symbol: &str) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+        let dir = self.symbol_dir(symbol);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            if let Some(timeframe) = path.file_stem().and_then(|s| s.to_str()) {
+                files.push((timeframe.to_string(), path.clone()));
+            }
+        }
+        Ok(files)
+    }
+}
+
+// Identify a data file by path, size and modification time so edits invalidate the store
+pub fn file_source(path: &Path) -> Result<String, Box<dyn Error>> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(format!("file:{}:{}:{}", fs::canonicalize(path)?.display(), metadata.len(), modified))
+}
+
+// Directory name for `symbol`: symbols like "BTC/USD" are not valid file names, so every byte
+// other than an ASCII letter, digit or '-' is written as %XX and distinct symbols never share
+// a directory
+fn escape_symbol(symbol: &str) -> String {
+    symbol.bytes()
+        .map(|b| if b.is_ascii_alphanumeric() || b == b'-' { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect()
+}
+
+// Symbol stored in directory `name`, or None for a name `escape_symbol` cannot produce
+fn unescape_symbol(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// Leaves the reader at the first bar
+fn read_header<R: Read>(reader: &mut R) -> Result<SeriesHeader, Box<dyn Error>> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err("Not a bar store file".into());
+    }
+
+    let source = String::from_utf8(read_bytes(reader)?)?;
+    let ranges = read_u32(reader)?;
+    let mut coverage = Vec::with_capacity(ranges as usize);
+    for _ in 0..ranges {
+        coverage.push((read_i64(reader)?, read_i64(reader)?));
+    }
+
+    let mut bars = [0u8; 8];
+    reader.read_exact(&mut bars)?;
+    Ok(SeriesHeader { source, coverage, bars: u64::from_le_bytes(bars) })
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = vec![0u8; read_u32(reader)? as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, Box<dyn Error>> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_i64<R: Read>(reader: &mut R) -> Result<i64, Box<dyn Error>> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(i64::from_le_bytes(buf))
+}
+
+fn encode_candle(candle: &Candle) -> [u8; 48] {
+    let mut record = [0u8; 48];
+    record[0..8].copy_from_slice(&candle.timestamp.timestamp().to_le_bytes());
+    for (k, value) in [candle.open, candle.high, candle.low, candle.close, candle.volume].iter().enumerate() {
+        record[8 + k * 8..16 + k * 8].copy_from_slice(&value.to_le_bytes());
+    }
+    record
+}
+
+fn decode_candle(record: &[u8; 48]) -> Result<Candle, Box<dyn Error>> {
+    let field = |k: usize| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&record[k * 8..(k + 1) * 8]);
+        buf
+    };
+
+    Ok(Candle {
+        timestamp: to_datetime(i64::from_le_bytes(field(0)))?,
+        open: f64::from_le_bytes(field(1)),
+        high: f64::from_le_bytes(field(2)),
+        low: f64::from_le_bytes(field(3)),
+        close: f64::from_le_bytes(field(4)),
+        volume: f64::from_le_bytes(field(5)),
+    })
+}
+
+fn to_datetime(seconds: i64) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    Utc.timestamp_opt(seconds, 0).single().ok_or_else(|| format!("Invalid timestamp {}", seconds).into())
+}
+
+// Sort and coalesce overlapping or adjacent inclusive ranges
+fn merge_ranges(mut ranges: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    ranges.sort();
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+// Parts of `range` not covered by the sorted, disjoint `coverage`
+fn subtract_ranges(range: (i64, i64), coverage: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let (mut start, end) = range;
+    let mut missing = Vec::new();
+
+    for &(covered_start, covered_end) in coverage {
+        if covered_end < start {
+            continue;
+        }
+        if covered_start > end {
+            break;
+        }
+        if covered_start > start {
+            missing.push((start, covered_start - 1));
+        }
+        start = start.max(covered_end + 1);
+    }
+
+    if start <= end {
+        missing.push((start, end));
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{day, flat, scratch_dir};
+    use chrono::Duration;
+    use std::cell::RefCell;
+
+    fn closes(data: &MarketData) -> Vec<f64> {
+        data.candles.iter().map(|c| c.close).collect()
+    }
+
+    #[test]
+    fn merge_ranges_coalesces_overlapping_and_adjacent() {
+        assert_eq!(merge_ranges(vec![(20, 30), (0, 5), (6, 10), (25, 40), (50, 60)]), vec![(0, 10), (20, 40), (50, 60)]);
+        assert_eq!(merge_ranges(vec![(0, 10), (2, 3)]), vec![(0, 10)]);
+        assert!(merge_ranges(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn subtract_ranges_leaves_the_gaps() {
+        let coverage = [(10, 20), (30, 40)];
+        assert_eq!(subtract_ranges((0, 50), &coverage), vec![(0, 9), (21, 29), (41, 50)]);
+        assert_eq!(subtract_ranges((12, 35), &coverage), vec![(21, 29)]);
+        assert!(subtract_ranges((10, 20), &coverage).is_empty());
+        assert_eq!(subtract_ranges((0, 5), &[]), vec![(0, 5)]);
+    }
+
+    #[test]
+    fn symbols_get_distinct_directories() {
+        let names: Vec<String> = ["BTC/USD", "BTC_USD", "BTC.USD", "BTC%2FUSD", "BTC-USD"].iter().map(|s| escape_symbol(s)).collect();
+        assert_eq!(names, vec!["BTC%2FUSD", "BTC%5FUSD", "BTC%2EUSD", "BTC%252FUSD", "BTC-USD"]);
+        for symbol in ["BTC/USD", "BTC_USD", "BTC%2FUSD", "€/USD"] {
+            assert_eq!(unescape_symbol(&escape_symbol(symbol)).as_deref(), Some(symbol));
+        }
+        assert_eq!(unescape_symbol("BTC%2"), None);
+    }
+
+    #[test]
+    fn series_round_trips_through_the_file() {
+        let store = DataStore::new(scratch_dir("store-round-trip"));
+        let series = StoredSeries {
+            source: "file:prices.csv".to_string(),
+            coverage: vec![(day(0).timestamp(), day(2).timestamp())],
+            candles: vec![flat(0, 10.0), Candle { volume: 2.5, ..flat(1, 11.5) }, flat(2, -1.0)],
+        };
+        store.write("BTC/USD", "1D", &series).unwrap();
+        store.write("BTC_USD", "1D", &StoredSeries { source: "other".to_string(), coverage: Vec::new(), candles: Vec::new() }).unwrap();
+
+        let read = store.read("BTC/USD", "1D").unwrap().unwrap();
+        assert_eq!(read.source, series.source);
+        assert_eq!(read.coverage, series.coverage);
+        assert_eq!(read.candles.len(), 3);
+        for (a, b) in read.candles.iter().zip(&series.candles) {
+            assert_eq!((a.timestamp, a.open, a.high, a.low, a.close, a.volume), (b.timestamp, b.open, b.high, b.low, b.close, b.volume));
+        }
+        assert!(store.read("BTC/USD", "1h").unwrap().is_none());
+
+        let listed: Vec<(String, u64)> = store.list().unwrap().into_iter().map(|info| (info.symbol, info.bars)).collect();
+        assert_eq!(listed, vec![("BTC/USD".to_string(), 3), ("BTC_USD".to_string(), 0)]);
+    }
+
+    #[test]
+    fn backfill_fetches_only_missing_ranges() {
+        let store = DataStore::new(scratch_dir("store-backfill"));
+        let fetched = RefCell::new(Vec::new());
+        let fetch = |from: DateTime<Utc>, to: DateTime<Utc>| {
+            fetched.borrow_mut().push((from, to));
+            Ok((0..10).map(|n| flat(n, n as f64)).filter(|c| c.timestamp >= from && c.timestamp <= to).collect())
+        };
+
+        let data = store.backfill("TEST", "1D", "src", day(3), day(5), fetch).unwrap();
+        assert_eq!(closes(&data), vec![3.0, 4.0, 5.0]);
+        let data = store.backfill("TEST", "1D", "src", day(1), day(7), fetch).unwrap();
+        assert_eq!(closes(&data), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        let data = store.backfill("TEST", "1D", "src", day(2), day(6), fetch).unwrap();
+        assert_eq!(closes(&data), vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        let second = Duration::seconds(1);
+        assert_eq!(*fetched.borrow(), vec![(day(3), day(5)), (day(1), day(3) - second), (day(5) + second, day(7))]);
+
+        // Bars from another source replace the series instead of counting as covered
+        store.backfill("TEST", "1D", "elsewhere", day(4), day(4), fetch).unwrap();
+        assert_eq!(fetched.borrow().last(), Some(&(day(4), day(4))));
+        assert_eq!(closes(&store.query("TEST", "1D", day(0), day(9)).unwrap()), vec![4.0]);
+    }
+}
@@ -1,9 +1,58 @@
-use crate::models::{Candle, MarketData, Trade, TradeDirection};
+use crate::analytics;
+use crate::data;
+use crate::indicators::{Bands, Bollinger, Donchian, Indicator, Macd, MacdOutput, Rsi, Sma};
+use crate::models::{MarketData, Order, TradeDirection};
+use crate::portfolio::Portfolio;
+use crate::resample::{MultiTimeframe, Session};
+use crate::registry::{self, Configurable, Descriptor, ParamSpec, Params, Registration};
+use crate::rules::{self, RuleStrategyConfig};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::path::Path;
 
+pub const DEFAULT_ORDER_SIZE: f64 = 1.0;
+
+// Strategy run when neither the command line nor the config names one
+pub const DEFAULT_STRATEGY: &str = "moving_average_crossover";
+
+/// Event-driven trading strategy
+///
+/// The backtester calls `on_candle` once per bar, in order, after `data.candles[index]` has
+/// closed. Returned orders are filled on the following bar. `prepare` is called once with
+/// the full series before the first bar, e.g. to build higher timeframes of it.
 pub trait Strategy {
     fn name(&self) -> &str;
-    fn execute(&self, data: &MarketData) -> Result<Vec<Trade>, Box<dyn Error>>;
+    fn prepare(&mut self, _data: &MarketData) {}
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order>;
+}
+
+// Direction of the open position in `symbol`, if any
+pub fn current_direction(portfolio: &Portfolio, symbol: &str) -> Option<TradeDirection> {
+    portfolio.position(symbol).map(|p| p.direction)
+}
+
+// Orders that move the book in `symbol` from its current position to `target`
+pub fn rebalance_to(portfolio: &Portfolio, symbol: &str, target: Option<TradeDirection>, size: f64) -> Vec<Order> {
+    let mut orders = Vec::new();
+    let position = portfolio.position(symbol);
+
+    if position.map(|p| p.direction) == target {
+        return orders;
+    }
+
+    // Close the existing position first
+    if let Some(position) = position {
+        orders.push(Order::market(symbol, position.direction.opposite(), position.size));
+    }
+
+    // Then open the new one
+    if let Some(direction) = target {
+        orders.push(Order::market(symbol, direction, size));
+    }
+
+    orders
 }
 
 // Moving Average Crossover Strategy
@@ -11,6 +60,9 @@ pub struct MovingAverageCrossover {
     pub name: String,
     pub fast_period: usize,
     pub slow_period: usize,
+    fast_ma: Sma,
+    slow_ma: Sma,
+    prev: Option<(f64, f64)>, // Fast and slow average on the previous bar
 }
 
 impl MovingAverageCrossover {
@@ -19,20 +71,24 @@ impl MovingAverageCrossover {
             name: format!("MA_{}_{}_Crossover", fast_period, slow_period),
             fast_period,
             slow_period,
+            fast_ma: Sma::new(fast_period),
+            slow_ma: Sma::new(slow_period),
+            prev: None,
         }
     }
+}
 
-    fn calculate_ma(&self, candles: &[Candle], period: usize, index: usize) -> Option<f64> {
-        if index < period - 1 || candles.len() <= index {
-            return None;
-        }
-
-        let sum: f64 = candles[index - period + 1..=index]
-            .iter()
-            .map(|candle| candle.close)
-            .sum();
+impl Configurable for MovingAverageCrossover {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("moving_average_crossover", "Reverses into the direction of a fast/slow SMA crossover", vec![
+            ParamSpec::integer("fast_period", 10, 1, 1000, "Bars in the fast moving average"),
+            ParamSpec::integer("slow_period", 30, 1, 1000, "Bars in the slow moving average"),
+        ])
+        .with_check(|p| registry::less_than(p, "fast_period", "slow_period"))
+    }
 
-        Some(sum / period as f64)
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("fast_period"), params.integer("slow_period"))
     }
 }
 
@@ -41,79 +97,34 @@ impl Strategy for MovingAverageCrossover {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
-        let candles = &data.candles;
-
-        if candles.len() < self.slow_period {
-            return Ok(trades); // Not enough data
-        }
-
-        let mut position: Option<TradeDirection> = None;
-
-        for i in self.slow_period..candles.len() {
-            let fast_ma = self.calculate_ma(candles, self.fast_period, i).unwrap();
-            let slow_ma = self.calculate_ma(candles, self.slow_period, i).unwrap();
-            let prev_fast_ma = self.calculate_ma(candles, self.fast_period, i - 1).unwrap();
-            let prev_slow_ma = self.calculate_ma(candles, self.slow_period, i - 1).unwrap();
-
-            // Detect crossing
-            let cross_above = prev_fast_ma <= prev_slow_ma && fast_ma > slow_ma;
-            let cross_below = prev_fast_ma >= prev_slow_ma && fast_ma < slow_ma;
-
-            // Generate trades based on crossovers
-            if cross_above && position != Some(TradeDirection::Long) {
-                // Close short position if exists
-                if position == Some(TradeDirection::Short) {
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: TradeDirection::Long, // Buy to close short
-                        price: candles[i].close,
-                        size: 1.0,
-                        costs: candles[i].close * 0.001, // 0.1% commission
-                    });
-                }
-
-                // Open long position
-                trades.push(Trade {
-                    timestamp: candles[i].timestamp,
-                    symbol: data.symbol.clone(),
-                    direction: TradeDirection::Long,
-                    price: candles[i].close,
-                    size: 1.0,
-                    costs: candles[i].close * 0.001, // 0.1% commission
-                });
-
-                position = Some(TradeDirection::Long);
-            } else if cross_below && position != Some(TradeDirection::Short) {
-                // Close long position if exists
-                if position == Some(TradeDirection::Long) {
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: TradeDirection::Short, // Sell to close long
-                        price: candles[i].close,
-                        size: 1.0,
-                        costs: candles[i].close * 0.001, // 0.1% commission
-                    });
-                }
-
-                // Open short position
-                trades.push(Trade {
-                    timestamp: candles[i].timestamp,
-                    symbol: data.symbol.clone(),
-                    direction: TradeDirection::Short,
-                    price: candles[i].close,
-                    size: 1.0,
-                    costs: candles[i].close * 0.001, // 0.1% commission
-                });
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.fast_period, self.slow_period);
+    }
 
-                position = Some(TradeDirection::Short);
-            }
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let candle = &data.candles[index];
+        let fast = self.fast_ma.update(candle);
+        let slow = self.slow_ma.update(candle);
+
+        let (Some(fast_ma), Some(slow_ma)) = (fast, slow) else {
+            return Vec::new(); // Not enough data
+        };
+        let Some((prev_fast_ma, prev_slow_ma)) = self.prev.replace((fast_ma, slow_ma)) else {
+            return Vec::new();
+        };
+
+        // Detect crossing
+        let cross_above = prev_fast_ma <= prev_slow_ma && fast_ma > slow_ma;
+        let cross_below = prev_fast_ma >= prev_slow_ma && fast_ma < slow_ma;
+
+        // Reverse into the direction of the crossover
+        if cross_above {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Long), DEFAULT_ORDER_SIZE)
+        } else if cross_below {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Short), DEFAULT_ORDER_SIZE)
+        } else {
+            Vec::new()
         }
-
-        Ok(trades)
     }
 }
 
@@ -123,6 +134,8 @@ pub struct RSIStrategy {
     pub period: usize,
     pub oversold_threshold: f64,
     pub overbought_threshold: f64,
+    rsi: Rsi,
+    prev_rsi: Option<f64>,
 }
 
 impl RSIStrategy {
@@ -132,261 +145,811 @@ impl RSIStrategy {
             period,
             oversold_threshold,
             overbought_threshold,
+            rsi: Rsi::new(period),
+            prev_rsi: None,
         }
     }
+}
 
-    fn calculate_rsi(&self, candles: &[Candle], index: usize) -> Option<f64> {
-        if index < self.period || candles.len() <= index {
-            return None;
-        }
+impl Configurable for RSIStrategy {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("rsi", "Goes long when RSI leaves oversold and short when it leaves overbought", vec![
+            ParamSpec::integer("period", 14, 1, 1000, "Bars in Wilder's RSI"),
+            ParamSpec::float("oversold_threshold", 30.0, 0.0, 100.0, "RSI level below which the market is oversold"),
+            ParamSpec::float("overbought_threshold", 70.0, 0.0, 100.0, "RSI level above which the market is overbought"),
+        ])
+    }
 
-        let mut gain_sum = 0.0;
-        let mut loss_sum = 0.0;
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("period"), params.float("oversold_threshold"), params.float("overbought_threshold"))
+    }
+}
 
-        // Calculate initial average gain and loss
-        for i in (index - self.period + 1)..=index {
-            let price_change = candles[i].close - candles[i - 1].close;
-            if price_change >= 0.0 {
-                gain_sum += price_change;
-            } else {
-                loss_sum -= price_change; // Make positive
-            }
+impl Strategy for RSIStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.period, self.oversold_threshold, self.overbought_threshold);
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let Some(rsi) = self.rsi.update(&data.candles[index]) else {
+            return Vec::new(); // Not enough data
+        };
+        let Some(prev_rsi) = self.prev_rsi.replace(rsi) else {
+            return Vec::new();
+        };
+
+        // Oversold -> Bullish
+        if prev_rsi <= self.oversold_threshold && rsi > self.oversold_threshold {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Long), DEFAULT_ORDER_SIZE)
+        }
+        // Overbought -> Bearish
+        else if prev_rsi >= self.overbought_threshold && rsi < self.overbought_threshold {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Short), DEFAULT_ORDER_SIZE)
+        } else {
+            Vec::new()
         }
+    }
+}
 
-        let avg_gain = gain_sum / self.period as f64;
-        let avg_loss = loss_sum / self.period as f64;
+// Mean Reversion Strategy
+pub struct MeanReversion {
+    pub name: String,
+    pub period: usize,
+    pub std_dev_multiplier: f64,
+    bands: Bollinger,
+}
 
-        if avg_loss == 0.0 {
-            return Some(100.0);
+impl MeanReversion {
+    pub fn new(period: usize, std_dev_multiplier: f64) -> Self {
+        Self {
+            name: format!("MeanReversion_{}_{}", period, std_dev_multiplier),
+            period,
+            std_dev_multiplier,
+            bands: Bollinger::new(period, std_dev_multiplier),
         }
+    }
+}
 
-        let rs = avg_gain / avg_loss;
-        let rsi = 100.0 - (100.0 / (1.0 + rs));
+impl Configurable for MeanReversion {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("mean_reversion", "Fades closes outside the Bollinger bands and exits back at the middle band", vec![
+            ParamSpec::integer("period", 20, 2, 1000, "Bars in the bands' moving average"),
+            ParamSpec::float("std_dev_multiplier", 2.0, 0.0, 10.0, "Band width in standard deviations"),
+        ])
+    }
 
-        Some(rsi)
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("period"), params.float("std_dev_multiplier"))
     }
 }
 
-impl Strategy for RSIStrategy {
+impl Strategy for MeanReversion {
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.period, self.std_dev_multiplier);
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
         let candles = &data.candles;
 
-        if candles.len() <= self.period {
-            return Ok(trades); // Not enough data
+        let Some(Bands { middle: sma, upper: upper_band, lower: lower_band }) = self.bands.update(&candles[index]) else {
+            return Vec::new(); // Not enough data
+        };
+
+        let close = candles[index].close;
+        let position = current_direction(portfolio, &data.symbol);
+
+        // Price is below lower band -> Buy
+        if close <= lower_band {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Long), DEFAULT_ORDER_SIZE)
         }
+        // Price is above upper band -> Sell
+        else if close >= upper_band {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Short), DEFAULT_ORDER_SIZE)
+        }
+        // Price returns to SMA -> Close position
+        else if (position == Some(TradeDirection::Long) && close >= sma) ||
+                (position == Some(TradeDirection::Short) && close <= sma) {
+            rebalance_to(portfolio, &data.symbol, None, DEFAULT_ORDER_SIZE)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+// Trend filter on a higher timeframe with a moving average crossover entry on the base bars
+pub struct MultiTimeframeTrend {
+    pub name: String,
+    pub trend_timeframe: String,
+    pub trend_period: usize,
+    pub fast_period: usize,
+    pub slow_period: usize,
+    trend: Option<MultiTimeframe>,
+    trend_ma: Sma,
+    trend_bars: usize, // Higher-timeframe bars fed to `trend_ma` so far
+    trend_direction: Option<TradeDirection>,
+    fast_ma: Sma,
+    slow_ma: Sma,
+}
+
+impl MultiTimeframeTrend {
+    pub fn new(trend_timeframe: &str, trend_period: usize, fast_period: usize, slow_period: usize) -> Self {
+        Self {
+            name: format!("MTF_{}_{}_MA_{}_{}", trend_timeframe, trend_period, fast_period, slow_period),
+            trend_timeframe: trend_timeframe.to_string(),
+            trend_period,
+            fast_period,
+            slow_period,
+            trend: None,
+            trend_ma: Sma::new(trend_period),
+            trend_bars: 0,
+            trend_direction: None,
+            fast_ma: Sma::new(fast_period),
+            slow_ma: Sma::new(slow_period),
+        }
+    }
+
+    // Long when the last closed higher-timeframe bar is above its average, short when below
+    fn update_trend(&mut self, index: usize) {
+        let Some(trend) = &self.trend else {
+            return;
+        };
+
+        for candle in &trend.completed_candles(index)[self.trend_bars..] {
+            self.trend_direction = match self.trend_ma.update(candle) {
+                Some(average) if candle.close > average => Some(TradeDirection::Long),
+                Some(average) if candle.close < average => Some(TradeDirection::Short),
+                _ => None,
+            };
+            self.trend_bars += 1;
+        }
+    }
+}
+
+impl Configurable for MultiTimeframeTrend {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("multi_timeframe_trend", "Moving average crossover on the base bars, filtered by a higher-timeframe trend", vec![
+            ParamSpec::integer("trend_hours", 24, 1, 720, "Length of the trend filter's bars in hours, a multiple of the base bar"),
+            ParamSpec::integer("trend_period", 20, 1, 1000, "Trend bars in the trend filter's moving average"),
+            ParamSpec::integer("fast_period", 5, 1, 1000, "Base bars in the fast moving average"),
+            ParamSpec::integer("slow_period", 20, 1, 1000, "Base bars in the slow moving average"),
+        ])
+        .with_check(|p| registry::less_than(p, "fast_period", "slow_period"))
+    }
+
+    fn from_params(params: &Params) -> Self {
+        let trend_timeframe = data::timeframe_label(params.integer("trend_hours") as i64 * 60 * 60);
+        Self::new(&trend_timeframe, params.integer("trend_period"), params.integer("fast_period"), params.integer("slow_period"))
+    }
+}
+
+impl Strategy for MultiTimeframeTrend {
+    fn name(&self) -> &str {
+        &self.name
+    }
 
-        let mut position: Option<TradeDirection> = None;
-
-        for i in self.period + 1..candles.len() {
-            if let Some(rsi) = self.calculate_rsi(candles, i) {
-                let prev_rsi = self.calculate_rsi(candles, i - 1).unwrap();
-
-                // Oversold -> Bullish
-                if prev_rsi <= self.oversold_threshold && rsi > self.oversold_threshold && position != Some(TradeDirection::Long) {
-                    // Close short position if exists
-                    if position == Some(TradeDirection::Short) {
-                        trades.push(Trade {
-                            timestamp: candles[i].timestamp,
-                            symbol: data.symbol.clone(),
-                            direction: TradeDirection::Long, // Buy to close short
-                            price: candles[i].close,
-                            size: 1.0,
-                            costs: candles[i].close * 0.001,
-                        });
-                    }
-
-                    // Open long position
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: TradeDirection::Long,
-                        price: candles[i].close,
-                        size: 1.0,
-                        costs: candles[i].close * 0.001,
-                    });
-
-                    position = Some(TradeDirection::Long);
-                }
-                // Overbought -> Bearish
-                else if prev_rsi >= self.overbought_threshold && rsi < self.overbought_threshold && position != Some(TradeDirection::Short) {
-                    // Close long position if exists
-                    if position == Some(TradeDirection::Long) {
-                        trades.push(Trade {
-                            timestamp: candles[i].timestamp,
-                            symbol: data.symbol.clone(),
-                            direction: TradeDirection::Short, // Sell to close long
-                            price: candles[i].close,
-                            size: 1.0,
-                            costs: candles[i].close * 0.001,
-                        });
-                    }
-
-                    // Open short position
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: TradeDirection::Short,
-                        price: candles[i].close,
-                        size: 1.0,
-                        costs: candles[i].close * 0.001,
-                    });
-
-                    position = Some(TradeDirection::Short);
-                }
+    fn prepare(&mut self, data: &MarketData) {
+        *self = Self::new(&self.trend_timeframe, self.trend_period, self.fast_period, self.slow_period);
+        self.trend = match MultiTimeframe::new(data, &self.trend_timeframe, Session::default()) {
+            Ok(trend) => Some(trend),
+            Err(e) => {
+                eprintln!("Warning: {} has no trend filter: {}", self.name, e);
+                None
             }
+        };
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let candle = &data.candles[index];
+        let fast = self.fast_ma.update(candle);
+        let slow = self.slow_ma.update(candle);
+        self.update_trend(index);
+
+        let (Some(fast_ma), Some(slow_ma)) = (fast, slow) else {
+            return Vec::new(); // Not enough data
+        };
+        let signal = if fast_ma > slow_ma { TradeDirection::Long } else { TradeDirection::Short };
+
+        // Only hold positions the higher timeframe agrees with
+        let target = self.trend_direction.filter(|trend| *trend == signal);
+        rebalance_to(portfolio, &data.symbol, target, DEFAULT_ORDER_SIZE)
+    }
+}
+
+// Donchian channel breakout: enter on a close beyond the previous `entry_period` bars' range,
+// exit on a close beyond the shorter `exit_period` channel on the other side
+pub struct DonchianBreakout {
+    pub name: String,
+    pub entry_period: usize,
+    pub exit_period: usize,
+    entry_channel: Donchian,
+    exit_channel: Donchian,
+    prev: Option<(Bands, Bands)>, // Entry and exit channel up to the previous bar
+}
+
+impl DonchianBreakout {
+    pub fn new(entry_period: usize, exit_period: usize) -> Self {
+        Self {
+            name: format!("Donchian_{}_{}", entry_period, exit_period),
+            entry_period,
+            exit_period,
+            entry_channel: Donchian::new(entry_period),
+            exit_channel: Donchian::new(exit_period),
+            prev: None,
         }
+    }
+}
+
+impl Configurable for DonchianBreakout {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("donchian_breakout", "Follows closes that break out of the Donchian channel, exits on the shorter opposite channel", vec![
+            ParamSpec::integer("entry_period", 20, 1, 1000, "Bars in the breakout channel"),
+            ParamSpec::integer("exit_period", 10, 1, 1000, "Bars in the exit channel"),
+        ])
+    }
 
-        Ok(trades)
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("entry_period"), params.integer("exit_period"))
     }
 }
 
-// Mean Reversion Strategy
-pub struct MeanReversion {
+impl Strategy for DonchianBreakout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.entry_period, self.exit_period);
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let candle = &data.candles[index];
+        let entry = self.entry_channel.update(candle);
+        let exit = self.exit_channel.update(candle);
+
+        // Compare against the channels before this bar, which cannot contain its own close
+        let prev = match (entry, exit) {
+            (Some(entry), Some(exit)) => self.prev.replace((entry, exit)),
+            _ => None,
+        };
+        let Some((entry, exit)) = prev else {
+            return Vec::new(); // Not enough data
+        };
+
+        let close = candle.close;
+        let target = match current_direction(portfolio, &data.symbol) {
+            _ if close > entry.upper => Some(TradeDirection::Long),
+            _ if close < entry.lower => Some(TradeDirection::Short),
+            Some(TradeDirection::Long) if close < exit.lower => None,
+            Some(TradeDirection::Short) if close > exit.upper => None,
+            position => position,
+        };
+        rebalance_to(portfolio, &data.symbol, target, DEFAULT_ORDER_SIZE)
+    }
+}
+
+// Time-series momentum: hold the sign of the return over `lookback` bars. Each new position
+// is scaled by `target_volatility` over the realized volatility, capped at `max_leverage`.
+pub struct TimeSeriesMomentum {
     pub name: String,
-    pub period: usize,
-    pub std_dev_multiplier: f64,
+    pub lookback: usize,
+    pub volatility_lookback: usize,
+    pub target_volatility: f64,
+    pub max_leverage: f64,
+    closes: VecDeque<f64>,
 }
 
-impl MeanReversion {
-    pub fn new(period: usize, std_dev_multiplier: f64) -> Self {
+impl TimeSeriesMomentum {
+    pub fn new(lookback: usize, volatility_lookback: usize, target_volatility: f64, max_leverage: f64) -> Self {
         Self {
-            name: format!("MeanReversion_{}_{}", period, std_dev_multiplier),
-            period,
-            std_dev_multiplier,
+            name: format!("Momentum_{}_{}", lookback, target_volatility),
+            lookback,
+            volatility_lookback,
+            target_volatility,
+            max_leverage,
+            closes: VecDeque::with_capacity(lookback.max(volatility_lookback) + 1),
+        }
+    }
+
+    // Annualized standard deviation of the last `volatility_lookback` close-to-close returns
+    fn realized_volatility(&self, bars_per_year: f64) -> Option<f64> {
+        let n = self.volatility_lookback;
+        let recent: Vec<f64> = self.closes.iter().skip(self.closes.len() - n - 1).copied().collect();
+        let returns: Vec<f64> = recent.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+        let mean = returns.iter().sum::<f64>() / n as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let volatility = (variance * bars_per_year).sqrt();
+        (volatility > 0.0).then_some(volatility)
+    }
+}
+
+impl Configurable for TimeSeriesMomentum {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("momentum", "Holds the direction of the trailing return, with entries scaled to a volatility target", vec![
+            ParamSpec::integer("lookback", 60, 1, 1000, "Bars in the trailing return"),
+            ParamSpec::integer("volatility_lookback", 20, 2, 1000, "Bars in the realized volatility estimate"),
+            ParamSpec::float("target_volatility", 0.15, 0.01, 5.0, "Annualized volatility a one-unit order is scaled to"),
+            ParamSpec::float("max_leverage", 2.0, 0.1, 100.0, "Largest multiple of a one-unit order"),
+        ])
+    }
+
+    fn from_params(params: &Params) -> Self {
+        Self::new(
+            params.integer("lookback"),
+            params.integer("volatility_lookback"),
+            params.float("target_volatility"),
+            params.float("max_leverage"),
+        )
+    }
+}
+
+impl Strategy for TimeSeriesMomentum {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.lookback, self.volatility_lookback, self.target_volatility, self.max_leverage);
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let close = data.candles[index].close;
+        if self.closes.len() > self.lookback.max(self.volatility_lookback) {
+            self.closes.pop_front();
+        }
+        self.closes.push_back(close);
+        if self.closes.len() <= self.lookback.max(self.volatility_lookback) {
+            return Vec::new(); // Not enough data
+        }
+
+        let past = self.closes[self.closes.len() - 1 - self.lookback];
+        let target = match close.partial_cmp(&past) {
+            Some(Ordering::Greater) => Some(TradeDirection::Long),
+            Some(Ordering::Less) => Some(TradeDirection::Short),
+            _ => None,
+        };
+        if target == current_direction(portfolio, &data.symbol) {
+            return Vec::new();
+        }
+
+        // Size the new position when it is opened; it keeps that size until the signal flips
+        let bars_per_year = analytics::periods_per_year(data.candles[..=index].iter().map(|c| c.timestamp));
+        let Some(volatility) = self.realized_volatility(bars_per_year) else {
+            return Vec::new();
+        };
+        let size = DEFAULT_ORDER_SIZE * (self.target_volatility / volatility).min(self.max_leverage);
+        rebalance_to(portfolio, &data.symbol, target, size)
+    }
+}
+
+// MACD signal-line crossover: long when MACD crosses above its signal line, short below
+pub struct MacdCrossover {
+    pub name: String,
+    pub fast_period: usize,
+    pub slow_period: usize,
+    pub signal_period: usize,
+    macd: Macd,
+    prev_histogram: Option<f64>,
+}
+
+impl MacdCrossover {
+    pub fn new(fast_period: usize, slow_period: usize, signal_period: usize) -> Self {
+        Self {
+            name: format!("MACD_{}_{}_{}", fast_period, slow_period, signal_period),
+            fast_period,
+            slow_period,
+            signal_period,
+            macd: Macd::new(fast_period, slow_period, signal_period),
+            prev_histogram: None,
         }
     }
+}
+
+impl Configurable for MacdCrossover {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("macd", "Reverses into the direction of a MACD signal-line crossover", vec![
+            ParamSpec::integer("fast_period", 12, 1, 1000, "Bars in the fast EMA"),
+            ParamSpec::integer("slow_period", 26, 1, 1000, "Bars in the slow EMA"),
+            ParamSpec::integer("signal_period", 9, 1, 1000, "Bars in the signal line's EMA of MACD"),
+        ])
+        .with_check(|p| registry::less_than(p, "fast_period", "slow_period"))
+    }
 
-    fn calculate_bollinger_bands(&self, candles: &[Candle], index: usize) -> Option<(f64, f64, f64)> {
-        if index < self.period - 1 || candles.len() <= index {
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("fast_period"), params.integer("slow_period"), params.integer("signal_period"))
+    }
+}
+
+impl Strategy for MacdCrossover {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.fast_period, self.slow_period, self.signal_period);
+    }
+
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let Some(MacdOutput { histogram, .. }) = self.macd.update(&data.candles[index]) else {
+            return Vec::new(); // Not enough data
+        };
+        let Some(prev_histogram) = self.prev_histogram.replace(histogram) else {
+            return Vec::new();
+        };
+
+        // The histogram is MACD minus its signal line, so a crossover flips its sign
+        if prev_histogram <= 0.0 && histogram > 0.0 {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Long), DEFAULT_ORDER_SIZE)
+        } else if prev_histogram >= 0.0 && histogram < 0.0 {
+            rebalance_to(portfolio, &data.symbol, Some(TradeDirection::Short), DEFAULT_ORDER_SIZE)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+// Statistical-arbitrage pairs trading on the spread between `data` and its pair leg
+//
+// The hedge ratio is the slope of an OLS regression of the main symbol's closes on the pair's
+// over the last `lookback` bars (the first step of an Engle-Granger cointegration test), and
+// the spread is the regression residual. Its z-score against the residual standard deviation
+// opens a position in the spread beyond `entry_z`, one unit of the main symbol against the
+// hedge ratio in the pair, and closes it once the z-score is back within `exit_z`.
+pub struct PairsTrading {
+    pub name: String,
+    pub lookback: usize,
+    pub entry_z: f64,
+    pub exit_z: f64,
+    closes: VecDeque<(f64, f64)>, // Main and pair close
+}
+
+// Hedge ratio and z-score of the latest spread
+struct SpreadState {
+    hedge_ratio: f64,
+    z_score: f64,
+}
+
+impl PairsTrading {
+    pub fn new(lookback: usize, entry_z: f64, exit_z: f64) -> Self {
+        Self {
+            name: format!("Pairs_{}_{}_{}", lookback, entry_z, exit_z),
+            lookback,
+            entry_z,
+            exit_z,
+            closes: VecDeque::with_capacity(lookback + 1),
+        }
+    }
+
+    // Regress the main closes on the pair closes over the window
+    fn spread(&self) -> Option<SpreadState> {
+        let n = self.closes.len() as f64;
+        let mean_x = self.closes.iter().map(|(_, x)| x).sum::<f64>() / n;
+        let mean_y = self.closes.iter().map(|(y, _)| y).sum::<f64>() / n;
+        let (mut var_x, mut var_y, mut cov) = (0.0, 0.0, 0.0);
+        for (y, x) in &self.closes {
+            var_x += (x - mean_x).powi(2) / n;
+            var_y += (y - mean_y).powi(2) / n;
+            cov += (x - mean_x) * (y - mean_y) / n;
+        }
+        if var_x <= 0.0 {
             return None;
         }
 
-        // Calculate SMA
-        let sum: f64 = candles[index - self.period + 1..=index]
-            .iter()
-            .map(|candle| candle.close)
-            .sum();
-        let sma = sum / self.period as f64;
+        let hedge_ratio = cov / var_x;
+        let residual_sd = (var_y - hedge_ratio * cov).max(0.0).sqrt();
+        if residual_sd <= 0.0 {
+            return None;
+        }
 
-        // Calculate standard deviation
-        let variance: f64 = candles[index - self.period + 1..=index]
-            .iter()
-            .map(|candle| (candle.close - sma).powi(2))
-            .sum::<f64>() / self.period as f64;
-        let std_dev = variance.sqrt();
+        // Residuals of the fit average zero over the window
+        let (y, x) = self.closes.back()?;
+        let residual = (y - mean_y) - hedge_ratio * (x - mean_x);
+        Some(SpreadState { hedge_ratio, z_score: residual / residual_sd })
+    }
+}
 
-        // Calculate Bollinger Bands
-        let upper_band = sma + (std_dev * self.std_dev_multiplier);
-        let lower_band = sma - (std_dev * self.std_dev_multiplier);
+impl Configurable for PairsTrading {
+    fn descriptor() -> Descriptor {
+        Descriptor::new("pairs_trading", "Trades the z-score of the regression spread against a second symbol (needs --pair)", vec![
+            ParamSpec::integer("lookback", 60, 3, 10000, "Bars in the hedge ratio regression and spread statistics"),
+            ParamSpec::float("entry_z", 2.0, 0.0, 10.0, "Spread z-score beyond which a position is opened"),
+            ParamSpec::float("exit_z", 0.5, 0.0, 10.0, "Spread z-score within which the position is closed"),
+        ])
+    }
 
-        Some((sma, upper_band, lower_band))
+    fn from_params(params: &Params) -> Self {
+        Self::new(params.integer("lookback"), params.float("entry_z"), params.float("exit_z"))
     }
 }
 
-impl Strategy for MeanReversion {
+impl Strategy for PairsTrading {
     fn name(&self) -> &str {
         &self.name
     }
 
-    fn execute(&self, data: &MarketData) -> Result<Vec<Trade>, Box<dyn Error>> {
-        let mut trades = Vec::new();
-        let candles = &data.candles;
+    fn prepare(&mut self, _data: &MarketData) {
+        *self = Self::new(self.lookback, self.entry_z, self.exit_z);
+    }
 
-        if candles.len() < self.period {
-            return Ok(trades); // Not enough data
+    fn on_candle(&mut self, data: &MarketData, index: usize, portfolio: &Portfolio) -> Vec<Order> {
+        let Some(pair) = &data.pair else {
+            return Vec::new(); // Nothing to trade against
+        };
+
+        if self.closes.len() == self.lookback {
+            self.closes.pop_front();
+        }
+        self.closes.push_back((data.candles[index].close, pair.candles[index].close));
+        if self.closes.len() < self.lookback {
+            return Vec::new(); // Not enough data
         }
+        let Some(SpreadState { hedge_ratio, z_score }) = self.spread() else {
+            return Vec::new();
+        };
+
+        // The risk layer brackets each leg on its own, so a stop or take-profit can close one
+        // leg and leave the other open. The spread is only held while both legs are; otherwise
+        // the leg left over is flattened unless the spread is entered again.
+        let (main, hedged) = (current_direction(portfolio, &data.symbol), current_direction(portfolio, &pair.symbol));
+        let position = if hedged.is_some() { main } else { None };
+
+        // Long the spread when it is cheap, short it when it is rich
+        let target = match position {
+            _ if z_score >= self.entry_z => Some(TradeDirection::Short),
+            _ if z_score <= -self.entry_z => Some(TradeDirection::Long),
+            Some(TradeDirection::Long) if z_score < -self.exit_z => position,
+            Some(TradeDirection::Short) if z_score > self.exit_z => position,
+            _ => None,
+        };
+        if target == position && main.is_some() == hedged.is_some() {
+            return Vec::new();
+        }
+
+        // The pair leg offsets the main one: opposite side for a positive hedge ratio
+        let hedge = target.map(|direction| if hedge_ratio > 0.0 { direction.opposite() } else { direction });
+        let mut orders = rebalance_to(portfolio, &data.symbol, target, DEFAULT_ORDER_SIZE);
+        orders.extend(rebalance_to(portfolio, &pair.symbol, hedge, DEFAULT_ORDER_SIZE * hedge_ratio.abs()));
+        orders
+    }
+}
+
+/// Strategy named in a config file, with parameter overrides on top of its defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyConfig {
+    pub name: String,
+    #[serde(default)]
+    pub params: HashMap<String, f64>,
+}
+
+// Built-in strategy boxed as a trait object
+fn register<S: Strategy + Configurable + 'static>() -> Registration<dyn Strategy> {
+    Registration { descriptor: S::descriptor(), build: |params| Box::new(S::from_params(params)) }
+}
+
+fn registry() -> Vec<Registration<dyn Strategy>> {
+    vec![
+        register::<MovingAverageCrossover>(),
+        register::<RSIStrategy>(),
+        register::<MeanReversion>(),
+        register::<MultiTimeframeTrend>(),
+        register::<DonchianBreakout>(),
+        register::<TimeSeriesMomentum>(),
+        register::<MacdCrossover>(),
+        register::<PairsTrading>(),
+    ]
+}
+
+// Name, description and parameters of every built-in strategy
+pub fn available_strategies() -> Vec<Descriptor> {
+    registry().into_iter().map(|r| r.descriptor).collect()
+}
+
+// Whether the strategy trades the pair leg of its data, which must then be configured
+pub fn needs_pair(strategy_name: &str) -> bool {
+    PairsTrading::descriptor().matches(strategy_name)
+}
+
+// Schema of a built-in strategy or rule strategy file
+pub fn describe_strategy(strategy_name: &str) -> Result<Descriptor, Box<dyn Error>> {
+    if rules::is_rule_file(strategy_name) {
+        return Ok(RuleStrategyConfig::from_file(Path::new(strategy_name))?.descriptor());
+    }
+    Ok(registry::find(&registry(), "strategy", strategy_name)?.descriptor.clone())
+}
+
+// Factory to create strategies by name, overriding default parameters from `params`.
+// A name ending in .toml or .json is loaded as a rule strategy file.
+pub fn create_strategy_with_params(strategy_name: &str, params: &HashMap<String, f64>) -> Result<Box<dyn Strategy>, Box<dyn Error>> {
+    if rules::is_rule_file(strategy_name) {
+        return Ok(Box::new(rules::load_rule_strategy(Path::new(strategy_name), params)?));
+    }
+    Ok(registry::find(&registry(), "strategy", strategy_name)?.create(params)?)
+}
 
-        let mut position: Option<TradeDirection> = None;
-
-        for i in self.period..candles.len() {
-            if let Some((sma, upper_band, lower_band)) = self.calculate_bollinger_bands(candles, i) {
-                let close = candles[i].close;
-
-                // Price is below lower band -> Buy
-                if close <= lower_band && position != Some(TradeDirection::Long) {
-                    // Close short position if exists
-                    if position == Some(TradeDirection::Short) {
-                        trades.push(Trade {
-                            timestamp: candles[i].timestamp,
-                            symbol: data.symbol.clone(),
-                            direction: TradeDirection::Long, // Buy to close short
-                            price: close,
-                            size: 1.0,
-                            costs: close * 0.001,
-                        });
-                    }
-
-                    // Open long position
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: TradeDirection::Long,
-                        price: close,
-                        size: 1.0,
-                        costs: close * 0.001,
-                    });
-
-                    position = Some(TradeDirection::Long);
-                }
-                // Price is above upper band -> Sell
-                else if close >= upper_band && position != Some(TradeDirection::Short) {
-                    // Close long position if exists
-                    if position == Some(TradeDirection::Long) {
-                        trades.push(Trade {
-                            timestamp: candles[i].timestamp,
-                            symbol: data.symbol.clone(),
-                            direction: TradeDirection::Short, // Sell to close long
-                            price: close,
-                            size: 1.0,
-                            costs: close * 0.001,
-                        });
-                    }
-
-                    // Open short position
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: TradeDirection::Short,
-                        price: close,
-                        size: 1.0,
-                        costs: close * 0.001,
-                    });
-
-                    position = Some(TradeDirection::Short);
-                }
-                // Price returns to SMA -> Close position
-                else if (position == Some(TradeDirection::Long) && close >= sma) || 
-                        (position == Some(TradeDirection::Short) && close <= sma) {
-                    
-                    trades.push(Trade {
-                        timestamp: candles[i].timestamp,
-                        symbol: data.symbol.clone(),
-                        direction: if position == Some(TradeDirection::Long) { TradeDirection::Short } else { TradeDirection::Long },
-                        price: close,
-                        size: 1.0,
-                        costs: close * 0.001,
-                    });
-
-                    position = None;
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{bar, day, flat};
+    use crate::models::{Candle, Trade};
+
+    // (bar index, symbol, direction, size) of an order
+    type Placed = (usize, String, TradeDirection, f64);
+
+    fn hold(portfolio: &mut Portfolio, symbol: &str, direction: TradeDirection, size: f64) {
+        portfolio.apply_fill(&Trade { timestamp: day(0), symbol: symbol.to_string(), direction, price: 100.0, size, costs: 0.0 });
+    }
+
+    // Call the strategy on every bar and fill its orders at once, at the close of their symbol
+    fn run(strategy: &mut dyn Strategy, data: &MarketData, portfolio: &mut Portfolio) -> Vec<Placed> {
+        strategy.prepare(data);
+        let mut placed = Vec::new();
+        for index in 0..data.candles.len() {
+            for order in strategy.on_candle(data, index, portfolio) {
+                let series = if order.symbol == data.symbol { data } else { data.pair.as_deref().unwrap() };
+                portfolio.apply_fill(&Trade {
+                    timestamp: day(index as i64),
+                    symbol: order.symbol.clone(),
+                    direction: order.direction,
+                    price: series.candles[index].close,
+                    size: order.size,
+                    costs: 0.0,
+                });
+                placed.push((index, order.symbol, order.direction, order.size));
             }
         }
+        placed
+    }
 
-        Ok(trades)
+    fn placed(index: usize, symbol: &str, direction: TradeDirection, size: f64) -> Placed {
+        (index, symbol.to_string(), direction, size)
+    }
+
+    // Main leg closing at twice the pair plus 10 plus `residuals`, with the pair at 1, 2, 3, ...
+    fn pair_data(residuals: &[f64]) -> MarketData {
+        let main = residuals.iter().enumerate().map(|(i, e)| flat(i as i64, 2.0 * (i + 1) as f64 + 10.0 + e)).collect();
+        let pair = (0..residuals.len()).map(|i| flat(i as i64, (i + 1) as f64)).collect();
+        let mut data = MarketData::new("Y", "1D", main);
+        data.pair = Some(Box::new(MarketData::new("X", "1D", pair)));
+        data
+    }
+
+    // Residuals with zero mean that are uncorrelated with the pair over four bars, so the
+    // regression recovers the hedge ratio of 2 exactly
+    const RICH: [f64; 4] = [1.0, -1.0, -1.0, 1.0]; // Last residual one standard deviation up
+    const FAIR: [f64; 4] = [1.0, -2.0, 1.0, 0.0]; // Last residual zero
+
+    #[test]
+    fn pairs_regresses_the_hedge_ratio_and_z_score() {
+        let mut strategy = PairsTrading::new(4, 0.9, 0.5);
+        let data = pair_data(&RICH);
+        strategy.closes = data.candles.iter().zip(&data.pair.as_ref().unwrap().candles).map(|(y, x)| (y.close, x.close)).collect();
+
+        let SpreadState { hedge_ratio, z_score } = strategy.spread().unwrap();
+        assert!((hedge_ratio - 2.0).abs() < 1e-9, "hedge ratio {}", hedge_ratio);
+        assert!((z_score - 1.0).abs() < 1e-9, "z-score {}", z_score);
+    }
+
+    #[test]
+    fn pairs_enters_a_rich_spread_and_exits_once_it_reverts() {
+        let entered = run(&mut PairsTrading::new(4, 0.9, 0.5), &pair_data(&RICH), &mut Portfolio::new(10_000.0));
+        assert_eq!(entered, vec![placed(3, "Y", TradeDirection::Short, 1.0), placed(3, "X", TradeDirection::Long, 2.0)]);
+
+        let mut portfolio = Portfolio::new(10_000.0);
+        hold(&mut portfolio, "Y", TradeDirection::Short, 1.0);
+        hold(&mut portfolio, "X", TradeDirection::Long, 2.0);
+        let exited = run(&mut PairsTrading::new(4, 0.9, 0.5), &pair_data(&FAIR), &mut portfolio);
+        assert_eq!(exited, vec![placed(3, "Y", TradeDirection::Long, 1.0), placed(3, "X", TradeDirection::Short, 2.0)]);
+    }
+
+    #[test]
+    fn pairs_flattens_a_leg_left_open_by_its_bracket() {
+        // The main leg was stopped out; the hedge alone is no spread position
+        let mut portfolio = Portfolio::new(10_000.0);
+        hold(&mut portfolio, "X", TradeDirection::Long, 2.0);
+        let orders = run(&mut PairsTrading::new(4, 0.9, 0.5), &pair_data(&FAIR), &mut portfolio);
+        assert_eq!(orders, vec![placed(3, "X", TradeDirection::Short, 2.0)]);
+
+        // Unless the spread is still rich enough to enter, when the main leg is reopened
+        let mut portfolio = Portfolio::new(10_000.0);
+        hold(&mut portfolio, "X", TradeDirection::Long, 2.0);
+        let orders = run(&mut PairsTrading::new(4, 0.9, 0.5), &pair_data(&RICH), &mut portfolio);
+        assert_eq!(orders, vec![placed(3, "Y", TradeDirection::Short, 1.0)]);
+    }
+
+    #[test]
+    fn momentum_scales_entries_to_the_volatility_target() {
+        // Log returns of 1% and 3%: sample variance 2e-4 per bar over 365.25 bars a year
+        let closes = [100.0, 100.0 * 0.01f64.exp(), 100.0 * 0.04f64.exp()];
+        let data = MarketData::new("TEST", "1D", closes.iter().enumerate().map(|(i, &c)| flat(i as i64, c)).collect());
+        let volatility = (2e-4 * 365.25f64).sqrt();
+
+        let orders = run(&mut TimeSeriesMomentum::new(1, 2, 0.15, 2.0), &data, &mut Portfolio::new(10_000.0));
+        assert_eq!(orders.len(), 1);
+        assert_eq!((orders[0].0, orders[0].2), (2, TradeDirection::Long));
+        assert!((orders[0].3 - 0.15 / volatility).abs() < 1e-9, "size {}", orders[0].3);
+
+        // A target far above the realized volatility is capped at the leverage limit
+        let orders = run(&mut TimeSeriesMomentum::new(1, 2, 1.0, 2.0), &data, &mut Portfolio::new(10_000.0));
+        assert_eq!(orders[0].3, 2.0);
+    }
+
+    #[test]
+    fn donchian_enters_on_a_breakout_and_exits_on_the_shorter_channel() {
+        let candles: Vec<Candle> = [
+            (101.0, 99.0, 100.0),
+            (101.0, 99.0, 100.0),
+            (101.0, 99.0, 100.0),
+            (101.0, 99.0, 100.0),
+            (104.0, 100.0, 103.0), // Above the three-bar high of 101
+            (104.0, 102.0, 103.0),
+            (103.0, 101.0, 101.0),
+            (101.0, 100.0, 100.5), // Below the two-bar low of 101, above the three-bar low of 100
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, &(high, low, close))| bar(i as i64, close, high, low, close))
+        .collect();
+        let data = MarketData::new("TEST", "1D", candles);
+
+        let orders = run(&mut DonchianBreakout::new(3, 2), &data, &mut Portfolio::new(10_000.0));
+        assert_eq!(orders, vec![placed(4, "TEST", TradeDirection::Long, 1.0), placed(7, "TEST", TradeDirection::Short, 1.0)]);
     }
-}
 
-// Factory to create strategies by name
-pub fn create_strategy(strategy_name: &str) -> Box<dyn Strategy> {
-    match strategy_name {
-        "moving_average_crossover" => Box::new(MovingAverageCrossover::new(10, 30)),
-        "rsi" => Box::new(RSIStrategy::new(14, 30.0, 70.0)),
-        "mean_reversion" => Box::new(MeanReversion::new(20, 2.0)),
-        _ => Box::new(MovingAverageCrossover::new(10, 30)), // Default
+    #[test]
+    fn macd_reverses_when_the_histogram_changes_sign() {
+        // Down, up and down again
+        let closes: Vec<f64> = (0..90).map(|i| match i {
+            0..30 => 200.0 - i as f64,
+            30..60 => 140.0 + i as f64,
+            _ => 320.0 - 2.0 * i as f64,
+        }).collect();
+        let data = MarketData::new("TEST", "1D", closes.iter().enumerate().map(|(i, &c)| flat(i as i64, c)).collect());
+
+        let mut macd = Macd::new(3, 6, 2);
+        let histograms: Vec<Option<f64>> = data.candles.iter().map(|c| macd.update(c).map(|m| m.histogram)).collect();
+        let crossings: Vec<(usize, TradeDirection)> = histograms.windows(2).enumerate()
+            .filter_map(|(i, w)| match (w[0]?, w[1]?) {
+                (prev, now) if prev <= 0.0 && now > 0.0 => Some((i + 1, TradeDirection::Long)),
+                (prev, now) if prev >= 0.0 && now < 0.0 => Some((i + 1, TradeDirection::Short)),
+                _ => None,
+            })
+            .collect();
+        assert!(crossings.len() >= 2, "{:?}", crossings);
+
+        // The first crossing opens one unit; every later one closes it and opens the other side
+        let orders = run(&mut MacdCrossover::new(3, 6, 2), &data, &mut Portfolio::new(10_000.0));
+        let mut expected = vec![placed(crossings[0].0, "TEST", crossings[0].1, 1.0)];
+        for &(index, direction) in &crossings[1..] {
+            expected.extend([placed(index, "TEST", direction, 1.0), placed(index, "TEST", direction, 1.0)]);
+        }
+        assert_eq!(orders, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn multi_timeframe_trend_reads_its_timeframe_from_trend_hours() {
+        let timeframe = |hours: Option<f64>| {
+            let overrides: HashMap<String, f64> = hours.map(|h| ("trend_hours".to_string(), h)).into_iter().collect();
+            MultiTimeframeTrend::from_params(&MultiTimeframeTrend::descriptor().validate(&overrides).unwrap()).trend_timeframe
+        };
+
+        assert_eq!(timeframe(None), "1D");
+        assert_eq!(timeframe(Some(4.0)), "4h");
+        assert_eq!(timeframe(Some(168.0)), "1W");
+    }
+
+    #[test]
+    fn mean_reversion_trades_on_its_first_full_window() {
+        // Bands of 9 +- sqrt(2) over the first three closes, with the last below the lower band
+        let data = MarketData::new("TEST", "1D", vec![flat(0, 10.0), flat(1, 10.0), flat(2, 7.0)]);
+        let orders = run(&mut MeanReversion::new(3, 1.0), &data, &mut Portfolio::new(10_000.0));
+
+        assert_eq!(orders, vec![placed(2, "TEST", TradeDirection::Long, 1.0)]);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::data;
+use crate::models::{Candle, MarketData};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Poisson, StandardNormal};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
+/// Settings for the synthetic market data generator
+///
+/// The same seed, model, interval and symbol always produce the same bars. The symbol is
+/// mixed into the seed so that several symbols generated with one config get distinct paths.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyntheticConfig {
+    pub seed: u64,
+    pub model: PriceModel,
+    pub initial_price: f64,
+    pub bar_interval: String, // e.g. "1m", "5m", "1h", "1D"
+    pub base_volume: f64, // Average volume per day
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            model: PriceModel::default(),
+            initial_price: 100.0,
+            bar_interval: "1D".to_string(),
+            base_volume: 50000.0,
+        }
+    }
+}
+
+/// Stochastic process driving the close price
+///
+/// Drifts and volatilities are annualized; they are scaled to the bar interval internally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PriceModel {
+    // Geometric Brownian motion
+    Gbm { drift: f64, volatility: f64 },
+    // GARCH(1,1) volatility clustering around a long-run volatility
+    Garch { drift: f64, long_run_volatility: f64, alpha: f64, beta: f64 },
+    // Merton jump-diffusion: GBM plus Poisson jumps with normally distributed log size
+    JumpDiffusion { drift: f64, volatility: f64, jump_intensity: f64, jump_mean: f64, jump_std: f64 },
+    // Markov chain over GBM regimes, switching with a fixed probability per bar
+    RegimeSwitching { regimes: Vec<Regime>, switch_probability: f64 },
+}
+
+impl Default for PriceModel {
+    fn default() -> Self {
+        PriceModel::Gbm { drift: 0.05, volatility: 0.6 }
+    }
+}
+
+impl PriceModel {
+    // Default parameters for a model given by name
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "gbm" => Ok(PriceModel::default()),
+            "garch" => Ok(PriceModel::Garch { drift: 0.05, long_run_volatility: 0.6, alpha: 0.1, beta: 0.85 }),
+            "jump_diffusion" => Ok(PriceModel::JumpDiffusion {
+                drift: 0.05,
+                volatility: 0.5,
+                jump_intensity: 6.0,
+                jump_mean: -0.02,
+                jump_std: 0.06,
+            }),
+            "regime_switching" => Ok(PriceModel::RegimeSwitching {
+                regimes: vec![
+                    Regime { drift: 0.4, volatility: 0.4 },
+                    Regime { drift: -0.5, volatility: 0.9 },
+                ],
+                switch_probability: 0.02,
+            }),
+            _ => Err(format!("Unknown price model '{}' (expected gbm, garch, jump_diffusion or regime_switching)", name).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Regime {
+    pub drift: f64,
+    pub volatility: f64,
+}
+
+// Generate bars for `symbol` covering start_date..=end_date ("YYYY-MM-DD")
+pub fn generate(symbol: &str, start_date: &str, end_date: &str, config: &SyntheticConfig) -> Result<MarketData, Box<dyn Error>> {
+    let (start, end) = data::parse_date_range(start_date, end_date)?;
+    let bar_seconds = data::timeframe_seconds(&config.bar_interval)
+        .ok_or_else(|| format!("Invalid bar interval '{}'", config.bar_interval))?;
+
+    Ok(MarketData::new(symbol, &config.bar_interval, generate_candles(symbol, start, end, bar_seconds, config)))
+}
+
+fn generate_candles(symbol: &str, start: DateTime<Utc>, end: DateTime<Utc>, bar_seconds: i64, config: &SyntheticConfig) -> Vec<Candle> {
+    let mut rng = StdRng::seed_from_u64(config.seed ^ fnv1a(symbol));
+    let dt = bar_seconds as f64 / SECONDS_PER_YEAR;
+    let bars_per_day = (86400.0 / bar_seconds as f64).max(1.0);
+
+    let mut candles = Vec::new();
+    let mut price = config.initial_price;
+    let mut timestamp = start;
+
+    // Model state
+    let mut variance = match &config.model {
+        PriceModel::Garch { long_run_volatility, .. } => long_run_volatility.powi(2) * dt,
+        _ => 0.0,
+    };
+    let mut last_shock: f64 = 0.0;
+    let mut regime = 0;
+
+    while timestamp <= end {
+        let z: f64 = rng.sample(StandardNormal);
+
+        // Log return of the bar and the per-bar volatility used to shape its range
+        let (log_return, bar_volatility) = match &config.model {
+            PriceModel::Gbm { drift, volatility } => {
+                let sigma = volatility * dt.sqrt();
+                ((drift - 0.5 * volatility.powi(2)) * dt + sigma * z, sigma)
+            }
+            PriceModel::Garch { drift, long_run_volatility, alpha, beta } => {
+                let omega = long_run_volatility.powi(2) * dt * (1.0 - alpha - beta).max(0.0);
+                variance = omega + alpha * last_shock.powi(2) + beta * variance;
+                let sigma = variance.sqrt();
+                last_shock = sigma * z;
+                (drift * dt - 0.5 * variance + last_shock, sigma)
+            }
+            PriceModel::JumpDiffusion { drift, volatility, jump_intensity, jump_mean, jump_std } => {
+                let sigma = volatility * dt.sqrt();
+                let jumps = Poisson::new(jump_intensity * dt)
+                    .map(|p| p.sample(&mut rng) as u64)
+                    .unwrap_or(0);
+                let jump: f64 = (0..jumps)
+                    .map(|_| jump_mean + jump_std * rng.sample::<f64, _>(StandardNormal))
+                    .sum();
+                ((drift - 0.5 * volatility.powi(2)) * dt + sigma * z + jump, sigma)
+            }
+            PriceModel::RegimeSwitching { regimes, switch_probability } => {
+                if regimes.len() > 1 && rng.gen_bool(switch_probability.clamp(0.0, 1.0)) {
+                    regime = (regime + rng.gen_range(1..regimes.len())) % regimes.len();
+                }
+                match regimes.get(regime) {
+                    Some(r) => {
+                        let sigma = r.volatility * dt.sqrt();
+                        ((r.drift - 0.5 * r.volatility.powi(2)) * dt + sigma * z, sigma)
+                    }
+                    None => (0.0, 0.0),
+                }
+            }
+        };
+
+        let open = price;
+        let close = open * log_return.exp();
+
+        // Extend the range beyond the open/close by a fraction of the bar's volatility
+        let up: f64 = rng.sample::<f64, _>(StandardNormal).abs() * bar_volatility * 0.5;
+        let down: f64 = rng.sample::<f64, _>(StandardNormal).abs() * bar_volatility * 0.5;
+        let high = open.max(close) * up.exp();
+        let low = open.min(close) * (-down).exp();
+
+        // Volume rises with the size of the move and follows a U-shaped intraday profile
+        let move_factor = if bar_volatility > 0.0 { 1.0 + 0.5 * (log_return.abs() / bar_volatility) } else { 1.0 };
+        let noise = (0.3 * rng.sample::<f64, _>(StandardNormal)).exp();
+        let volume = config.base_volume / bars_per_day * move_factor * noise * intraday_volume_factor(timestamp, bar_seconds);
+
+        candles.push(Candle {
+            timestamp,
+            open,
+            high,
+            low,
+            close,
+            volume,
+        });
+
+        price = close;
+        timestamp += Duration::seconds(bar_seconds);
+    }
+
+    candles
+}
+
+// Heavier volume near the start and end of the day for intraday bars
+fn intraday_volume_factor(timestamp: DateTime<Utc>, bar_seconds: i64) -> f64 {
+    if bar_seconds >= 86400 {
+        return 1.0;
+    }
+
+    let day_fraction = timestamp.num_seconds_from_midnight() as f64 / 86400.0;
+    let distance_from_midday = (day_fraction - 0.5) * 2.0;
+    0.6 + 1.2 * distance_from_midday.powi(2)
+}
+
+// FNV-1a hash, stable across platforms and Rust versions
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELS: [&str; 4] = ["gbm", "garch", "jump_diffusion", "regime_switching"];
+
+    fn generate_with(model: &str, seed: u64) -> Vec<Candle> {
+        let config = SyntheticConfig {
+            seed,
+            model: PriceModel::from_name(model).unwrap(),
+            bar_interval: "1h".to_string(),
+            ..SyntheticConfig::default()
+        };
+        generate("BTC/USD", "2023-01-01", "2023-03-31", &config).unwrap().candles
+    }
+
+    fn bars(candles: &[Candle]) -> Vec<(i64, f64, f64, f64, f64, f64)> {
+        candles.iter().map(|c| (c.timestamp.timestamp(), c.open, c.high, c.low, c.close, c.volume)).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_identical_bars() {
+        for model in MODELS {
+            assert_eq!(bars(&generate_with(model, 7)), bars(&generate_with(model, 7)), "{}", model);
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_bars() {
+        for model in MODELS {
+            let (a, b) = (generate_with(model, 7), generate_with(model, 8));
+            assert_eq!(a.len(), b.len());
+            assert_ne!(bars(&a), bars(&b), "{}", model);
+        }
+    }
+
+    #[test]
+    fn symbols_get_distinct_paths_from_one_seed() {
+        let config = SyntheticConfig::default();
+        let a = generate("BTC/USD", "2023-01-01", "2023-03-31", &config).unwrap().candles;
+        let b = generate("ETH/USD", "2023-01-01", "2023-03-31", &config).unwrap().candles;
+        assert_ne!(bars(&a), bars(&b));
+    }
+
+    #[test]
+    fn bars_are_consistent() {
+        for model in MODELS {
+            let candles = generate_with(model, 42);
+            assert_eq!(candles.len(), 90 * 24, "{}", model);
+            for (i, c) in candles.iter().enumerate() {
+                assert!(c.low <= c.open.min(c.close) && c.open.max(c.close) <= c.high, "{} bar {}: {:?}", model, i, c);
+                assert!(c.low > 0.0 && c.volume > 0.0, "{} bar {}: {:?}", model, i, c);
+                if i > 0 {
+                    assert_eq!(c.open, candles[i - 1].close, "{} bar {}", model, i);
+                    assert_eq!((c.timestamp - candles[i - 1].timestamp).num_seconds(), 3600);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use crate::config::BacktestConfig;
+use crate::execution;
+use crate::models::{Candle, MarketData, Trade, TradeDirection};
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use statrs::distribution::{ContinuousCDF, StudentsT};
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::Range;
+
+const BPS: f64 = 10_000.0;
+
+/// How the randomized parent orders of a TCA run are drawn
+///
+/// Each order trades one window of the data, picked at random: a calendar day on intraday
+/// bars, otherwise `window_bars` consecutive bars. Its size is a random fraction of the
+/// window's volume between `min_participation` and `max_participation`, and its side is
+/// a coin flip.
+#[derive(Debug, Clone)]
+pub struct TcaConfig {
+    pub orders: usize,
+    pub window_bars: usize,
+    pub min_participation: f64,
+    pub max_participation: f64,
+    pub seed: u64,
+}
+
+/// A parent order every algorithm is given to work
+#[derive(Debug, Clone, Serialize)]
+pub struct ParentOrder {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub direction: TradeDirection,
+    pub size: f64,
+    #[serde(skip)]
+    bars: Range<usize>,
+}
+
+/// Transaction costs of one algorithm working one parent order
+///
+/// Costs are in basis points, signed so that positive is worse for the trader: paying more
+/// than the benchmark on a buy or receiving less on a sell.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionCosts {
+    pub fill_rate: f64, // Fraction of the parent order filled by the end of the window
+    pub average_price: f64,
+    pub implementation_shortfall: f64, // Fills, fees and the unfilled rest at the close, against arrival
+    pub vs_arrival: f64, // Average price against the window's first open
+    pub vs_vwap: f64, // Average price against the window's volume-weighted typical price
+    pub vs_twap: f64, // Average price against the window's mean typical price
+    pub vs_close: f64, // Average price against the window's last close
+    pub timing_cost: f64, // Market move from arrival to the opens of the bars that filled
+    pub participation_rate: f64, // Filled size over market volume from the first to the last fill
+    pub schedule_deviation: f64, // Mean gap between the filled fraction and the window's volume curve
+}
+
+/// Mean of a cost over all orders with its spread
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Statistic {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub ci95: f64, // Half-width of the 95% confidence interval of the mean (Student's t)
+}
+
+/// One algorithm's costs over all parent orders
+#[derive(Debug, Clone, Serialize)]
+pub struct TcaSummary {
+    pub algorithm: String,
+    pub fill_rate: f64,
+    pub implementation_shortfall: Statistic,
+    pub vs_arrival: Statistic,
+    pub vs_vwap: Statistic,
+    pub vs_twap: Statistic,
+    pub vs_close: Statistic,
+    pub timing_cost: Statistic,
+    pub participation_rate: f64,
+    pub schedule_deviation: f64,
+    pub shortfall_vs_baseline: Option<Statistic>, // Paired difference from the first algorithm's shortfall
+    pub costs: Vec<ExecutionCosts>, // Per parent order, in the order of `TcaResult::orders`
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TcaResult {
+    pub windows: usize,
+    pub orders: Vec<ParentOrder>,
+    pub summaries: Vec<TcaSummary>,
+}
+
+/// Transaction cost analysis of execution algorithms
+///
+/// Every algorithm works the same randomized parent orders, so their costs can be compared
+/// order by order: each summary after the first reports its shortfall minus the first
+/// algorithm's with a paired confidence interval, which cancels out the market moves the
+/// algorithms share.
+pub fn run_tca(
+    algorithms: &[(String, HashMap<String, f64>)],
+    data: &MarketData,
+    config: &BacktestConfig,
+    tca: &TcaConfig,
+) -> Result<TcaResult, Box<dyn Error>> {
+    if tca.orders == 0 {
+        return Err("TCA needs at least one order".into());
+    }
+    if !(0.0 < tca.min_participation && tca.min_participation <= tca.max_participation) {
+        return Err("Participation range must satisfy 0 < min <= max".into());
+    }
+
+    let candles = &data.candles;
+    let windows: Vec<Range<usize>> = trading_windows(candles, tca.window_bars)
+        .into_iter()
+        .filter(|w| candles[w.clone()].iter().any(|c| c.volume > 0.0))
+        .collect();
+    if windows.is_empty() {
+        return Err(format!("No window of {} bars with volume to trade in", tca.window_bars.max(2)).into());
+    }
+
+    let mut rng = StdRng::seed_from_u64(tca.seed);
+    let orders: Vec<ParentOrder> = (0..tca.orders)
+        .map(|_| {
+            let bars = windows[rng.gen_range(0..windows.len())].clone();
+            let volume: f64 = candles[bars.clone()].iter().map(|c| c.volume).sum();
+            ParentOrder {
+                start: candles[bars.start].timestamp,
+                end: candles[bars.end - 1].timestamp,
+                direction: if rng.gen_bool(0.5) { TradeDirection::Long } else { TradeDirection::Short },
+                size: volume * rng.gen_range(tca.min_participation..=tca.max_participation),
+                bars,
+            }
+        })
+        .collect();
+
+    let mut summaries: Vec<TcaSummary> = Vec::new();
+    for (name, params) in algorithms {
+        let algorithm = execution::create_execution_algorithm(name, params)?.name().to_string();
+        let costs: Result<Vec<ExecutionCosts>, String> = orders
+            .par_iter()
+            .map(|order| {
+                let worker = execution::create_execution_algorithm(name, params).map_err(|e| e.to_string())?;
+                let trades = worker
+                    .execute(data, order.size, order.direction, order.start, Some(order.end), config)
+                    .map_err(|e| e.to_string())?;
+                Ok(measure(&candles[order.bars.clone()], order, &trades))
+            })
+            .collect();
+        let costs = costs?;
+
+        let baseline = summaries.first().map(|first| {
+            let differences: Vec<f64> = costs.iter().zip(&first.costs)
+                .map(|(a, b)| a.implementation_shortfall - b.implementation_shortfall)
+                .collect();
+            statistic(&differences)
+        });
+        summaries.push(summarize(algorithm, costs, baseline));
+    }
+
+    Ok(TcaResult { windows: windows.len(), orders, summaries })
+}
+
+// Windows parent orders are worked over: each calendar day with at least two bars when the
+// data is intraday, otherwise every run of `window_bars` consecutive bars
+fn trading_windows(candles: &[Candle], window_bars: usize) -> Vec<Range<usize>> {
+    let intraday = candles.windows(2).any(|w| w[0].timestamp.date_naive() == w[1].timestamp.date_naive());
+    if !intraday {
+        let window_bars = window_bars.max(2);
+        return (0..(candles.len() + 1).saturating_sub(window_bars)).map(|start| start..start + window_bars).collect();
+    }
+
+    let mut windows = Vec::new();
+    let mut start = 0;
+    for end in 1..=candles.len() {
+        if end == candles.len() || candles[end].timestamp.date_naive() != candles[start].timestamp.date_naive() {
+            if end - start >= 2 {
+                windows.push(start..end);
+            }
+            start = end;
+        }
+    }
+    windows
+}
+
+// Costs of `trades` working `order` over the window's `candles`
+fn measure(candles: &[Candle], order: &ParentOrder, trades: &[Trade]) -> ExecutionCosts {
+    let side = order.direction.sign();
+    let typical = |c: &Candle| (c.high + c.low + c.close) / 3.0;
+    let arrival = candles[0].open;
+    let close = candles[candles.len() - 1].close;
+    let volume: f64 = candles.iter().map(|c| c.volume).sum();
+    let vwap = candles.iter().map(|c| typical(c) * c.volume).sum::<f64>() / volume;
+    let twap = candles.iter().map(typical).sum::<f64>() / candles.len() as f64;
+
+    let executed: f64 = trades.iter().map(|t| t.size).sum();
+    let fees: f64 = trades.iter().map(|t| t.costs).sum();
+    let average_price = if executed > 0.0 { trades.iter().map(|t| t.price * t.size).sum::<f64>() / executed } else { arrival };
+    let against = |benchmark: f64| side * (average_price / benchmark - 1.0) * BPS;
+
+    // Shortfall of the whole parent order: fills against arrival, fees, and what is left
+    // unfilled valued at the close
+    let unfilled = (order.size - executed).max(0.0);
+    let shortfall = side * trades.iter().map(|t| t.size * (t.price - arrival)).sum::<f64>()
+        + side * unfilled * (close - arrival)
+        + fees;
+
+    // Fill-weighted open of the bars that filled, i.e. the price before the fills' own impact
+    let opens: HashMap<DateTime<Utc>, f64> = candles.iter().map(|c| (c.timestamp, c.open)).collect();
+    let reached = trades.iter().map(|t| t.size * opens.get(&t.timestamp).copied().unwrap_or(t.price)).sum::<f64>();
+    let timing_cost = if executed > 0.0 { side * (reached / executed / arrival - 1.0) * BPS } else { 0.0 };
+
+    let filled_bars: Vec<usize> = candles.iter()
+        .enumerate()
+        .filter(|(_, c)| trades.iter().any(|t| t.timestamp == c.timestamp))
+        .map(|(i, _)| i)
+        .collect();
+    let participation_rate = match (filled_bars.first(), filled_bars.last()) {
+        (Some(&first), Some(&last)) => {
+            let market: f64 = candles[first..=last].iter().map(|c| c.volume).sum();
+            if market > 0.0 { executed / market } else { 0.0 }
+        }
+        _ => 0.0,
+    };
+
+    // After each bar, compare the filled fraction of the order with the fraction of the
+    // window's volume traded so far
+    let (mut filled, mut traded, mut deviation) = (0.0, 0.0, 0.0);
+    for candle in candles {
+        filled += trades.iter().filter(|t| t.timestamp == candle.timestamp).map(|t| t.size).sum::<f64>();
+        traded += candle.volume;
+        deviation += (filled / order.size - traded / volume).abs();
+    }
+
+    ExecutionCosts {
+        fill_rate: executed / order.size,
+        average_price,
+        implementation_shortfall: shortfall / (order.size * arrival) * BPS,
+        vs_arrival: against(arrival),
+        vs_vwap: against(vwap),
+        vs_twap: against(twap),
+        vs_close: against(close),
+        timing_cost,
+        participation_rate,
+        schedule_deviation: deviation / candles.len() as f64,
+    }
+}
+
+fn summarize(algorithm: String, costs: Vec<ExecutionCosts>, shortfall_vs_baseline: Option<Statistic>) -> TcaSummary {
+    let of = |field: fn(&ExecutionCosts) -> f64| statistic(&costs.iter().map(field).collect::<Vec<_>>());
+
+    TcaSummary {
+        algorithm,
+        fill_rate: of(|c| c.fill_rate).mean,
+        implementation_shortfall: of(|c| c.implementation_shortfall),
+        vs_arrival: of(|c| c.vs_arrival),
+        vs_vwap: of(|c| c.vs_vwap),
+        vs_twap: of(|c| c.vs_twap),
+        vs_close: of(|c| c.vs_close),
+        timing_cost: of(|c| c.timing_cost),
+        participation_rate: of(|c| c.participation_rate).mean,
+        schedule_deviation: of(|c| c.schedule_deviation).mean,
+        shortfall_vs_baseline,
+        costs,
+    }
+}
+
+// Mean, sample standard deviation and 95% confidence half-width of the mean
+fn statistic(values: &[f64]) -> Statistic {
+    let n = values.len();
+    if n == 0 {
+        return Statistic::default();
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    if n < 2 {
+        return Statistic { mean, ..Statistic::default() };
+    }
+
+    let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt();
+    let t = StudentsT::new(0.0, 1.0, (n - 1) as f64).map_or(1.96, |t| t.inverse_cdf(0.975));
+    Statistic { mean, std_dev, ci95: t * std_dev / (n as f64).sqrt() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::{bar, day};
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    // Typical prices of 100 on 100 units, then 103 on 300 units
+    fn window() -> Vec<Candle> {
+        vec![
+            bar(0, 100.0, 102.0, 98.0, 100.0),
+            Candle { volume: 300.0, ..bar(1, 101.0, 106.0, 100.0, 103.0) },
+        ]
+    }
+
+    // 8 of 10 units filled at an average of 102.5, paying 0.2 in fees
+    fn measured(direction: TradeDirection) -> ExecutionCosts {
+        let order = ParentOrder { start: day(0), end: day(1), direction, size: 10.0, bars: 0..2 };
+        let trade = |n: i64, price: f64| Trade { timestamp: day(n), symbol: "TEST".to_string(), direction, price, size: 4.0, costs: 0.1 };
+        measure(&window(), &order, &[trade(0, 101.0), trade(1, 104.0)])
+    }
+
+    #[test]
+    fn buys_pay_above_the_benchmarks() {
+        let costs = measured(TradeDirection::Long);
+
+        assert_close(costs.fill_rate, 0.8);
+        assert_close(costs.average_price, 102.5);
+        assert_close(costs.vs_arrival, 250.0);
+        assert_close(costs.vs_vwap, (102.5 / 102.25 - 1.0) * BPS);
+        assert_close(costs.vs_twap, (102.5 / 101.5 - 1.0) * BPS);
+        assert!(costs.vs_close < 0.0, "bought below the close");
+        // 4 * 1 + 4 * 4 from the fills, 2 * 3 for the rest at the close and 0.2 in fees
+        assert_close(costs.implementation_shortfall, 26.2 / 1_000.0 * BPS);
+        // The second fill's bar opened 1 above arrival
+        assert_close(costs.timing_cost, 50.0);
+        assert_close(costs.participation_rate, 8.0 / 400.0);
+    }
+
+    #[test]
+    fn sells_pay_below_the_benchmarks() {
+        let buy = measured(TradeDirection::Long);
+        let sell = measured(TradeDirection::Short);
+
+        assert_close(sell.vs_arrival, -buy.vs_arrival);
+        assert_close(sell.vs_vwap, -buy.vs_vwap);
+        assert_close(sell.vs_twap, -buy.vs_twap);
+        assert_close(sell.vs_close, -buy.vs_close);
+        assert_close(sell.timing_cost, -buy.timing_cost);
+        // Fees cost both sides
+        assert_close(sell.implementation_shortfall, (-26.0 + 0.2) / 1_000.0 * BPS);
+    }
+
+    #[test]
+    fn confidence_interval_uses_students_t() {
+        // t with 4 degrees of freedom at 97.5% is 2.776445
+        let stat = statistic(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_close(stat.mean, 3.0);
+        assert_close(stat.std_dev, 2.5_f64.sqrt());
+        assert!((stat.ci95 - 2.776445 * 2.5_f64.sqrt() / 5.0_f64.sqrt()).abs() < 1e-5, "{}", stat.ci95);
+
+        // and 12.7062 with one, here times a standard error of 1
+        let stat = statistic(&[0.0, 2.0]);
+        assert!((stat.ci95 - 12.7062).abs() < 1e-3, "{}", stat.ci95);
+
+        let stat = statistic(&[7.0]);
+        assert_eq!((stat.mean, stat.std_dev, stat.ci95), (7.0, 0.0, 0.0));
+    }
+}
@@ -2,154 +2,6 @@ use plotters::prelude::*;
 use std::error::Error;
 use std::path::Path;
 
-// Technical indicators frequently used in trading
-pub mod indicators {
-    use crate::models::Candle;
-    
-    // Exponential Moving Average (EMA)
-    pub fn calculate_ema(candles: &[Candle], period: usize, index: usize) -> Option<f64> {
-        if index < period - 1 || candles.len() <= index {
-            return None;
-        }
-        
-        if index == period - 1 {
-            // First EMA is the SMA
-            let sum: f64 = candles[0..period]
-                .iter()
-                .map(|candle| candle.close)
-                .sum();
-            return Some(sum / period as f64);
-        }
-        
-        // EMA = Price(t) * k + EMA(y) * (1 - k)
-        // where k = 2 / (period + 1)
-        let k = 2.0 / (period as f64 + 1.0);
-        let prev_ema = calculate_ema(candles, period, index - 1).unwrap();
-        let ema = candles[index].close * k + prev_ema * (1.0 - k);
-        
-        Some(ema)
-    }
-    
-    // Relative Strength Index (RSI)
-    pub fn calculate_rsi(candles: &[Candle], period: usize, index: usize) -> Option<f64> {
-        if index < period || candles.len() <= index {
-            return None;
-        }
-        
-        let mut gains = 0.0;
-        let mut losses = 0.0;
-        
-        // Calculate average gains and losses
-        for i in (index - period + 1)..=index {
-            let change = candles[i].close - candles[i - 1].close;
-            if change >= 0.0 {
-                gains += change;
-            } else {
-                losses -= change; // Convert to positive
-            }
-        }
-        
-        let avg_gain = gains / period as f64;
-        let avg_loss = losses / period as f64;
-        
-        // Calculate RSI
-        if avg_loss == 0.0 {
-            return Some(100.0);
-        }
-        
-        let rs = avg_gain / avg_loss;
-        let rsi = 100.0 - (100.0 / (1.0 + rs));
-        
-        Some(rsi)
-    }
-    
-    // Bollinger Bands
-    pub fn calculate_bollinger_bands(candles: &[Candle], period: usize, num_std_dev: f64, index: usize) -> Option<(f64, f64, f64)> {
-        if index < period - 1 || candles.len() <= index {
-            return None;
-        }
-        
-        // Calculate Simple Moving Average
-        let sum: f64 = candles[index - period + 1..=index]
-            .iter()
-            .map(|candle| candle.close)
-            .sum();
-        let sma = sum / period as f64;
-        
-        // Calculate Standard Deviation
-        let variance: f64 = candles[index - period + 1..=index]
-            .iter()
-            .map(|candle| (candle.close - sma).powi(2))
-            .sum::<f64>() / period as f64;
-        let std_dev = variance.sqrt();
-        
-        // Calculate Bollinger Bands
-        let upper_band = sma + (std_dev * num_std_dev);
-        let lower_band = sma - (std_dev * num_std_dev);
-        
-        Some((sma, upper_band, lower_band))
-    }
-    
-    // Moving Average Convergence Divergence (MACD)
-    pub fn calculate_macd(candles: &[Candle], fast_period: usize, slow_period: usize, signal_period: usize, index: usize) -> Option<(f64, f64, f64)> {
-        if index < slow_period + signal_period - 2 || candles.len() <= index {
-            return None;
-        }
-        
-        // Calculate MACD line
-        let fast_ema = calculate_ema(candles, fast_period, index)?;
-        let slow_ema = calculate_ema(candles, slow_period, index)?;
-        let macd_line = fast_ema - slow_ema;
-        
-        // Calculate signal line (EMA of MACD line)
-        // For this simplified implementation, we'll manually calculate the signal line
-        let mut macd_values = Vec::with_capacity(signal_period);
-        for i in (index - signal_period + 1)..=index {
-            if let (Some(fast), Some(slow)) = (calculate_ema(candles, fast_period, i), calculate_ema(candles, slow_period, i)) {
-                macd_values.push(fast - slow);
-            }
-        }
-        
-        // Calculate EMA of MACD values for signal line
-        let signal_line = macd_values.iter().sum::<f64>() / signal_period as f64;
-        
-        // MACD histogram
-        let histogram = macd_line - signal_line;
-        
-        Some((macd_line, signal_line, histogram))
-    }
-    
-    // Average True Range (ATR)
-    pub fn calculate_atr(candles: &[Candle], period: usize, index: usize) -> Option<f64> {
-        if index < period || candles.len() <= index {
-            return None;
-        }
-        
-        let mut tr_sum = 0.0;
-        
-        // Calculate True Range for last 'period' candles
-        for i in (index - period + 1)..=index {
-            let high = candles[i].high;
-            let low = candles[i].low;
-            let prev_close = candles[i - 1].close;
-            
-            // True Range is the greatest of the following:
-            // 1. Current High - Current Low
-            // 2. |Current High - Previous Close|
-            // 3. |Current Low - Previous Close|
-            let tr1 = high - low;
-            let tr2 = (high - prev_close).abs();
-            let tr3 = (low - prev_close).abs();
-            
-            let true_range = tr1.max(tr2).max(tr3);
-            tr_sum += true_range;
-        }
-        
-        // ATR is the average of True Range values
-        Some(tr_sum / period as f64)
-    }
-}
-
 // Risk management functions
 pub mod risk {
     use crate::models::TradeDirection;
@@ -186,6 +38,44 @@ pub mod risk {
     }
 }
 
+// Descriptive statistics over return series
+pub mod statistics {
+    // Pearson correlation of two equally long series (0.0 when either is constant)
+    pub fn correlation(a: &[f64], b: &[f64]) -> f64 {
+        let n = a.len().min(b.len());
+        if n < 2 {
+            return 0.0;
+        }
+        
+        let mean_a = a[..n].iter().sum::<f64>() / n as f64;
+        let mean_b = b[..n].iter().sum::<f64>() / n as f64;
+        
+        let mut covariance = 0.0;
+        let mut variance_a = 0.0;
+        let mut variance_b = 0.0;
+        for i in 0..n {
+            let da = a[i] - mean_a;
+            let db = b[i] - mean_b;
+            covariance += da * db;
+            variance_a += da * da;
+            variance_b += db * db;
+        }
+        
+        if variance_a == 0.0 || variance_b == 0.0 {
+            return 0.0;
+        }
+        
+        covariance / (variance_a * variance_b).sqrt()
+    }
+    
+    // Pairwise correlations between every pair of series
+    pub fn correlation_matrix(series: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        series.iter()
+            .map(|a| series.iter().map(|b| correlation(a, b)).collect())
+            .collect()
+    }
+}
+
 // Visualization utilities for backtesting results
 pub fn plot_equity_curve<P: AsRef<Path>>(
     equity_curve: &[(chrono::DateTime<chrono::Utc>, f64)],
@@ -194,8 +84,8 @@ pub fn plot_equity_curve<P: AsRef<Path>>(
     let root = BitMapBackend::new(output_path.as_ref(), (800, 600)).into_drawing_area();
     root.fill(&WHITE)?;
     
-    let min_date = equity_curve.first().map(|p| p.0).unwrap_or_else(|| chrono::Utc::now());
-    let max_date = equity_curve.last().map(|p| p.0).unwrap_or_else(|| chrono::Utc::now());
+    let min_date = equity_curve.first().map(|p| p.0).unwrap_or_else(chrono::Utc::now);
+    let max_date = equity_curve.last().map(|p| p.0).unwrap_or_else(chrono::Utc::now);
     
     let min_equity = equity_curve
         .iter()
@@ -0,0 +1,157 @@
+use crate::backtest;
+use crate::config::BacktestConfig;
+use crate::models::{BacktestResult, MarketData};
+use crate::optimizer::{self, Objective, ParamRange};
+use crate::portfolio::Portfolio;
+use crate::strategies::{self, StrategyConfig};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Window lengths (in bars) for a rolling walk-forward run
+#[derive(Debug, Clone)]
+pub struct WalkForwardConfig {
+    pub in_sample_bars: usize,
+    pub out_of_sample_bars: usize,
+    pub objective: Objective,
+}
+
+/// One optimize-then-test step
+#[derive(Debug, Clone, Serialize)]
+pub struct WalkForwardWindow {
+    pub in_sample_start: DateTime<Utc>,
+    pub in_sample_end: DateTime<Utc>,
+    pub out_of_sample_start: DateTime<Utc>,
+    pub out_of_sample_end: DateTime<Utc>,
+    pub params: BTreeMap<String, f64>,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalkForwardResult {
+    pub windows: Vec<WalkForwardWindow>,
+    /// Out-of-sample segments stitched into a single backtest
+    pub result: BacktestResult,
+    /// Mean out-of-sample score over mean in-sample score
+    pub efficiency: f64,
+}
+
+/// Rolling walk-forward analysis
+///
+/// Parameters are grid-searched on each in-sample window and the winner is traded on the
+/// out-of-sample window that follows it. The in-sample bars are replayed to the strategy
+/// as warm-up only. Each out-of-sample segment starts with the equity the previous one
+/// ended with, so the stitched equity curve is continuous.
+pub fn run_walk_forward(
+    strategy: &StrategyConfig,
+    ranges: &[ParamRange],
+    data: &MarketData,
+    config: &BacktestConfig,
+    wf: &WalkForwardConfig,
+) -> Result<WalkForwardResult, Box<dyn Error>> {
+    let candles = &data.candles;
+    if wf.in_sample_bars == 0 || wf.out_of_sample_bars == 0 {
+        return Err("Walk-forward windows must be at least one bar long".into());
+    }
+    if candles.len() <= wf.in_sample_bars {
+        return Err(format!(
+            "Need more than {} bars for walk-forward analysis, got {}",
+            wf.in_sample_bars,
+            candles.len()
+        ).into());
+    }
+
+    let mut windows = Vec::new();
+    let mut segments = Vec::new();
+    let mut capital = config.initial_capital;
+    let mut start = 0;
+
+    while start + wf.in_sample_bars < candles.len() {
+        let in_sample_end = start + wf.in_sample_bars;
+        let out_of_sample_end = (in_sample_end + wf.out_of_sample_bars).min(candles.len());
+
+        // Optimize on the in-sample window only
+        let in_sample = data.slice(start..in_sample_end);
+        let ranked = optimizer::grid_search(strategy, ranges, &in_sample, config, wf.objective)?;
+        let Some(best) = ranked.first() else {
+            break;
+        };
+
+        // Trade the winner out of sample, warmed up on the in-sample bars
+        let window = data.slice(start..out_of_sample_end);
+        let segment_config = BacktestConfig {
+            initial_capital: capital,
+            ..config.clone()
+        };
+        let mut params = strategy.params.clone();
+        params.extend(best.params.iter().map(|(k, v)| (k.clone(), *v)));
+        let mut run_strategy = strategies::create_strategy_with_params(&strategy.name, &params)?;
+        let segment = backtest::run_strategy_from(run_strategy.as_mut(), &window, &segment_config, wf.in_sample_bars);
+
+        capital = segment.equity_curve.last().map(|p| p.1).unwrap_or(capital);
+        windows.push(WalkForwardWindow {
+            in_sample_start: candles[start].timestamp,
+            in_sample_end: candles[in_sample_end - 1].timestamp,
+            out_of_sample_start: candles[in_sample_end].timestamp,
+            out_of_sample_end: candles[out_of_sample_end - 1].timestamp,
+            params: best.params.clone(),
+            in_sample_score: best.score,
+            out_of_sample_score: wf.objective.score(&segment),
+        });
+        segments.push(segment);
+
+        start += wf.out_of_sample_bars;
+    }
+
+    let mean = |scores: Vec<f64>| scores.iter().sum::<f64>() / scores.len().max(1) as f64;
+    let in_sample_mean = mean(windows.iter().map(|w| w.in_sample_score).collect());
+    let out_of_sample_mean = mean(windows.iter().map(|w| w.out_of_sample_score).collect());
+    let efficiency = if in_sample_mean != 0.0 { out_of_sample_mean / in_sample_mean } else { 0.0 };
+
+    let mut result = stitch(&format!("WalkForward_{}", strategy.name), segments, data, config.initial_capital);
+    result.metrics.insert("walk_forward_efficiency".to_string(), efficiency);
+
+    Ok(WalkForwardResult { windows, result, efficiency })
+}
+
+// Concatenate consecutive backtest segments into one result, benchmarked against `data`
+fn stitch(strategy_name: &str, segments: Vec<BacktestResult>, data: &MarketData, initial_capital: f64) -> BacktestResult {
+    let mut ledger = Portfolio::new(initial_capital);
+    let mut equity_curve = Vec::new();
+
+    for segment in segments {
+        ledger.realized_pnl += segment.metrics.get("realized_pnl").copied().unwrap_or(0.0);
+        ledger.total_costs += segment.metrics.get("total_costs").copied().unwrap_or(0.0);
+        ledger.trades.extend(segment.trades);
+        ledger.closed_positions.extend(segment.positions);
+        equity_curve.extend(segment.equity_curve);
+    }
+    ledger.cash = equity_curve.last().map(|p| p.1).unwrap_or(initial_capital);
+
+    let mut result = backtest::build_result(strategy_name, ledger, equity_curve);
+    // Buy-and-hold of the traded symbol unless the caller sets another benchmark
+    backtest::set_benchmark(&mut result, data);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic::{self, SyntheticConfig};
+    use std::collections::HashMap;
+
+    #[test]
+    fn stitched_result_is_benchmarked_against_the_traded_symbol() {
+        let data = synthetic::generate("BTC/USD", "2023-01-01", "2023-12-31", &SyntheticConfig::default()).unwrap();
+        let strategy = StrategyConfig { name: "moving_average_crossover".to_string(), params: HashMap::new() };
+        let ranges: Vec<ParamRange> = vec!["fast_period=5:15:5".parse().unwrap()];
+        let wf = WalkForwardConfig { in_sample_bars: 120, out_of_sample_bars: 60, objective: Objective::Sharpe };
+
+        let result = run_walk_forward(&strategy, &ranges, &data, &BacktestConfig::default(), &wf).unwrap();
+        let benchmark = result.result.analytics.benchmark.expect("walk-forward result has no benchmark");
+        assert_eq!(benchmark.symbol, "BTC/USD");
+        assert!(benchmark.beta.is_finite() && benchmark.correlation.is_finite());
+    }
+}